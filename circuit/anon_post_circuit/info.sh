@@ -0,0 +1,2 @@
+echo "Show the size of the ZK circuit..."
+bb gates -b target/verified_anonymous_sns_anon_post.json | grep "circuit"
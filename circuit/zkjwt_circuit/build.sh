@@ -34,6 +34,9 @@ mkdir -p "target/vk"
 echo "Copying verified_anonymous_sns_jwt.json and paste to the ./circuit directory..."
 cp target/verified_anonymous_sns_jwt.json "../verified_anonymous_sns_jwt.json"
 
+echo "Check the ABI of verified_anonymous_sns_jwt.json against the JWT inputs of the prover (public/jwt_input.json)..."
+(cd ../.. && cargo test --lib test_jwt_circuit_artifact_matches_inputs) || exit 1
+
 echo "Generating vkey in the ./target/vk directory..."
 bb write_vk -b ./target/verified_anonymous_sns_jwt.json -o ./target/vk --oracle_hash keccak
 
@@ -49,4 +52,7 @@ cp ./target/Verifier.sol ../../contracts/src/circuits/zk-jwt/honk-verifier
 echo "Rename the Verifier.sol with the honk_vk.sol in the ./contracts/circuit/ultra-verifier directory"
 mv ../../contracts/src/circuits/zk-jwt/honk-verifier/Verifier.sol ../../contracts/src/circuits/zk-jwt/honk-verifier/honk_vk.sol
 
+echo "Check the verification key of the honk_vk.sol against the circuit..."
+(cd ../.. && cargo run --bin solidity_verifier -- check circuit/verified_anonymous_sns_jwt.json contracts/src/circuits/zk-jwt/honk-verifier/honk_vk.sol) || exit 1
+
 echo "Done"
@@ -0,0 +1,24 @@
+# Extract version from Nargo.toml
+VERSION=$(grep '^version = ' Nargo.toml | cut -d '"' -f 2)
+echo "Circuit version: $VERSION"
+
+rm -rf target
+
+# Align with the Noir/Nargo (1.0.0-beta.6) and bb.js (0.85.0) versions used for the JWT circuit
+echo "Check the Noir/Nargo version and bb.js version of the local machine (This version is supposed to be v1.0.0-beta.6 / v0.85.0)..."
+nargo -V
+bb -V
+
+echo "Compiling circuit..."
+if ! nargo compile; then
+    echo "Compilation failed. Exiting..."
+    exit 1
+fi
+
+echo "Gate count:"
+bb gates -b target/verified_anonymous_sns_jwt_hd.json | jq  '.functions[0].circuit_size'
+
+echo "Copying verified_anonymous_sns_jwt_hd.json and paste to the ./circuit directory..."
+cp target/verified_anonymous_sns_jwt_hd.json "../verified_anonymous_sns_jwt_hd.json"
+
+echo "Done"
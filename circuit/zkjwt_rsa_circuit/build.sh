@@ -0,0 +1,36 @@
+# Extract version from Nargo.toml
+VERSION=$(grep '^version = ' zkjwt_rsa/Nargo.toml | cut -d '"' -f 2)
+echo "Circuit version: $VERSION"
+
+rm -rf target
+
+# Install Noir/Nargo
+echo "Install the Noir/Nargo v1.0.0-beta.6..."
+noirup --version 1.0.0-beta.6
+
+# Align the Noir/Nargo version (1.0.0-beta.6) and bb.js version (>= 0.85.0) of the local machine.
+echo "Install the bb.js version v0.85.0..."
+bbup --version 0.85.0
+
+echo "Check the Noir/Nargo version and bb.js version of the local machine (This version is supposed to be v1.0.0-beta.6 / v0.85.0)..."
+nargo -V
+bb -V
+
+echo "Compiling circuits..."
+if ! nargo compile --workspace; then
+    echo "Compilation failed. Exiting..."
+    exit 1
+fi
+
+# RS256 with a 2048-bit key is `circuit/zkjwt_circuit`
+for VARIANT in rs256_3072 rs256_4096 rs384_2048 rs384_3072 rs384_4096 rs512_2048 rs512_3072 rs512_4096; do
+    CIRCUIT="verified_anonymous_sns_jwt_${VARIANT}"
+
+    echo "Gate count of ${CIRCUIT}:"
+    bb gates -b target/${CIRCUIT}.json | jq  '.functions[0].circuit_size'
+
+    echo "Copying ${CIRCUIT}.json and paste to the ./circuit directory..."
+    cp target/${CIRCUIT}.json "../${CIRCUIT}.json"
+done
+
+echo "Done"
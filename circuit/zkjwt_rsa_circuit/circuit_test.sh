@@ -0,0 +1,2 @@
+echo "Run the test of ZK circuits"
+nargo test --workspace --show-output
@@ -0,0 +1,5 @@
+echo "Show the size of the ZK circuits..."
+for VARIANT in rs256_3072 rs256_4096 rs384_2048 rs384_3072 rs384_4096 rs512_2048 rs512_3072 rs512_4096; do
+    echo "verified_anonymous_sns_jwt_${VARIANT}:"
+    bb gates -b target/verified_anonymous_sns_jwt_${VARIANT}.json | grep "circuit"
+done
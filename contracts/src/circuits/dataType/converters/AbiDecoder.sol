@@ -1,5 +1,67 @@
 pragma solidity >=0.8.21;
 
+import { DataType } from "../DataType.sol";
+import { NUMBER_OF_PUBLIC_INPUTS, PAIRING_POINT_OBJECT_LENGTH } from "../../zk-jwt/honk-verifier/honk_vk.sol";
+
+/**
+ * @notice - The AbiDecoder library decodes the values of the publicInputs of a ZK-JWT proof (= bytes32[] publicInputs, which is passed to the HonkVerifier).
+ * @dev - The layout of the publicInputs (= the public inputs of the circuit/zkjwt_circuit, then its return value):
+ *        - MODULUS_LIMBS fields: JWT pubkey modulus limbs (120 bits each, i.e. 18 for a 2048-bit RSA key)
+ *        - 64 fields: domain bytes (zero padded)
+ *        - 1 field: domain length
+ *        - 1 field: ephemeral pubkey (shifted)
+ *        - 1 field: ephemeral pubkey expiry
+ *        - 129 fields: iss bytes and length
+ *        - 129 fields: aud bytes and length
+ *        - 1 field: iat
+ *        - 1 field: nullifier (return value)
+ * @dev - The number of publicInputs is the one of the HonkVerifier (honk_vk.sol) without its pairing point object, so the
+ *        MODULUS_LIMBS follow the key size the verifier is generated for (circuit/zkjwt_circuit/build.sh).
+ */
 library AbiDecoder {
-    // @dev - [TODO]:
-}
\ No newline at end of file
+    uint256 constant MAX_DOMAIN_LENGTH = 64;
+    uint256 constant CLAIM_PUBLIC_INPUTS_LENGTH = (MAX_DOMAIN_LENGTH + 1) + 1 + 1 + (128 + 1) + (128 + 1) + 1 + 1;
+    uint256 constant PUBLIC_INPUTS_LENGTH = NUMBER_OF_PUBLIC_INPUTS - PAIRING_POINT_OBJECT_LENGTH;
+    uint256 constant MODULUS_LIMBS = PUBLIC_INPUTS_LENGTH - CLAIM_PUBLIC_INPUTS_LENGTH;
+    uint256 constant DOMAIN_OFFSET = MODULUS_LIMBS;
+    uint256 constant DOMAIN_LENGTH_INDEX = DOMAIN_OFFSET + MAX_DOMAIN_LENGTH;
+
+    /**
+     * @notice - Decode the domain from the domain bytes and the domain length of a given publicInputs.
+     */
+    function decodeDomain(bytes32[] calldata publicInputs) internal pure returns (string memory) {
+        require(publicInputs.length == PUBLIC_INPUTS_LENGTH, "A given publicInputs has an invalid length");
+
+        uint256 domainLength = uint256(publicInputs[DOMAIN_LENGTH_INDEX]);
+        require(domainLength <= MAX_DOMAIN_LENGTH, "A given publicInputs has an invalid domain length");
+
+        bytes memory domain = new bytes(domainLength);
+        for (uint256 i = 0; i < domainLength; i++) {
+            uint256 domainByte = uint256(publicInputs[DOMAIN_OFFSET + i]);
+            require(domainByte < 256, "A given publicInputs has an invalid domain byte");
+            domain[i] = bytes1(uint8(domainByte));
+        }
+        return string(domain);
+    }
+
+    /**
+     * @notice - Decode the nullifier, which is returned by the circuit and is therefore the last field of a given publicInputs.
+     */
+    function decodeNullifierHash(bytes32[] calldata publicInputs) internal pure returns (bytes32) {
+        require(publicInputs.length == PUBLIC_INPUTS_LENGTH, "A given publicInputs has an invalid length");
+        return publicInputs[PUBLIC_INPUTS_LENGTH - 1];
+    }
+
+    /**
+     * @notice - Decode the PublicInput struct from a given publicInputs.
+     * @dev - The createdAt is not part of the publicInputs, so a given createdAt is stored as it is.
+     */
+    function decodePublicInput(
+        bytes32[] calldata publicInputs,
+        string memory createdAt
+    ) internal pure returns (DataType.PublicInput memory publicInput) {
+        publicInput.domain = decodeDomain(publicInputs);
+        publicInput.nullifierHash = decodeNullifierHash(publicInputs);
+        publicInput.createdAt = createdAt;
+    }
+}
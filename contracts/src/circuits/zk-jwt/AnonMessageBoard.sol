@@ -0,0 +1,46 @@
+pragma solidity >=0.8.21;
+
+import { ZkJwtProofManager } from "./ZkJwtProofManager.sol";
+import { DataType } from "../dataType/DataType.sol";
+
+/**
+ * @notice - The AnonMessageBoard contract is used to post messages anonymously as a member of a domain, which is proven by a ZK-JWT proof recorded in the ZkJwtProofManager.
+ * @dev - Only the holder of a recorded nullifierHash (= the account, which committed to it and recorded it in the ZkJwtProofManager) can post messages with it.
+ *        Neither the calldata nor the event of a post carries the nullifierHash, so that the posts cannot be linked by it. 
+ *        The posts of a holder still share its account, so that a member, who does not want its posts to be linked, records a proof with a fresh account per post.
+ */
+contract AnonMessageBoard {
+    ZkJwtProofManager public zkJwtProofManager;
+
+    uint256 public messageCount;
+
+    // @dev - The domainHash is the keccak256 hash of the domain, so that the messages of a domain can be filtered by the topic
+    event MessagePosted(
+        uint256 indexed messageId,
+        bytes32 indexed domainHash,
+        string domain,
+        string text,
+        uint256 timestamp
+    );
+
+    constructor(
+        ZkJwtProofManager _zkJwtProofManager
+    ) {
+        zkJwtProofManager = _zkJwtProofManager;
+    }
+
+    /**
+     * @notice - Post a message as the member of the domain of the nullifierHash held by the sender.
+     */
+    function postMessage(string calldata text) public returns (uint256 messageId) {
+        bytes32 nullifierHash = zkJwtProofManager.nullifiersOfRecorders(msg.sender);
+        require(nullifierHash != bytes32(0), "A given nullifierHash is not recorded by the sender");
+        require(bytes(text).length > 0, "A given text is empty");
+
+        // @dev - The domain was decoded from the publicInputs of the proof of a given nullifierHash
+        DataType.PublicInput memory publicInput = zkJwtProofManager.getPublicInputsOfZkJwtProof(nullifierHash);
+
+        messageId = ++messageCount;
+        emit MessagePosted(messageId, keccak256(bytes(publicInput.domain)), publicInput.domain, text, block.timestamp);
+    }
+}
@@ -2,6 +2,7 @@ pragma solidity >=0.8.21;
 
 import { ZkJwtProofVerifier } from "./ZkJwtProofVerifier.sol";
 import { DataType } from "../dataType/DataType.sol";
+import { AbiDecoder } from "../dataType/converters/AbiDecoder.sol";
 
 /**
  * @notice - This contract is used to manage the position and salary proof (1024-bit DKIM signature) with its publicInputs.
@@ -14,6 +15,9 @@ contract ZkJwtProofManager {
     // @dev - Storages
     mapping(bytes32 nullifierHash => DataType.PublicInput) public publicInputsOfZkJwtProofs;  // nullifierHash -> PublicInput    
     mapping(bytes32 nullifierHash => bool isNullified) public nullifiers;
+    mapping(bytes32 nullifierHash => address recorder) public recordersOfNullifiers;  // @dev - The account, which recorded a given nullifierHash, is its holder (i.e. on the AnonMessageBoard)
+    mapping(address recorder => bytes32 nullifierHash) public nullifiersOfRecorders;  // @dev - An account holds a single nullifierHash, so that the AnonMessageBoard does not need it in its calldata
+    mapping(bytes32 commitment => uint256 blockNumber) public commitmentsOfProofs;   // @dev - proofCommitment(nullifierHash, recorder) -> The block, in which it is committed
     DataType.PublicInput[] public publicInputsOfAllProofs;  // The publicInputs of all ZK-JWT proofs to show the list of all proofs related data on FE (front-end).
 
     constructor(
@@ -22,34 +26,56 @@ contract ZkJwtProofManager {
         zkJwtProofVerifier = _zkJwtProofVerifier;
     }
 
+    /**
+     * @notice - The commitment of a recorder to the nullifierHash of a ZK-JWT proof, which it records afterwards.
+     */
+    function proofCommitment(bytes32 nullifierHash, address recorder) public pure returns (bytes32) {
+        return keccak256(abi.encode(nullifierHash, recorder));
+    }
+
+    /**
+     * @notice - Commit to the nullifierHash of a ZK-JWT proof before recording it.
+     * @dev - The commitment hides the nullifierHash until the proof is recorded in a later block. An account, which sees the proof in the mempool, 
+     *        has not committed to its nullifierHash in an earlier block, so that it cannot front-run the recording and become the holder of it.
+     */
+    function commitToProof(bytes32 commitment) public {
+        require(commitmentsOfProofs[commitment] == 0, "A given commitment is already committed");
+        commitmentsOfProofs[commitment] = block.number;
+    }
+
     /**
      * @notice - Record the publicInputs of a given ZK-JWT proof on-chain.
+     * @dev - The sender must have committed to the nullifierHash of a given proof via the commitToProof() in an earlier block.
      */
     function recordPublicInputsOfZkJwtProof(
         bytes calldata proof, 
         bytes32[] calldata publicInputs,
-        DataType.PublicInput memory separatedPublicInputs // @dev - To avoid the "Stack too deep" error, a given publicInputs would be stored in the form of the struct data (= DataType.PublicInput)
+        DataType.PublicInput memory separatedPublicInputs // @dev - The domain and nullifierHash must match the ones decoded from a given publicInputs. (NOTE: The createdAt is stored as it is)
     ) public returns (bool) {
+        // @dev - Decode the domain and the nullifierHash from a given publicInputs, and check whether a given separatedPublicInputs matches them
+        DataType.PublicInput memory publicInput = AbiDecoder.decodePublicInput(publicInputs, separatedPublicInputs.createdAt);
+        require(keccak256(bytes(separatedPublicInputs.domain)) == keccak256(bytes(publicInput.domain)), "A given domain does not match the publicInputs of a given proof");
+        require(separatedPublicInputs.nullifierHash == publicInput.nullifierHash, "A given nullifierHash does not match the publicInputs of a given proof");
+
+        // @dev - Check whether the sender committed to the nullifierHash before a given proof was revealed
+        uint256 committedAt = commitmentsOfProofs[proofCommitment(publicInput.nullifierHash, msg.sender)];
+        require(committedAt != 0 && committedAt < block.number, "A given nullifierHash is not committed by the sender in an earlier block");
+
         // @dev - Verify a ZK-JWT proof
         bool result = zkJwtProofVerifier.verifyZkJwtProof(proof, publicInputs);
         require(result, "A given ZK-JWT proof is not valid");
 
-        // @dev - Record a publicInput of a given ZK-JWT proof
-        DataType.PublicInput memory publicInput;
-        //publicInput.jwtPubkeyModulusLimbs = separatedPublicInputs.jwtPubkeyModulusLimbs;
-        publicInput.domain = separatedPublicInputs.domain;
-        publicInput.nullifierHash = separatedPublicInputs.nullifierHash;
-        publicInput.createdAt = separatedPublicInputs.createdAt;
-        //publicInput.createdAt = block.timestamp;
-
         // @dev - Store the publicInput of a given ZK-JWT proof
         publicInputsOfZkJwtProofs[publicInput.nullifierHash] = publicInput;
 
         // @dev - Checking whether a given nullifierHash is already used or not for preventing from double spending of a given proof.
         require(nullifiers[publicInput.nullifierHash] == false, "A given nullifierHash is already used, which means a given proof is already used");
+        require(nullifiersOfRecorders[msg.sender] == bytes32(0), "The sender already holds a nullifierHash");
 
         // @dev - Store the nullifierHash to prevent double submission of the same email
         nullifiers[publicInput.nullifierHash] = true;
+        recordersOfNullifiers[publicInput.nullifierHash] = msg.sender;
+        nullifiersOfRecorders[msg.sender] = publicInput.nullifierHash;
 
         // @dev - Store the publicInputs into the list of all proofs to be displayed on the UI (front-end).
         publicInputsOfAllProofs.push(publicInput);
@@ -26,13 +26,13 @@ use mopro_bindings::{
     prove_jwt, // @dev - prove_jwt() is available directly from the root
     verify_jwt_proof,
     proof::jwt_proof::{
-        prepare_public_inputs,
-        pubkey_modulus_from_jwk,
         generate_inputs,
         verify_jwt, // @dev - verify_jwt() is in the proof::jwt_proof module
         JsonWebKey,
-        JWTCircuitInputs
+        JWTCircuitInputs,
+        RsaJwtKey,
     },
+    proof::solidity::SolidityProof,
 };
 use std::collections::HashMap;
 use num_bigint::BigUint;
@@ -98,7 +98,7 @@ pub async fn generate_proof() -> (Vec<u8>, Vec<FixedBytes<32>>) {
         id_token.to_string(),
         pubkey_str,
         domain.clone(),
-    );
+    ).unwrap();
     println!("🔄 Generated proof: {:?}", proof);
     println!("🔍 Proof details:");
     println!("  - Raw proof length: {} bytes", proof.len());
@@ -117,33 +117,16 @@ pub async fn generate_proof() -> (Vec<u8>, Vec<FixedBytes<32>>) {
         println!("  - Proof format (last 64 bytes): {}", alloy::hex::encode(&proof[proof.len()-64..]));
     }
 
-    // Convert parameters to the correct types for prepare_public_inputs
-    let google_jwt_pubkey_modulus = &pubkey.n;
-    let jwt_pubkey = pubkey_modulus_from_jwk(&google_jwt_pubkey_modulus).unwrap();
-    // let jwt_pubkey = {
-    //     use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
-    //     let modulus_bytes = BASE64_URL_SAFE_NO_PAD.decode(&pubkey.n).unwrap();
-    //     BigUint::from_bytes_be(&modulus_bytes)
-    // };
-    let ephemeral_pubkey_biguint = BigUint::from_str(&ephemeral_pubkey).unwrap();
-    let parsed_ephemeral_pubkey_expiry: DateTime<Utc> = ephemeral_expiry.parse::<DateTime<Utc>>().expect("Invalid datetime format");
-
-    // Extract public inputs from the proof
-    let public_inputs_vec = prepare_public_inputs(
-        jwt_pubkey,
-        domain,
-        ephemeral_pubkey_biguint,
-        parsed_ephemeral_pubkey_expiry,
-    );
-    println!("public_inputs_vec: {:?}\n", public_inputs_vec);
-
-    // Convert Vec<String> to Vec<FixedBytes<32>> for the contract call
-    let public_inputs: Vec<FixedBytes<32>> = public_inputs_vec
+    // Split the proof into the proof and public inputs (nullifier included) that the verifier expects
+    let key = RsaJwtKey::from_jwk(&pubkey).unwrap();
+    let solidity_proof = SolidityProof::from_rsa_jwt_proof(&proof, &key).unwrap();
+    println!("public_inputs_vec: {:?}\n", solidity_proof.public_inputs_hex());
+
+    // Convert the public inputs to Vec<FixedBytes<32>> for the contract call
+    let public_inputs: Vec<FixedBytes<32>> = solidity_proof
+        .public_inputs
         .iter()
-        .map(|s| {
-            let trimmed = s.trim_start_matches("0x");
-            FixedBytes::from_hex(trimmed).unwrap_or_else(|_| FixedBytes::ZERO)
-        })
+        .map(|field| FixedBytes::from(*field))
         .collect();
 
     println!("public_inputs: {:?}\n", public_inputs);
@@ -151,5 +134,5 @@ pub async fn generate_proof() -> (Vec<u8>, Vec<FixedBytes<32>>) {
     println!("✅ Proof generation test completed successfully");
 
     // Return the proof and public inputs
-    (proof, public_inputs)
+    (solidity_proof.proof, public_inputs)
 }
\ No newline at end of file
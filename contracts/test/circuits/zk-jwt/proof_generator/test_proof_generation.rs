@@ -74,7 +74,7 @@ async fn test_proof_generation() -> eyre::Result<()> {
         id_token.to_string(),
         pubkey_str,
         domain,
-    );
+    ).unwrap();
     println!("proof: {:?}", proof);
     assert!(!proof.is_empty(), "Proof should not be empty");
 
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Ok, Result};
+use chrono::{DateTime, Utc};
+
+use super::{
+    group_tree, rate_limit,
+    revocation::{sha256_digest, verify_signature},
+    storage, AnonymousMessage, IdentityCommitment, RateLimitConfig,
+};
+use crate::proof::{
+    anon_post::{message_field, topic_field, verify_anon_post_proof, AnonPostPublicInputs},
+    ephemeral_commitment::decimal_to_field,
+};
+
+impl IdentityCommitment {
+    pub fn signing_payload(&self) -> Vec<u8> {
+        sha256_digest(&format!(
+            "identity_{}_{}_{}",
+            self.pubkey, self.group_id, self.commitment
+        ))
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        verify_signature(&self.pubkey, &self.signing_payload(), &self.signature)
+    }
+}
+
+impl AnonymousMessage {
+    /// Public inputs the proof of the message is verified against.
+    pub fn public_inputs(&self, rate_limit: u32) -> Result<AnonPostPublicInputs> {
+        Ok(AnonPostPublicInputs {
+            merkle_root: decimal_to_field(&self.merkleRoot)?,
+            topic: topic_field(&self.topic),
+            message_hash: message_field(&self.message()),
+            epoch: self.epoch,
+            rate_limit,
+            nullifier: decimal_to_field(&self.nullifier)?,
+            rate_limit_nullifier: decimal_to_field(&self.rateLimitNullifier)?,
+            share_y: decimal_to_field(&self.shareY)?,
+        })
+    }
+}
+
+fn load_nullifiers(path: &str) -> Result<HashMap<String, String>> {
+    storage::load_json(path, "post_nullifiers.json")
+}
+
+fn store_nullifiers(path: &str, map: &HashMap<String, String>) -> Result<()> {
+    storage::store_json(path, "post_nullifiers.json", map)
+}
+
+pub fn is_nullifier_used(nullifier: &str, path: &str) -> Result<bool> {
+    Ok(load_nullifiers(path)?.contains_key(nullifier))
+}
+
+/// Verifies an anonymous post against the group tree and records its nullifiers, so a proof
+/// is accepted once and a member posts at most `rate_limit` times per epoch. Going over the
+/// rate limit bans the member from the group.
+pub fn post_anonymous_message(
+    message: AnonymousMessage,
+    rate_limit_config: RateLimitConfig,
+    srs_path: String,
+    circuit_path: String,
+    path: String,
+) -> Result<bool> {
+    if !group_tree::is_known_group_root(
+        message.anonGroupId.clone(),
+        message.merkleRoot.clone(),
+        path.clone(),
+    )? {
+        bail!("post_anonymous_message: Unknown group root.")
+    }
+
+    let timestamp: DateTime<Utc> = message.timestamp.parse()?;
+    if message.epoch != rate_limit_config.epoch_at(timestamp)
+        || !rate_limit_config.is_open_epoch(message.epoch, Utc::now())
+    {
+        bail!("post_anonymous_message: Message is not in the current epoch.")
+    }
+
+    let mut nullifiers = load_nullifiers(&path)?;
+    if nullifiers.contains_key(&message.nullifier) {
+        bail!("post_anonymous_message: Nullifier already used.")
+    }
+
+    let public_inputs = message.public_inputs(rate_limit_config.rate_limit)?;
+    let valid = verify_anon_post_proof(
+        srs_path,
+        circuit_path,
+        message.proof.clone(),
+        &public_inputs,
+    );
+    if !valid {
+        bail!("post_anonymous_message: Invalid proof.")
+    }
+
+    rate_limit::record_rate_limit_share(
+        message.anonGroupId.clone(),
+        message.id.clone(),
+        public_inputs.rate_limit_share(),
+        path.clone(),
+    )?;
+
+    nullifiers.insert(message.nullifier, message.id);
+    store_nullifiers(&path, &nullifiers)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::api_server::storage::tests::test_dir;
+    use crate::proof::ephemeral_key::EphemeralKey;
+
+    fn sample_message(merkle_root: String, nullifier: &str) -> AnonymousMessage {
+        let now = Utc::now();
+        AnonymousMessage {
+            id: "a1b2c3d4".to_string(),
+            anonGroupId: "pse.dev".to_string(),
+            anonGroupProvider: "google-oauth".to_string(),
+            text: "Hello, anonymous world!".to_string(),
+            timestamp: now.to_rfc3339(),
+            internal: false,
+            likes: 0,
+            topic: "general".to_string(),
+            merkleRoot: merkle_root,
+            nullifier: nullifier.to_string(),
+            epoch: RateLimitConfig::default().epoch_at(now),
+            rateLimitNullifier: "2".to_string(),
+            shareY: "3".to_string(),
+            proof: vec![],
+        }
+    }
+
+    fn post(message: AnonymousMessage, path: &str) -> Result<bool> {
+        post_anonymous_message(
+            message,
+            RateLimitConfig::default(),
+            String::new(),
+            String::new(),
+            path.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_identity_commitment_signature() {
+        let mut key = EphemeralKey::generate_ephemeral_key().unwrap();
+        let commitment = key.identity_commitment("pse.dev");
+        assert!(commitment.verify().is_ok());
+
+        let mut forged = commitment.clone();
+        forged.group_id = "example.com".to_string();
+        assert!(forged.verify().is_err());
+    }
+
+    #[test]
+    fn test_post_anonymous_message_rejects_unknown_root() {
+        let path = test_dir();
+        let message = sample_message("12345".to_string(), "1");
+
+        let err = post(message, &path).unwrap_err();
+        assert!(err.to_string().contains("Unknown group root"));
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_post_anonymous_message_rejects_used_nullifier() {
+        let path = test_dir();
+        group_tree::add_member_commitment("pse.dev".to_string(), "7".to_string(), path.clone())
+            .unwrap();
+        let root = group_tree::get_group_root("pse.dev".to_string(), path.clone()).unwrap();
+
+        let mut nullifiers = HashMap::new();
+        nullifiers.insert("99".to_string(), "previous".to_string());
+        store_nullifiers(&path, &nullifiers).unwrap();
+        assert!(is_nullifier_used("99", &path).unwrap());
+
+        let err = post(sample_message(root, "99"), &path).unwrap_err();
+        assert!(err.to_string().contains("Nullifier already used"));
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_post_anonymous_message_rejects_stale_epoch() {
+        let path = test_dir();
+        group_tree::add_member_commitment("pse.dev".to_string(), "7".to_string(), path.clone())
+            .unwrap();
+        let root = group_tree::get_group_root("pse.dev".to_string(), path.clone()).unwrap();
+
+        let mut message = sample_message(root.clone(), "1");
+        message.epoch -= 2;
+        let err = post(message, &path).unwrap_err();
+        assert!(err.to_string().contains("not in the current epoch"));
+
+        // The epoch must also match the timestamp of the message
+        let mut message = sample_message(root, "1");
+        message.timestamp = "2025-05-01T09:07:57.379Z".to_string();
+        let err = post(message, &path).unwrap_err();
+        assert!(err.to_string().contains("not in the current epoch"));
+
+        let _ = fs::remove_dir_all(path);
+    }
+}
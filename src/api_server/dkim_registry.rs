@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Ok, Result};
+
+use super::{nullifier::normalize_nullifier, storage, Member};
+
+/// Key of `Member::proof_args` holding the hash of the DKIM key returned by the zkemail proof.
+pub const DKIM_PUBKEY_HASH_PROOF_ARG: &str = "dkim_pubkey_hash";
+
+fn load_dkim_pubkey_hashes(path: &str) -> Result<HashMap<String, Vec<String>>> {
+    storage::load_json(path, "dkim_pubkey_hashes.json")
+}
+
+fn store_dkim_pubkey_hashes(path: &str, map: &HashMap<String, Vec<String>>) -> Result<()> {
+    storage::store_json(path, "dkim_pubkey_hashes.json", map)
+}
+
+/// Trusts the DKIM key of `pubkey_hash`, `RSAPubkey::hash` in zkemail.nr, to sign the emails of
+/// `domain`. The key is the one of a `<selector>._domainkey` DNS record of the domain or of
+/// its email provider.
+pub fn register_dkim_pubkey_hash(domain: &str, pubkey_hash: &str, path: &str) -> Result<()> {
+    let pubkey_hash = normalize_nullifier(pubkey_hash)?;
+    let mut map = load_dkim_pubkey_hashes(path)?;
+    let hashes = map.entry(domain.to_lowercase()).or_default();
+    if !hashes.contains(&pubkey_hash) {
+        hashes.push(pubkey_hash);
+    }
+    store_dkim_pubkey_hashes(path, &map)
+}
+
+pub fn is_trusted_dkim_pubkey_hash(domain: &str, pubkey_hash: &str, path: &str) -> Result<bool> {
+    let pubkey_hash = normalize_nullifier(pubkey_hash)?;
+    Ok(load_dkim_pubkey_hashes(path)?
+        .get(&domain.to_lowercase())
+        .is_some_and(|hashes| hashes.contains(&pubkey_hash)))
+}
+
+/// Checks that the DKIM key a member proves an email of its group with is trusted for the group.
+pub fn check_member_dkim_pubkey_hash(member: &Member, path: &str) -> Result<()> {
+    let pubkey_hash = match member.proof_args.get(DKIM_PUBKEY_HASH_PROOF_ARG) {
+        Some(values) if values.len() == 1 => &values[0],
+        _ => bail!(
+            "Member has no {} proof argument",
+            DKIM_PUBKEY_HASH_PROOF_ARG
+        ),
+    };
+    if !is_trusted_dkim_pubkey_hash(&member.group_id, pubkey_hash, path)? {
+        bail!(
+            "DKIM key {} is not trusted for {}",
+            pubkey_hash,
+            member.group_id
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::api_server::storage::tests::test_dir;
+
+    #[test]
+    fn test_register_dkim_pubkey_hash() {
+        let path = test_dir();
+        assert!(!is_trusted_dkim_pubkey_hash("gmail.com", "7", &path).unwrap());
+
+        register_dkim_pubkey_hash("Gmail.com", "7", &path).unwrap();
+        register_dkim_pubkey_hash("gmail.com", "0x07", &path).unwrap();
+        assert!(is_trusted_dkim_pubkey_hash("gmail.com", "0x07", &path).unwrap());
+        assert_eq!(
+            load_dkim_pubkey_hashes(&path).unwrap()["gmail.com"].len(),
+            1
+        );
+
+        // A key is only trusted for the domains it is registered for
+        assert!(!is_trusted_dkim_pubkey_hash("example.com", "7", &path).unwrap());
+        assert!(!is_trusted_dkim_pubkey_hash("gmail.com", "8", &path).unwrap());
+        assert!(register_dkim_pubkey_hash("gmail.com", "0xzz", &path).is_err());
+
+        let _ = fs::remove_dir_all(path);
+    }
+}
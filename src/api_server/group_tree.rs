@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use acir::{acir_field::FieldElement, AcirField};
+use anyhow::{bail, Ok, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{storage, GroupMerkleProof};
+use crate::proof::{
+    ephemeral_commitment::{decimal_to_field, field_to_biguint},
+    merkle_tree::{IncrementalMerkleTree, MerkleProof, MERKLE_TREE_DEPTH, ROOT_HISTORY_SIZE},
+};
+
+/// Member commitments of a group in insertion order, plus the accepted root history.
+/// Banned commitments are zeroed in `leaves` and cannot join again.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct StoredGroupTree {
+    leaves: Vec<String>,
+    roots: Vec<String>,
+    #[serde(default)]
+    banned: Vec<String>,
+}
+
+fn to_decimal(value: &FieldElement) -> String {
+    field_to_biguint(*value).to_string()
+}
+
+fn load_trees(path: &str) -> Result<HashMap<String, StoredGroupTree>> {
+    storage::load_json(path, "group_trees.json")
+}
+
+fn store_trees(path: &str, map: &HashMap<String, StoredGroupTree>) -> Result<()> {
+    storage::store_json(path, "group_trees.json", map)
+}
+
+fn build_tree(stored: &StoredGroupTree) -> Result<IncrementalMerkleTree> {
+    let leaves = stored
+        .leaves
+        .iter()
+        .map(|leaf| decimal_to_field(leaf))
+        .collect::<Result<Vec<_>>>()?;
+    IncrementalMerkleTree::from_leaves(MERKLE_TREE_DEPTH, &leaves)
+}
+
+impl GroupMerkleProof {
+    pub fn to_merkle_proof(&self) -> Result<MerkleProof> {
+        Ok(MerkleProof {
+            leaf_index: self.leaf_index as usize,
+            siblings: self
+                .siblings
+                .iter()
+                .map(|sibling| decimal_to_field(sibling))
+                .collect::<Result<Vec<_>>>()?,
+            root: decimal_to_field(&self.root)?,
+        })
+    }
+}
+
+/// Loads the member tree of a group; unknown groups have an empty tree.
+pub fn get_group_tree(group_id: &str, path: &str) -> Result<IncrementalMerkleTree> {
+    let trees = load_trees(path)?;
+    match trees.get(group_id) {
+        Some(stored) => build_tree(stored),
+        None => Ok(IncrementalMerkleTree::new(MERKLE_TREE_DEPTH)),
+    }
+}
+
+/// Rejects a commitment that is banned from or already in the tree of `stored`.
+fn check_new_commitment(
+    stored: &StoredGroupTree,
+    tree: &IncrementalMerkleTree,
+    commitment: FieldElement,
+) -> Result<()> {
+    if stored.banned.contains(&to_decimal(&commitment)) {
+        bail!("Commitment is banned from the group")
+    }
+    if tree.index_of(commitment).is_some() {
+        bail!("Commitment is already a member of the group")
+    }
+    Ok(())
+}
+
+/// Checks that `add_member_commitment` accepts `commitment` for `group_id`, without adding it.
+pub fn check_member_commitment(group_id: &str, commitment: &str, path: &str) -> Result<()> {
+    let commitment = decimal_to_field(commitment)?;
+    let stored = load_trees(path)?.remove(group_id).unwrap_or_default();
+    check_new_commitment(&stored, &build_tree(&stored)?, commitment)
+}
+
+/// Appends a member commitment to the tree of `group_id` and returns its leaf index.
+pub fn add_member_commitment(group_id: String, commitment: String, path: String) -> Result<u64> {
+    let commitment = decimal_to_field(&commitment)?;
+
+    let mut trees = load_trees(&path)?;
+    let stored = trees.entry(group_id).or_default();
+    let mut tree = build_tree(stored)?;
+    if let Err(e) = check_new_commitment(stored, &tree, commitment) {
+        bail!("add_member_commitment: {}.", e)
+    }
+    let index = tree.insert(commitment)?;
+
+    stored.leaves.push(to_decimal(&commitment));
+    stored.roots.push(to_decimal(&tree.root()));
+    let history_start = stored.roots.len().saturating_sub(ROOT_HISTORY_SIZE);
+    stored.roots.drain(..history_start);
+    store_trees(&path, &trees)?;
+
+    Ok(index as u64)
+}
+
+/// Removes a member commitment from the tree of `group_id` and bans it. Only the new root
+/// is accepted afterwards, so proofs against older roots that include it are rejected.
+pub fn ban_member_commitment(group_id: String, commitment: String, path: String) -> Result<()> {
+    let commitment = decimal_to_field(&commitment)?;
+
+    let mut trees = load_trees(&path)?;
+    let stored = match trees.get_mut(&group_id) {
+        Some(stored) => stored,
+        None => bail!("ban_member_commitment: Unknown group."),
+    };
+    let mut tree = build_tree(stored)?;
+    let index = match tree.index_of(commitment) {
+        Some(index) => index,
+        None => bail!("ban_member_commitment: Commitment is not a member of the group."),
+    };
+    tree.remove(index)?;
+
+    stored.leaves[index] = to_decimal(&FieldElement::zero());
+    stored.roots = vec![to_decimal(&tree.root())];
+    stored.banned.push(to_decimal(&commitment));
+    store_trees(&path, &trees)?;
+
+    Ok(())
+}
+
+pub fn is_banned_member_commitment(
+    group_id: String,
+    commitment: String,
+    path: String,
+) -> Result<bool> {
+    let commitment = to_decimal(&decimal_to_field(&commitment)?);
+    Ok(load_trees(&path)?
+        .get(&group_id)
+        .map_or(false, |stored| stored.banned.contains(&commitment)))
+}
+
+pub fn get_group_root(group_id: String, path: String) -> Result<String> {
+    Ok(to_decimal(&get_group_tree(&group_id, &path)?.root()))
+}
+
+/// Whether `root` is the current root of the group or one of its recent predecessors.
+pub fn is_known_group_root(group_id: String, root: String, path: String) -> Result<bool> {
+    let root = decimal_to_field(&root)?;
+    let trees = load_trees(&path)?;
+    let stored = match trees.get(&group_id) {
+        Some(stored) => stored,
+        None => return Ok(root == IncrementalMerkleTree::new(MERKLE_TREE_DEPTH).root()),
+    };
+    Ok(stored
+        .roots
+        .iter()
+        .any(|known| decimal_to_field(known).map_or(false, |known| known == root)))
+}
+
+/// Inclusion path of a member commitment against the current root of its group.
+pub fn get_group_merkle_proof(
+    group_id: String,
+    commitment: String,
+    path: String,
+) -> Result<GroupMerkleProof> {
+    let tree = get_group_tree(&group_id, &path)?;
+    let leaf = decimal_to_field(&commitment)?;
+    let index = match tree.index_of(leaf) {
+        Some(index) if leaf != FieldElement::zero() => index,
+        _ => bail!("get_group_merkle_proof: Commitment is not a member of the group."),
+    };
+    let proof = tree.proof(index).unwrap();
+
+    Ok(GroupMerkleProof {
+        group_id,
+        commitment,
+        leaf_index: index as u64,
+        siblings: proof.siblings.iter().map(to_decimal).collect(),
+        root: to_decimal(&proof.root),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::api_server::storage::tests::test_dir;
+
+    #[test]
+    fn test_add_member_commitment() {
+        let path = test_dir();
+        let group = "pse.dev".to_string();
+
+        let empty_root = get_group_root(group.clone(), path.clone()).unwrap();
+        assert!(is_known_group_root(group.clone(), empty_root.clone(), path.clone()).unwrap());
+
+        for i in 1..=3u64 {
+            let index = add_member_commitment(group.clone(), i.to_string(), path.clone()).unwrap();
+            assert_eq!(index, i - 1);
+        }
+        assert!(add_member_commitment(group.clone(), "2".to_string(), path.clone()).is_err());
+
+        // Groups have independent trees
+        add_member_commitment("example.com".to_string(), "2".to_string(), path.clone()).unwrap();
+
+        let root = get_group_root(group.clone(), path.clone()).unwrap();
+        assert_ne!(root, empty_root);
+        assert!(is_known_group_root(group.clone(), root, path.clone()).unwrap());
+        assert!(!is_known_group_root(group, empty_root, path.clone()).unwrap());
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_get_group_merkle_proof() {
+        let path = test_dir();
+        let group = "pse.dev".to_string();
+        for i in 1..=5u64 {
+            add_member_commitment(group.clone(), (i * 1000).to_string(), path.clone()).unwrap();
+        }
+
+        let proof =
+            get_group_merkle_proof(group.clone(), "3000".to_string(), path.clone()).unwrap();
+        assert_eq!(proof.leaf_index, 2);
+        assert_eq!(
+            proof.root,
+            get_group_root(group.clone(), path.clone()).unwrap()
+        );
+
+        let merkle_proof = proof.to_merkle_proof().unwrap();
+        assert!(merkle_proof.verify(FieldElement::from(3000u64)));
+
+        assert!(get_group_merkle_proof(group, "42".to_string(), path.clone()).is_err());
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_root_history_is_bounded() {
+        let path = test_dir();
+        let group = "pse.dev".to_string();
+
+        add_member_commitment(group.clone(), "1".to_string(), path.clone()).unwrap();
+        let first_root = get_group_root(group.clone(), path.clone()).unwrap();
+        for i in 2..=(ROOT_HISTORY_SIZE as u64 + 1) {
+            add_member_commitment(group.clone(), i.to_string(), path.clone()).unwrap();
+        }
+
+        assert!(!is_known_group_root(group.clone(), first_root, path.clone()).unwrap());
+        assert_eq!(
+            load_trees(&path).unwrap()[&group].roots.len(),
+            ROOT_HISTORY_SIZE
+        );
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_ban_member_commitment() {
+        let path = test_dir();
+        let group = "pse.dev".to_string();
+        for i in 1..=3u64 {
+            add_member_commitment(group.clone(), i.to_string(), path.clone()).unwrap();
+        }
+        let old_root = get_group_root(group.clone(), path.clone()).unwrap();
+
+        ban_member_commitment(group.clone(), "2".to_string(), path.clone()).unwrap();
+        assert!(is_banned_member_commitment(group.clone(), "2".to_string(), path.clone()).unwrap());
+        assert!(!is_known_group_root(group.clone(), old_root, path.clone()).unwrap());
+        assert!(get_group_merkle_proof(group.clone(), "2".to_string(), path.clone()).is_err());
+        assert!(add_member_commitment(group.clone(), "2".to_string(), path.clone()).is_err());
+
+        // Remaining members keep their index and can still prove membership
+        let proof = get_group_merkle_proof(group.clone(), "3".to_string(), path.clone()).unwrap();
+        assert_eq!(proof.leaf_index, 2);
+        assert!(is_known_group_root(group.clone(), proof.root, path.clone()).unwrap());
+        assert_eq!(
+            add_member_commitment(group.clone(), "4".to_string(), path.clone()).unwrap(),
+            3
+        );
+
+        assert!(ban_member_commitment(group, "2".to_string(), path.clone()).is_err());
+
+        let _ = fs::remove_dir_all(path);
+    }
+}
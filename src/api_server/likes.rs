@@ -1,8 +1,14 @@
-use anyhow::{Ok, Result};
+use anyhow::{bail, Ok, Result};
 use num_bigint::BigUint;
 use std::str::FromStr;
 
+use super::revocation;
+
 pub fn post_likes(pub_key: String, msg_id: u32, like: bool, path: String) -> Result<u32> {
+    if revocation::is_revoked(&pub_key, &path)? {
+        bail!("post_likes: Revoked ephemeral key.")
+    }
+
     // membership check: pub_key is existed
     // FileApi::get_member(BigUint::from_str(&pub_key).unwrap(), path.clone())
     //     .map_err(|e| anyhow::anyhow!(e.to_string()))?;
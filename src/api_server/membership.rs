@@ -3,30 +3,111 @@ use std::str::FromStr;
 use anyhow::{bail, Ok, Result};
 use num_bigint::BigUint;
 
-use super::Member;
+use super::{
+    dkim_registry, group_tree,
+    nullifier::{self, member_nullifier, NullifierChainReader},
+    revocation, IdentityCommitment, KeyRotation, Member, Provider,
+};
 
 pub fn create_membership(member: Member, path: String) -> Result<bool> {
-    let valid = member.clone().provider.verify_proof(
+    create_membership_with_chain_reader(member, path, None)
+}
+
+/// Registers a member whose proof nullifier, read from the proof and claimed in `proof_args`,
+/// is used neither here nor, given a `chain_reader`, in `ZkJwtProofManager`, and records the
+/// nullifier. The nullifier is checked up front to skip verifying a reused proof, and checked
+/// again when recorded, under the storage lock, so concurrent registrations of one proof
+/// record it once. Email members must prove with a DKIM key registered for their group in
+/// `dkim_registry`.
+///
+/// Google proofs are not verified yet (`GoogleOAuthProvider::verify_proof` accepts any
+/// proof), so for them the nullifier only limits how often the same proof bytes register.
+pub fn create_membership_with_chain_reader(
+    member: Member,
+    path: String,
+    chain_reader: Option<&dyn NullifierChainReader>,
+) -> Result<bool> {
+    if revocation::is_revoked(&member.pubkey, &path)? {
+        bail!("create_membership: Revoked ephemeral key.")
+    }
+
+    let nullifier = member_nullifier(&member)?;
+    if let Err(e) = nullifier::check_nullifier(&nullifier, &path, chain_reader) {
+        bail!("create_membership: {}.", e)
+    }
+    if matches!(member.provider, Provider::EmailDkim) {
+        if let Err(e) = dkim_registry::check_member_dkim_pubkey_hash(&member, &path) {
+            bail!("create_membership: {}.", e)
+        }
+    }
+
+    if let Err(e) = member.clone().provider.verify_proof(
         member.clone().proof,
         member.clone().group_id,
         BigUint::from_str(member.clone().pubkey.as_str()).unwrap(),
         member.clone().pubkey_expiry,
         member.clone().proof_args,
-    );
-    if !valid {
-        bail!("create_membership: Invalid proof.")
+    ) {
+        bail!("create_membership: Invalid proof: {}.", e)
     }
 
+    if let Err(e) = nullifier::record_nullifier(&nullifier, &member.group_id, &path, None) {
+        bail!("create_membership: {}.", e)
+    }
     Ok(true)
     // FileApi::insert_member(member, path)
 }
 
+/// Registers the membership of a rotated ephemeral key and retires the previous key. The
+/// rotation is checked before the membership is created, so a rejected rotation records no
+/// nullifier.
+pub fn rotate_membership(member: Member, rotation: KeyRotation, path: String) -> Result<bool> {
+    if member.pubkey != rotation.new_pubkey || member.pubkey_expiry != rotation.new_pubkey_expiry {
+        bail!("rotate_membership: Member does not match the rotated ephemeral key.")
+    }
+    revocation::check_rotation(&rotation, &path)?;
+
+    create_membership(member, path.clone())?;
+    revocation::record_rotation(rotation, path)
+}
+
+/// Registers a member and adds the member commitment of its ephemeral key to the group tree,
+/// so the member can post anonymously. Returns the leaf index of the commitment. The
+/// commitment is checked before the membership is created, so a banned or duplicate
+/// commitment records no nullifier.
+pub fn create_anonymous_membership(
+    member: Member,
+    commitment: IdentityCommitment,
+    path: String,
+) -> Result<u64> {
+    if member.pubkey != commitment.pubkey || member.group_id != commitment.group_id {
+        bail!("create_anonymous_membership: Commitment does not match the member.")
+    }
+    commitment.verify()?;
+    if let Err(e) =
+        group_tree::check_member_commitment(&commitment.group_id, &commitment.commitment, &path)
+    {
+        bail!("create_anonymous_membership: {}.", e)
+    }
+
+    create_membership(member, path.clone())?;
+    group_tree::add_member_commitment(commitment.group_id, commitment.commitment, path)
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
     use super::*;
-    use crate::api_server::Provider;
+    use crate::api_server::{storage::tests::test_dir, EmailDkimProvider};
+    use crate::proof::{
+        ephemeral_key::EphemeralKey,
+        jwt_proof::{google_issuers, JwtPublicInputs},
+        solidity::SolidityProof,
+        zkemail::tests::proof_with_outputs,
+    };
 
     fn cleanup() {
         let _ = std::fs::remove_file("members.json");
@@ -57,4 +138,197 @@ mod tests {
 
     //     cleanup();
     // }
+
+    /// JWT proof of `sample_member` returning `nullifier`, with a dummy proof.
+    fn google_proof(nullifier: &str) -> Vec<u8> {
+        let nullifier = nullifier::normalize_nullifier(nullifier).unwrap();
+        let modulus = BigUint::from(1u8) << 2047u32;
+        let public_inputs = JwtPublicInputs {
+            domain: "pse.dev".to_string(),
+            google_jwt_pubkey_modulus: URL_SAFE_NO_PAD.encode(modulus.to_bytes_be()),
+            ephemeral_pubkey: "12345".to_string(),
+            ephemeral_pubkey_expiry: "2025-05-07T09:07:57.379Z".to_string(),
+            iss: google_issuers()[0].clone(),
+            aud: "client-id".to_string(),
+            iat: 1746004080,
+        };
+        SolidityProof::from_jwt_public_inputs(
+            vec![0; 64],
+            &public_inputs,
+            &BigUint::parse_bytes(&nullifier.as_bytes()[2..], 16).unwrap(),
+        )
+        .unwrap()
+        .to_honk_proof()
+    }
+
+    fn member_with_nullifier(nullifier: &str) -> Member {
+        let mut member = sample_member();
+        member.proof = google_proof(nullifier);
+        member.proof_args.insert(
+            nullifier::NULLIFIER_PROOF_ARG.to_string(),
+            vec![nullifier.to_string()],
+        );
+        member
+    }
+
+    #[test]
+    fn test_create_membership_rejects_reused_nullifier() {
+        let path = test_dir();
+
+        let err = create_membership(sample_member(), path.clone()).unwrap_err();
+        assert!(err.to_string().contains("no nullifier proof argument"));
+
+        assert!(create_membership(member_with_nullifier("42"), path.clone()).unwrap());
+        assert!(nullifier::is_nullifier_used("42", &path).unwrap());
+        let err = create_membership(member_with_nullifier("42"), path.clone()).unwrap_err();
+        assert!(err.to_string().contains("already used"));
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_create_membership_records_a_nullifier_once_under_concurrency() {
+        let path = test_dir();
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || create_membership(member_with_nullifier("21"), path))
+            })
+            .collect();
+        let registered = threads
+            .into_iter()
+            .filter_map(|t| t.join().unwrap().ok())
+            .count();
+        assert_eq!(registered, 1);
+        assert!(nullifier::is_nullifier_used("21", &path).unwrap());
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_create_membership_rejects_mismatched_nullifier() {
+        let path = test_dir();
+
+        // A proof returning a used nullifier cannot claim a fresh one
+        assert!(create_membership(member_with_nullifier("42"), path.clone()).unwrap());
+        let mut member = member_with_nullifier("43");
+        member.proof = google_proof("42");
+        let err = create_membership(member, path.clone()).unwrap_err();
+        assert!(err.to_string().contains("not the nullifier of the proof"));
+        assert!(!nullifier::is_nullifier_used("43", &path).unwrap());
+
+        let err = create_membership(member_with_nullifier("0x2a"), path.clone()).unwrap_err();
+        assert!(err.to_string().contains("already used"));
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_rotate_membership_checks_rotation_first() {
+        let path = test_dir();
+        let mut key = EphemeralKey::generate_ephemeral_key().unwrap();
+        let (_, rotation) = key.rotate(chrono::Duration::days(1)).unwrap();
+        let mut member = member_with_nullifier("11");
+        member.pubkey = rotation.new_pubkey.clone();
+        member.pubkey_expiry = rotation.new_pubkey_expiry.clone();
+
+        // The previous key is revoked, so the rotated member is not registered
+        revocation::revoke_key(key.revoke(), path.clone()).unwrap();
+        let err = rotate_membership(member, rotation, path.clone()).unwrap_err();
+        assert!(err.to_string().contains("already revoked"));
+        assert!(!nullifier::is_nullifier_used("11", &path).unwrap());
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_create_anonymous_membership_checks_commitment_first() {
+        let path = test_dir();
+        let mut key = EphemeralKey::generate_ephemeral_key().unwrap();
+        let commitment = key.identity_commitment("pse.dev");
+        let mut member = member_with_nullifier("13");
+        member.pubkey = commitment.pubkey.clone();
+
+        // A duplicate commitment does not spend the nullifier of the member
+        group_tree::add_member_commitment(
+            "pse.dev".to_string(),
+            commitment.commitment.clone(),
+            path.clone(),
+        )
+        .unwrap();
+        let err = create_anonymous_membership(member.clone(), commitment.clone(), path.clone())
+            .unwrap_err();
+        assert!(err.to_string().contains("already a member"));
+        assert!(!nullifier::is_nullifier_used("13", &path).unwrap());
+
+        // Nor does a banned one
+        group_tree::ban_member_commitment(
+            "pse.dev".to_string(),
+            commitment.commitment.clone(),
+            path.clone(),
+        )
+        .unwrap();
+        let err = create_anonymous_membership(member, commitment, path.clone()).unwrap_err();
+        assert!(err.to_string().contains("banned"));
+        assert!(!nullifier::is_nullifier_used("13", &path).unwrap());
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_create_membership_rejects_nullifier_used_onchain() {
+        let path = test_dir();
+        let chain_reader = nullifier::tests::MockChainReader::default();
+        chain_reader
+            .nullifiers
+            .lock()
+            .unwrap()
+            .insert(nullifier::normalize_nullifier("7").unwrap());
+
+        let err = create_membership_with_chain_reader(
+            member_with_nullifier("7"),
+            path.clone(),
+            Some(&chain_reader),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("on-chain"));
+        assert!(!nullifier::is_nullifier_used("7", &path).unwrap());
+
+        assert!(create_membership_with_chain_reader(
+            member_with_nullifier("8"),
+            path.clone(),
+            Some(&chain_reader),
+        )
+        .unwrap());
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_create_membership_requires_trusted_dkim_key() {
+        let path = test_dir();
+        let proof = proof_with_outputs(7, 9, "me@pse.dev", "1");
+        let nullifier = EmailDkimProvider::proof_nullifier(&proof).unwrap();
+        let mut member = member_with_nullifier(&nullifier);
+        member.provider = Provider::EmailDkim;
+        member.proof = proof;
+
+        let err = create_membership(member.clone(), path.clone()).unwrap_err();
+        assert!(err.to_string().contains("no dkim_pubkey_hash proof"));
+
+        member.proof_args.insert(
+            dkim_registry::DKIM_PUBKEY_HASH_PROOF_ARG.to_string(),
+            vec!["0x07".to_string()],
+        );
+        let err = create_membership(member.clone(), path.clone()).unwrap_err();
+        assert!(err.to_string().contains("not trusted for pse.dev"));
+
+        // A trusted key still needs a valid proof
+        dkim_registry::register_dkim_pubkey_hash("pse.dev", "7", &path).unwrap();
+        let err = create_membership(member, path.clone()).unwrap_err();
+        assert!(err.to_string().contains("Invalid proof"));
+        assert!(!nullifier::is_nullifier_used(&nullifier, &path).unwrap());
+
+        let _ = std::fs::remove_dir_all(path);
+    }
 }
@@ -1,9 +1,10 @@
 use std::{mem, str::FromStr};
 
-use super::{api::Api, Message, SignedMessage};
+use super::{api::Api, revocation, Message, SignedMessage};
+use crate::proof::ephemeral_key::{parse_ephemeral_public_key, parse_signature};
 use anyhow::{bail, Ok, Result};
 use chrono::{DateTime, Utc};
-use ed25519_dalek::{Signature, Signer, SigningKey};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier};
 use num_bigint::BigUint;
 use reqwest::Client;
 use serde::Serialize;
@@ -128,6 +129,28 @@ pub fn sign_message(
     serde_json::to_string(&payload).unwrap()
 }
 
+/// Verifies the ed25519 signature of a message and rejects messages from revoked ephemeral keys.
+pub fn verify_signed_message(signed_message: SignedMessage, path: String) -> Result<bool> {
+    if revocation::is_revoked(&signed_message.ephemeralPubkey, &path)? {
+        bail!("verify_signed_message: Revoked ephemeral key.")
+    }
+
+    let message = Message {
+        id: signed_message.id,
+        anonGroupId: signed_message.anonGroupId,
+        anonGroupProvider: signed_message.anonGroupProvider,
+        text: signed_message.text,
+        timestamp: signed_message.timestamp,
+        internal: signed_message.internal,
+        likes: signed_message.likes,
+    };
+    let message_hash = hash_message(message);
+
+    let verifying_key = parse_ephemeral_public_key(&signed_message.ephemeralPubkey)?;
+    let signature = parse_signature(&signed_message.signature)?;
+    Ok(verifying_key.verify(&message_hash, &signature).is_ok())
+}
+
 pub async fn create_message(signed_message_str: String) -> Result<()> {
     let client = Client::new();
 
@@ -151,6 +174,7 @@ pub async fn create_message(signed_message_str: String) -> Result<()> {
 mod tests {
 
     use super::*;
+    use crate::api_server::storage::tests::test_dir;
 
     #[tokio::test]
     async fn test_sign_message() {
@@ -184,6 +208,32 @@ mod tests {
         create_message(signed_message_str).await.unwrap();
     }
 
+    #[test]
+    fn test_verify_signed_message() {
+        let path = test_dir();
+
+        let mut key = crate::proof::ephemeral_key::EphemeralKey::generate_ephemeral_key().unwrap();
+        let signed_message_str = sign_message(
+            "pse.dev".to_string(),
+            "sent from Rust".to_string(),
+            false,
+            key.get_ephemeral_public_key(),
+            key.get_ephemeral_private_key(),
+            key.get_ephemeral_expiry(),
+        );
+        let signed_message: SignedMessage = serde_json::from_str(&signed_message_str).unwrap();
+        assert!(verify_signed_message(signed_message.clone(), path.clone()).unwrap());
+
+        let mut tampered = signed_message.clone();
+        tampered.text = "tampered".to_string();
+        assert!(!verify_signed_message(tampered, path.clone()).unwrap());
+
+        revocation::revoke_key(key.revoke(), path.clone()).unwrap();
+        assert!(verify_signed_message(signed_message, path.clone()).is_err());
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
     #[tokio::test]
     async fn test_create_message() {
         let signed_message = SignedMessage {
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use anyhow::{bail, Result};
 use ed25519::Signature;
 use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
@@ -8,17 +9,35 @@ mod api;
 mod provider;
 use provider::*;
 
+pub mod anon_post;
+pub mod dkim_registry;
+pub mod group_tree;
 pub mod likes;
 pub mod membership;
 pub mod message;
+pub mod nullifier;
+pub mod rate_limit;
+pub mod revocation;
+pub(crate) mod storage;
 
 #[derive(uniffi::Enum, Serialize, Deserialize, Clone)]
 pub enum Provider {
     Google,
     Microsoft,
+    EmailDkim,
 }
 
 impl Provider {
+    /// Nullifier the membership circuit of the provider returns with the public inputs of
+    /// `proof`.
+    pub fn proof_nullifier(&self, proof: &[u8]) -> Result<String> {
+        match self {
+            Self::Google => GoogleOAuthProvider::proof_nullifier(proof),
+            Self::Microsoft => bail!("Not supported yet."),
+            Self::EmailDkim => EmailDkimProvider::proof_nullifier(proof),
+        }
+    }
+
     pub fn verify_proof(
         &self,
         proof: Vec<u8>,
@@ -26,16 +45,28 @@ impl Provider {
         ephemeral_pubkey: BigUint,
         ephemeral_pubkey_expiry: String,
         proof_args: HashMap<String, Vec<String>>,
-    ) -> bool {
+    ) -> Result<()> {
         match self {
-            Self::Google => GoogleOAuthProvider::verify_proof(
+            Self::Google => {
+                if !GoogleOAuthProvider::verify_proof(
+                    proof,
+                    anon_group_id,
+                    ephemeral_pubkey,
+                    ephemeral_pubkey_expiry,
+                    proof_args,
+                ) {
+                    bail!("Proof verification failed")
+                }
+                Ok(())
+            }
+            Self::Microsoft => bail!("Not supported yet."),
+            Self::EmailDkim => EmailDkimProvider::verify_member_proof(
                 proof,
-                anon_group_id,
-                ephemeral_pubkey,
-                ephemeral_pubkey_expiry,
-                proof_args,
+                &anon_group_id,
+                &ephemeral_pubkey,
+                &ephemeral_pubkey_expiry,
+                &proof_args,
             ),
-            Self::Microsoft => panic!("Not supported yet."),
         }
     }
 }
@@ -74,3 +105,81 @@ pub struct SignedMessage {
     pub ephemeralPubkeyExpiry: String,
     pub likes: u32,
 }
+
+/// Revocation of an ephemeral key, signed by the key being revoked.
+#[derive(uniffi::Record, Serialize, Deserialize, Clone, Debug)]
+pub struct KeyRevocation {
+    pub pubkey: String,
+    pub timestamp: String,
+    pub signature: String,
+}
+
+/// Links a new ephemeral key to the previous one, signed by the previous key.
+#[derive(uniffi::Record, Serialize, Deserialize, Clone, Debug)]
+pub struct KeyRotation {
+    pub previous_pubkey: String,
+    pub new_pubkey: String,
+    pub new_pubkey_hash: String,
+    pub new_pubkey_expiry: String,
+    pub timestamp: String,
+    pub signature: String,
+}
+
+/// Inclusion path of a member commitment in its group's Merkle tree, leaf level first.
+#[derive(uniffi::Record, Serialize, Deserialize, Clone, Debug)]
+pub struct GroupMerkleProof {
+    pub group_id: String,
+    pub commitment: String,
+    pub leaf_index: u64,
+    pub siblings: Vec<String>,
+    pub root: String,
+}
+
+/// Member commitment of an ephemeral key in its group's tree, signed by the ephemeral key.
+#[derive(uniffi::Record, Serialize, Deserialize, Clone, Debug)]
+pub struct IdentityCommitment {
+    pub pubkey: String,
+    pub group_id: String,
+    pub commitment: String,
+    pub signature: String,
+}
+
+/// Message posted with a proof of group membership instead of an ephemeral key signature.
+#[derive(uniffi::Record, Serialize, Deserialize, Clone, Debug)]
+pub struct AnonymousMessage {
+    pub id: String,
+    pub anonGroupId: String,
+    pub anonGroupProvider: String,
+    pub text: String,
+    pub timestamp: String,
+    pub internal: bool,
+    pub likes: u32,
+    pub topic: String,
+    pub merkleRoot: String,
+    pub nullifier: String,
+    pub epoch: u64,
+    pub rateLimitNullifier: String,
+    pub shareY: String,
+    pub proof: Vec<u8>,
+}
+
+/// Maximum number of anonymous posts per member in an epoch of `epoch_length_secs`.
+#[derive(uniffi::Record, Serialize, Deserialize, Clone, Debug)]
+pub struct RateLimitConfig {
+    pub rate_limit: u32,
+    pub epoch_length_secs: u64,
+}
+
+impl AnonymousMessage {
+    pub fn message(&self) -> Message {
+        Message {
+            id: self.id.clone(),
+            anonGroupId: self.anonGroupId.clone(),
+            anonGroupProvider: self.anonGroupProvider.clone(),
+            text: self.text.clone(),
+            timestamp: self.timestamp.clone(),
+            internal: self.internal,
+            likes: self.likes,
+        }
+    }
+}
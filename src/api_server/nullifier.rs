@@ -0,0 +1,268 @@
+use std::{collections::HashMap, str::FromStr};
+
+use anyhow::{anyhow, bail, Ok, Result};
+use chrono::Utc;
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use super::{storage, Member};
+
+/// Key of `Member::proof_args` holding the nullifier returned by the membership circuit.
+pub const NULLIFIER_PROOF_ARG: &str = "nullifier";
+
+/// Nullifier of a registered membership proof.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NullifierRecord {
+    pub domain: String,
+    pub recorded_at: String,
+    /// Whether `ZkJwtProofManager` has recorded the nullifier as well.
+    pub onchain: bool,
+}
+
+/// Reads the `nullifiers` mapping of `ZkJwtProofManager`, so a proof already recorded
+/// on-chain is not registered again off-chain. Returns `None` when the chain is unreachable.
+#[uniffi::export(with_foreign)]
+pub trait NullifierChainReader: Send + Sync {
+    fn is_nullifier_used(&self, nullifier_hash: String) -> Option<bool>;
+}
+
+/// Nullifier as the `bytes32` of `ZkJwtProofManager`, from its decimal or `0x` hex value.
+pub fn normalize_nullifier(nullifier: &str) -> Result<String> {
+    let value = match nullifier.strip_prefix("0x") {
+        Some(digits) => BigUint::parse_bytes(digits.as_bytes(), 16),
+        None => BigUint::from_str(nullifier).ok(),
+    }
+    .ok_or_else(|| anyhow!("Invalid nullifier: {}", nullifier))?;
+    if value.bits() > 256 {
+        bail!("Nullifier does not fit in 32 bytes: {}", nullifier);
+    }
+    Ok(format!("0x{:0>64x}", value))
+}
+
+/// Nullifier a member registers, read from the public inputs of its proof. The nullifier
+/// the member claims in `proof_args` must be the one of the proof.
+pub fn member_nullifier(member: &Member) -> Result<String> {
+    let claimed = match member.proof_args.get(NULLIFIER_PROOF_ARG) {
+        Some(values) if values.len() == 1 => normalize_nullifier(&values[0])?,
+        _ => bail!("Member has no {} proof argument", NULLIFIER_PROOF_ARG),
+    };
+    let nullifier = member.provider.proof_nullifier(&member.proof)?;
+    if claimed != nullifier {
+        bail!("Nullifier {} is not the nullifier of the proof", claimed);
+    }
+    Ok(nullifier)
+}
+
+fn is_used_onchain(chain_reader: &dyn NullifierChainReader, nullifier: &str) -> Result<bool> {
+    chain_reader
+        .is_nullifier_used(nullifier.to_string())
+        .ok_or_else(|| anyhow!("Failed to read nullifier {} on-chain", nullifier))
+}
+
+const NULLIFIERS_FILE: &str = "membership_nullifiers.json";
+
+fn load_nullifiers(path: &str) -> Result<HashMap<String, NullifierRecord>> {
+    storage::load_json(path, NULLIFIERS_FILE)
+}
+
+pub fn is_nullifier_used(nullifier: &str, path: &str) -> Result<bool> {
+    Ok(load_nullifiers(path)?.contains_key(&normalize_nullifier(nullifier)?))
+}
+
+pub fn get_nullifier(nullifier: &str, path: &str) -> Result<Option<NullifierRecord>> {
+    Ok(load_nullifiers(path)?
+        .get(&normalize_nullifier(nullifier)?)
+        .cloned())
+}
+
+/// Checks that a nullifier is used neither here nor, given a `chain_reader`, on-chain.
+pub fn check_nullifier(
+    nullifier: &str,
+    path: &str,
+    chain_reader: Option<&dyn NullifierChainReader>,
+) -> Result<()> {
+    let nullifier = normalize_nullifier(nullifier)?;
+    if load_nullifiers(path)?.contains_key(&nullifier) {
+        bail!("Nullifier {} is already used", nullifier);
+    }
+    if let Some(chain_reader) = chain_reader {
+        if is_used_onchain(chain_reader, &nullifier)? {
+            bail!("Nullifier {} is already used on-chain", nullifier);
+        }
+    }
+    Ok(())
+}
+
+/// Records the nullifier of a membership proof of `domain`, like
+/// `ZkJwtProofManager.recordPublicInputsOfZkJwtProof` does. A used nullifier is rejected; the
+/// check and the insert happen under the storage lock, so two concurrent registrations of
+/// one proof cannot both record its nullifier.
+pub fn record_nullifier(
+    nullifier: &str,
+    domain: &str,
+    path: &str,
+    chain_reader: Option<&dyn NullifierChainReader>,
+) -> Result<()> {
+    let nullifier = normalize_nullifier(nullifier)?;
+    if let Some(chain_reader) = chain_reader {
+        if is_used_onchain(chain_reader, &nullifier)? {
+            bail!("Nullifier {} is already used on-chain", nullifier);
+        }
+    }
+
+    storage::update_json(
+        path,
+        NULLIFIERS_FILE,
+        |map: &mut HashMap<String, NullifierRecord>| {
+            if map.contains_key(&nullifier) {
+                bail!("Nullifier {} is already used", nullifier);
+            }
+            map.insert(
+                nullifier.clone(),
+                NullifierRecord {
+                    domain: domain.to_string(),
+                    recorded_at: Utc::now().to_rfc3339(),
+                    onchain: false,
+                },
+            );
+            Ok(())
+        },
+    )
+}
+
+/// Marks the recorded nullifiers `ZkJwtProofManager` has recorded since the last sync, and
+/// returns how many there were.
+pub fn sync_nullifiers(path: &str, chain_reader: &dyn NullifierChainReader) -> Result<u32> {
+    let mut recorded = Vec::new();
+    for (nullifier, _) in load_nullifiers(path)?
+        .into_iter()
+        .filter(|(_, record)| !record.onchain)
+    {
+        if is_used_onchain(chain_reader, &nullifier)? {
+            recorded.push(nullifier);
+        }
+    }
+    if recorded.is_empty() {
+        return Ok(0);
+    }
+
+    storage::update_json(
+        path,
+        NULLIFIERS_FILE,
+        |map: &mut HashMap<String, NullifierRecord>| {
+            let mut synced = 0;
+            for nullifier in &recorded {
+                if let Some(record) = map.get_mut(nullifier).filter(|record| !record.onchain) {
+                    record.onchain = true;
+                    synced += 1;
+                }
+            }
+            Ok(synced)
+        },
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{collections::HashSet, fs, sync::Mutex};
+
+    use super::*;
+    use crate::api_server::storage::tests::test_dir;
+
+    /// `NullifierChainReader` of the nullifiers in its set, or of an unreachable chain.
+    #[derive(Default)]
+    pub(crate) struct MockChainReader {
+        pub(crate) nullifiers: Mutex<HashSet<String>>,
+        pub(crate) unreachable: bool,
+    }
+
+    impl NullifierChainReader for MockChainReader {
+        fn is_nullifier_used(&self, nullifier_hash: String) -> Option<bool> {
+            if self.unreachable {
+                return None;
+            }
+            Some(self.nullifiers.lock().unwrap().contains(&nullifier_hash))
+        }
+    }
+
+    #[test]
+    fn test_normalize_nullifier() {
+        let hex = format!("0x{:0>64}", "2a");
+        assert_eq!(normalize_nullifier("42").unwrap(), hex);
+        assert_eq!(normalize_nullifier("0x2a").unwrap(), hex);
+        assert_eq!(normalize_nullifier(&hex).unwrap(), hex);
+        assert!(normalize_nullifier("0xzz").is_err());
+        assert!(normalize_nullifier(&format!("0x1{}", "0".repeat(64))).is_err());
+    }
+
+    #[test]
+    fn test_record_nullifier_rejects_reuse() {
+        let path = test_dir();
+        assert!(!is_nullifier_used("42", &path).unwrap());
+
+        record_nullifier("42", "pse.dev", &path, None).unwrap();
+        assert!(is_nullifier_used("0x2a", &path).unwrap());
+        let record = get_nullifier("42", &path).unwrap().unwrap();
+        assert_eq!(record.domain, "pse.dev");
+        assert!(!record.onchain);
+
+        // The same nullifier in another format is the same nullifier
+        let err = record_nullifier("0x2a", "example.com", &path, None).unwrap_err();
+        assert!(err.to_string().contains("already used"));
+        assert_eq!(
+            get_nullifier("42", &path).unwrap().unwrap().domain,
+            "pse.dev"
+        );
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_record_nullifier_checks_the_chain() {
+        let path = test_dir();
+        let chain_reader = MockChainReader::default();
+        chain_reader
+            .nullifiers
+            .lock()
+            .unwrap()
+            .insert(normalize_nullifier("7").unwrap());
+
+        let err = record_nullifier("7", "pse.dev", &path, Some(&chain_reader)).unwrap_err();
+        assert!(err.to_string().contains("on-chain"));
+        assert!(!is_nullifier_used("7", &path).unwrap());
+
+        let unreachable = MockChainReader {
+            unreachable: true,
+            ..Default::default()
+        };
+        assert!(record_nullifier("8", "pse.dev", &path, Some(&unreachable)).is_err());
+        assert!(!is_nullifier_used("8", &path).unwrap());
+
+        record_nullifier("8", "pse.dev", &path, Some(&chain_reader)).unwrap();
+        assert!(is_nullifier_used("8", &path).unwrap());
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_sync_nullifiers() {
+        let path = test_dir();
+        let chain_reader = MockChainReader::default();
+        record_nullifier("1", "pse.dev", &path, None).unwrap();
+        record_nullifier("2", "pse.dev", &path, None).unwrap();
+        assert_eq!(sync_nullifiers(&path, &chain_reader).unwrap(), 0);
+
+        // The proof of the first membership is recorded on-chain afterwards
+        chain_reader
+            .nullifiers
+            .lock()
+            .unwrap()
+            .insert(normalize_nullifier("1").unwrap());
+        assert_eq!(sync_nullifiers(&path, &chain_reader).unwrap(), 1);
+        assert!(get_nullifier("1", &path).unwrap().unwrap().onchain);
+        assert!(!get_nullifier("2", &path).unwrap().unwrap().onchain);
+        assert_eq!(sync_nullifiers(&path, &chain_reader).unwrap(), 0);
+
+        let _ = fs::remove_dir_all(path);
+    }
+}
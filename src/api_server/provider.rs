@@ -2,7 +2,9 @@ use std::collections::HashMap;
 
 use num_bigint::BigUint;
 
+pub mod email_dkim;
 pub mod google;
+pub use email_dkim::EmailDkimProvider;
 pub use google::GoogleOAuthProvider;
 
 struct AnonGroup {
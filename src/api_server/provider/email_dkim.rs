@@ -0,0 +1,161 @@
+use anyhow::{bail, Result};
+use chrono::Utc;
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use super::{AnonGroup, AnonGroupProvider, EphemeralKey};
+use crate::api_server::{
+    dkim_registry::DKIM_PUBKEY_HASH_PROOF_ARG,
+    nullifier::{normalize_nullifier, NULLIFIER_PROOF_ARG},
+};
+use crate::proof::{
+    ephemeral_commitment::EphemeralPubkeyCommitment,
+    jwt_proof::EphemeralExpiryPolicy,
+    zkemail::{generate_zkemail_proof, verify_email_membership_proof, ZkEmailPublicOutputs},
+};
+use std::collections::HashMap;
+
+/// Key of `Member::proof_args` holding the salt of the ephemeral key, from which the verifier
+/// recomputes the nonce in the subject of the email. The salt only opens the nonce: the
+/// identity secret of the key derives from its private key, not from the salt.
+pub const EPHEMERAL_SALT_PROOF_ARG: &str = "ephemeral_salt";
+
+/// Membership of the domain of an email address, proven with a DKIM-signed email the member
+/// sends with the nonce of its ephemeral key as subject, for organizations without Google
+/// Workspace. The DKIM key the proof returns is checked against the keys registered for the
+/// domain by `create_membership`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EmailDkimProvider;
+
+impl EmailDkimProvider {
+    /// Sender nullifier of the `From` address the zkemail circuit returns with the public
+    /// inputs of `proof`.
+    pub fn proof_nullifier(proof: &[u8]) -> Result<String> {
+        let outputs = ZkEmailPublicOutputs::from_proof(proof)?;
+        Ok(format!("0x{:0>64x}", outputs.sender_nullifier()?))
+    }
+
+    /// Verifies the zkemail proof of a member of `anon_group_id`, with the SRS downloaded.
+    /// `proof_args` holds the salt of the ephemeral key, the DKIM key hash and the sender
+    /// nullifier of the proof. The key lifetime is checked with the default
+    /// `EphemeralExpiryPolicy`.
+    pub fn verify_member_proof(
+        proof: Vec<u8>,
+        anon_group_id: &str,
+        ephemeral_pubkey: &BigUint,
+        ephemeral_pubkey_expiry: &str,
+        proof_args: &HashMap<String, Vec<String>>,
+    ) -> Result<()> {
+        let proof_arg = |key: &str| match proof_args.get(key) {
+            Some(values) if values.len() == 1 => Ok(values[0].clone()),
+            _ => bail!("Member has no {} proof argument", key),
+        };
+        let commitment = EphemeralPubkeyCommitment::from_strings(
+            &ephemeral_pubkey.to_string(),
+            &proof_arg(EPHEMERAL_SALT_PROOF_ARG)?,
+            ephemeral_pubkey_expiry,
+        )?;
+
+        let outputs = verify_email_membership_proof(
+            None,
+            proof,
+            anon_group_id,
+            &commitment,
+            &[proof_arg(DKIM_PUBKEY_HASH_PROOF_ARG)?],
+            &EphemeralExpiryPolicy::default(),
+            Utc::now(),
+        )?;
+        if normalize_nullifier(&proof_arg(NULLIFIER_PROOF_ARG)?)?
+            != format!("0x{:0>64x}", outputs.sender_nullifier()?)
+        {
+            bail!("Nullifier is not the sender nullifier of the proof");
+        }
+        Ok(())
+    }
+}
+
+impl AnonGroupProvider for EmailDkimProvider {
+    fn name() -> String {
+        "email-dkim".to_string()
+    }
+
+    fn get_slug() -> String {
+        "domain".to_string()
+    }
+
+    /// Proves the inputs of `ZkEmailInputs::to_circuit_inputs`, with the SRS downloaded. The
+    /// subject of the email binds the ephemeral key.
+    fn generate_proof(
+        _ephemeral_key: EphemeralKey,
+        inputs: HashMap<String, Vec<String>>,
+    ) -> Vec<u8> {
+        generate_zkemail_proof(None, &inputs).unwrap()
+    }
+
+    fn verify_proof(
+        proof: Vec<u8>,
+        anon_group_id: String,
+        ephemeral_pubkey: BigUint,
+        ephemeral_pubkey_expiry: String,
+        proof_args: HashMap<String, Vec<String>>,
+    ) -> bool {
+        Self::verify_member_proof(
+            proof,
+            &anon_group_id,
+            &ephemeral_pubkey,
+            &ephemeral_pubkey_expiry,
+            &proof_args,
+        )
+        .is_ok()
+    }
+
+    fn get_anon_group(_group_id: String) -> AnonGroup {
+        unimplemented!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proof::zkemail::tests::proof_with_outputs;
+
+    #[test]
+    fn test_verify_member_proof_checks_proof_args() {
+        let pubkey = BigUint::from(12345u64);
+        let expiry = "2099-01-01T00:00:00Z";
+        let commitment =
+            EphemeralPubkeyCommitment::from_strings(&pubkey.to_string(), "2", expiry).unwrap();
+        let proof = proof_with_outputs(7, 42, "me@example.com", &commitment.nonce_decimal());
+
+        let mut proof_args = HashMap::new();
+        let verify = |proof_args: &HashMap<String, Vec<String>>| {
+            EmailDkimProvider::verify_member_proof(
+                proof.clone(),
+                "example.com",
+                &pubkey,
+                expiry,
+                proof_args,
+            )
+            .unwrap_err()
+            .to_string()
+        };
+        assert!(verify(&proof_args).contains(EPHEMERAL_SALT_PROOF_ARG));
+
+        // The wrong salt gives another nonce than the subject
+        proof_args.insert(EPHEMERAL_SALT_PROOF_ARG.to_string(), vec!["3".to_string()]);
+        assert!(verify(&proof_args).contains(DKIM_PUBKEY_HASH_PROOF_ARG));
+        proof_args.insert(
+            DKIM_PUBKEY_HASH_PROOF_ARG.to_string(),
+            vec!["7".to_string()],
+        );
+        assert!(verify(&proof_args).contains("nonce"));
+
+        // The claimed DKIM key must be the one of the proof
+        proof_args.insert(EPHEMERAL_SALT_PROOF_ARG.to_string(), vec!["2".to_string()]);
+        proof_args.insert(
+            DKIM_PUBKEY_HASH_PROOF_ARG.to_string(),
+            vec!["8".to_string()],
+        );
+        assert!(verify(&proof_args).contains("trusted DKIM key"));
+    }
+}
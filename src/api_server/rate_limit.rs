@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use acir::acir_field::FieldElement;
+use anyhow::{bail, Ok, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{group_tree, storage, RateLimitConfig};
+use crate::proof::{
+    anon_post::identity_commitment,
+    ephemeral_commitment::{decimal_to_field, field_to_biguint},
+    rate_limit::{epoch_at, recover_identity_secret, RateLimitShare},
+};
+
+pub const DEFAULT_RATE_LIMIT: u32 = 10;
+pub const DEFAULT_EPOCH_LENGTH_SECS: u64 = 3600;
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            rate_limit: DEFAULT_RATE_LIMIT,
+            epoch_length_secs: DEFAULT_EPOCH_LENGTH_SECS,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn epoch_at(&self, timestamp: DateTime<Utc>) -> u64 {
+        epoch_at(timestamp, self.epoch_length_secs)
+    }
+
+    /// Whether posts of `epoch` are accepted at `now`: the current epoch, or the previous
+    /// one for posts proven just before it ended.
+    pub fn is_open_epoch(&self, epoch: u64, now: DateTime<Utc>) -> bool {
+        let current = self.epoch_at(now);
+        epoch == current || epoch + 1 == current
+    }
+}
+
+/// Share of a post, stored under its rate limiting nullifier.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct StoredShare {
+    group_id: String,
+    message_id: String,
+    epoch: u64,
+    share_x: String,
+    share_y: String,
+}
+
+fn to_decimal(value: &FieldElement) -> String {
+    field_to_biguint(*value).to_string()
+}
+
+fn load_shares(path: &str) -> Result<HashMap<String, StoredShare>> {
+    storage::load_json(path, "rate_limit_shares.json")
+}
+
+fn store_shares(path: &str, map: &HashMap<String, StoredShare>) -> Result<()> {
+    storage::store_json(path, "rate_limit_shares.json", map)
+}
+
+pub fn is_rate_limit_nullifier_used(nullifier: &str, path: &str) -> Result<bool> {
+    Ok(load_shares(path)?.contains_key(nullifier))
+}
+
+/// Records the share of a verified post. A second share under the same nullifier means
+/// the member went over the rate limit: their identity secret is recovered from the two
+/// shares and their commitment is banned from the group.
+pub fn record_rate_limit_share(
+    group_id: String,
+    message_id: String,
+    share: RateLimitShare,
+    path: String,
+) -> Result<()> {
+    let nullifier = to_decimal(&share.nullifier);
+    let mut shares = load_shares(&path)?;
+
+    if let Some(stored) = shares.get(&nullifier) {
+        let previous = RateLimitShare {
+            epoch: stored.epoch,
+            nullifier: share.nullifier,
+            share_x: decimal_to_field(&stored.share_x)?,
+            share_y: decimal_to_field(&stored.share_y)?,
+        };
+        if previous.share_x == share.share_x {
+            bail!("record_rate_limit_share: Message already posted.")
+        }
+
+        let commitment = match recover_identity_secret(&previous, &share) {
+            Some(identity_secret) => to_decimal(&identity_commitment(identity_secret)),
+            None => bail!("record_rate_limit_share: Inconsistent rate limit shares."),
+        };
+        group_tree::ban_member_commitment(stored.group_id.clone(), commitment, path.clone())?;
+        bail!("record_rate_limit_share: Rate limit exceeded, member banned from the group.")
+    }
+
+    shares.insert(
+        nullifier,
+        StoredShare {
+            group_id,
+            message_id,
+            epoch: share.epoch,
+            share_x: to_decimal(&share.share_x),
+            share_y: to_decimal(&share.share_y),
+        },
+    );
+    store_shares(&path, &shares)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::api_server::storage::tests::test_dir;
+
+    #[test]
+    fn test_open_epochs() {
+        let config = RateLimitConfig::default();
+        let now: DateTime<Utc> = "2025-05-01T09:07:57.379Z".parse().unwrap();
+        let current = config.epoch_at(now);
+
+        assert!(config.is_open_epoch(current, now));
+        assert!(config.is_open_epoch(current - 1, now));
+        assert!(!config.is_open_epoch(current - 2, now));
+        assert!(!config.is_open_epoch(current + 1, now));
+    }
+
+    #[test]
+    fn test_rate_limit_slashing() {
+        let path = test_dir();
+        let group = "pse.dev".to_string();
+        let secret = FieldElement::from(12345u64);
+        let commitment = to_decimal(&identity_commitment(secret));
+        group_tree::add_member_commitment(group.clone(), "7".to_string(), path.clone()).unwrap();
+        group_tree::add_member_commitment(group.clone(), commitment.clone(), path.clone()).unwrap();
+
+        let share = |message_id: u32, message: u64| {
+            RateLimitShare::new(secret, 1000, message_id, 2, FieldElement::from(message)).unwrap()
+        };
+
+        // Distinct message ids within the limit are accepted
+        record_rate_limit_share(group.clone(), "a".to_string(), share(0, 1), path.clone()).unwrap();
+        record_rate_limit_share(group.clone(), "b".to_string(), share(1, 2), path.clone()).unwrap();
+        assert!(is_rate_limit_nullifier_used(&to_decimal(&share(0, 1).nullifier), &path).unwrap());
+
+        // Resubmitting a message does not slash
+        let err =
+            record_rate_limit_share(group.clone(), "a".to_string(), share(0, 1), path.clone())
+                .unwrap_err();
+        assert!(err.to_string().contains("Message already posted"));
+        assert!(!group_tree::is_banned_member_commitment(
+            group.clone(),
+            commitment.clone(),
+            path.clone()
+        )
+        .unwrap());
+
+        // A third message has to reuse a message id, which reveals the member
+        let err =
+            record_rate_limit_share(group.clone(), "c".to_string(), share(0, 3), path.clone())
+                .unwrap_err();
+        assert!(err.to_string().contains("Rate limit exceeded"));
+        assert!(group_tree::is_banned_member_commitment(
+            group.clone(),
+            commitment.clone(),
+            path.clone()
+        )
+        .unwrap());
+        assert!(
+            group_tree::get_group_merkle_proof(group.clone(), commitment, path.clone()).is_err()
+        );
+        assert!(group_tree::get_group_merkle_proof(group, "7".to_string(), path.clone()).is_ok());
+
+        let _ = fs::remove_dir_all(path);
+    }
+}
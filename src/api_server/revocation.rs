@@ -0,0 +1,224 @@
+use std::{collections::HashMap, str::FromStr};
+
+use anyhow::{anyhow, bail, Ok, Result};
+use ed25519_dalek::Verifier;
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{storage, KeyRevocation, KeyRotation};
+use crate::proof::ephemeral_key::{parse_ephemeral_public_key, parse_signature};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RevocationRecord {
+    pub revoked_at: String,
+    pub replaced_by: Option<String>,
+}
+
+pub(super) fn sha256_digest(data: &str) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(data.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+pub(super) fn verify_signature(pubkey: &str, payload: &[u8], signature: &str) -> Result<()> {
+    let verifying_key = parse_ephemeral_public_key(pubkey)?;
+    let signature = parse_signature(signature)?;
+    if verifying_key.verify(payload, &signature).is_err() {
+        bail!("Invalid signature for ephemeral key {}", pubkey);
+    }
+    Ok(())
+}
+
+impl KeyRevocation {
+    pub fn signing_payload(&self) -> Vec<u8> {
+        sha256_digest(&format!("revoke_{}_{}", self.pubkey, self.timestamp))
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        verify_signature(&self.pubkey, &self.signing_payload(), &self.signature)
+    }
+}
+
+impl KeyRotation {
+    pub fn signing_payload(&self) -> Vec<u8> {
+        sha256_digest(&format!(
+            "rotate_{}_{}_{}_{}_{}",
+            self.previous_pubkey,
+            self.new_pubkey,
+            self.new_pubkey_hash,
+            self.new_pubkey_expiry,
+            self.timestamp
+        ))
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        verify_signature(
+            &self.previous_pubkey,
+            &self.signing_payload(),
+            &self.signature,
+        )
+    }
+}
+
+fn load_revocations(path: &str) -> Result<HashMap<String, RevocationRecord>> {
+    storage::load_json(path, "revocations.json")
+}
+
+fn store_revocations(path: &str, map: &HashMap<String, RevocationRecord>) -> Result<()> {
+    storage::store_json(path, "revocations.json", map)
+}
+
+/// Decimal form of an ephemeral pubkey the revocations are keyed by, so the same key with
+/// leading zeros is the same entry.
+fn canonical_pubkey(pubkey: &str) -> Result<String> {
+    Ok(BigUint::from_str(pubkey)
+        .map_err(|e| anyhow!("Invalid ephemeral pubkey: {}", e))?
+        .to_string())
+}
+
+pub fn is_revoked(pubkey: &str, path: &str) -> Result<bool> {
+    Ok(load_revocations(path)?.contains_key(&canonical_pubkey(pubkey)?))
+}
+
+pub fn get_revocation(pubkey: &str, path: &str) -> Result<Option<RevocationRecord>> {
+    Ok(load_revocations(path)?
+        .get(&canonical_pubkey(pubkey)?)
+        .cloned())
+}
+
+/// Records a signed revocation. Revoking an already revoked key is a no-op.
+pub fn revoke_key(revocation: KeyRevocation, path: String) -> Result<bool> {
+    revocation.verify()?;
+
+    let pubkey = canonical_pubkey(&revocation.pubkey)?;
+    let mut map = load_revocations(&path)?;
+    if map.contains_key(&pubkey) {
+        return Ok(true);
+    }
+    map.insert(
+        pubkey,
+        RevocationRecord {
+            revoked_at: revocation.timestamp,
+            replaced_by: None,
+        },
+    );
+    store_revocations(&path, &map)?;
+
+    Ok(true)
+}
+
+/// Checks that a rotation is signed by the previous key and that neither key is revoked.
+pub fn check_rotation(rotation: &KeyRotation, path: &str) -> Result<()> {
+    rotation.verify()?;
+
+    let map = load_revocations(path)?;
+    if map.contains_key(&canonical_pubkey(&rotation.previous_pubkey)?) {
+        bail!("record_rotation: Previous ephemeral key is already revoked.");
+    }
+    if map.contains_key(&canonical_pubkey(&rotation.new_pubkey)?) {
+        bail!("record_rotation: New ephemeral key is revoked.");
+    }
+    Ok(())
+}
+
+/// Records a signed rotation, revoking the previous key and linking it to the new one.
+pub fn record_rotation(rotation: KeyRotation, path: String) -> Result<bool> {
+    check_rotation(&rotation, &path)?;
+
+    let mut map = load_revocations(&path)?;
+    map.insert(
+        canonical_pubkey(&rotation.previous_pubkey)?,
+        RevocationRecord {
+            revoked_at: rotation.timestamp,
+            replaced_by: Some(canonical_pubkey(&rotation.new_pubkey)?),
+        },
+    );
+    store_revocations(&path, &map)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::api_server::storage::tests::test_dir;
+    use crate::proof::ephemeral_key::EphemeralKey;
+    use chrono::Duration;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    #[test]
+    fn test_revoke_key() {
+        let path = test_dir();
+        let mut key = EphemeralKey::generate_ephemeral_key().unwrap();
+        let pubkey = key.get_ephemeral_public_key();
+
+        assert!(!is_revoked(&pubkey, &path).unwrap());
+        assert!(revoke_key(key.revoke(), path.clone()).unwrap());
+        assert!(is_revoked(&pubkey, &path).unwrap());
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_is_revoked_ignores_leading_zeros() {
+        let path = test_dir();
+        let mut key = EphemeralKey::generate_ephemeral_key().unwrap();
+        let pubkey = key.get_ephemeral_public_key();
+
+        // Revocation of the pubkey with leading zeros, signed by the key
+        let private_key = BigUint::from_str(&key.get_ephemeral_private_key())
+            .unwrap()
+            .to_bytes_be();
+        let mut private_key_bytes = [0u8; 32];
+        private_key_bytes[32 - private_key.len()..].copy_from_slice(&private_key);
+        let mut revocation = key.revoke();
+        revocation.pubkey = format!("00{}", pubkey);
+        let signature =
+            SigningKey::from_bytes(&private_key_bytes).sign(&revocation.signing_payload());
+        revocation.signature = BigUint::from_bytes_be(&signature.to_bytes()).to_string();
+        assert!(revoke_key(revocation, path.clone()).unwrap());
+        assert!(is_revoked(&pubkey, &path).unwrap());
+        assert!(is_revoked(&format!("0{}", pubkey), &path).unwrap());
+        assert!(is_revoked("not a pubkey", &path).is_err());
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_revoke_key_rejects_forged_signature() {
+        let path = test_dir();
+        let mut key = EphemeralKey::generate_ephemeral_key().unwrap();
+        let other = EphemeralKey::generate_ephemeral_key().unwrap();
+
+        let mut revocation = key.revoke();
+        revocation.pubkey = other.get_ephemeral_public_key();
+
+        assert!(revoke_key(revocation, path.clone()).is_err());
+        assert!(!is_revoked(&other.get_ephemeral_public_key(), &path).unwrap());
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_record_rotation() {
+        let path = test_dir();
+        let mut key = EphemeralKey::generate_ephemeral_key().unwrap();
+        let (next, rotation) = key.rotate(Duration::days(1)).unwrap();
+
+        assert!(record_rotation(rotation.clone(), path.clone()).unwrap());
+
+        let record = get_revocation(&key.get_ephemeral_public_key(), &path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.replaced_by, Some(next.get_ephemeral_public_key()));
+        assert!(!is_revoked(&next.get_ephemeral_public_key(), &path).unwrap());
+
+        // The retired key cannot be rotated a second time
+        assert!(record_rotation(rotation, path.clone()).is_err());
+
+        let _ = fs::remove_dir_all(path);
+    }
+}
@@ -0,0 +1,121 @@
+use std::{fs, io::Write, path::Path, sync::Mutex};
+
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Reads the JSON file `file_name` of the storage directory `path`, or the default value when
+/// the file does not exist yet.
+pub(crate) fn load_json<T: DeserializeOwned + Default>(path: &str, file_name: &str) -> Result<T> {
+    let path = Path::new(path).join(file_name);
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Writes `value` as the JSON file `file_name` of `path`. The JSON goes to a temporary file
+/// renamed over the previous one, so a failed write never leaves a truncated file behind.
+pub(crate) fn store_json<T: Serialize>(path: &str, file_name: &str, value: &T) -> Result<()> {
+    let path = Path::new(path).join(file_name);
+    let tmp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4()));
+    let result = write_file(&tmp_path, serde_json::to_string_pretty(value)?.as_bytes())
+        .and_then(|_| Ok(fs::rename(&tmp_path, &path)?));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Serializes `update_json` calls, so no two of them read and write a file at the same time.
+static UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Applies `update` to the JSON file `file_name` of `path` and stores the result, holding a
+/// lock across the read and the write so a concurrent update is not lost. Nothing is stored
+/// when `update` fails.
+pub(crate) fn update_json<T, R>(
+    path: &str,
+    file_name: &str,
+    update: impl FnOnce(&mut T) -> Result<R>,
+) -> Result<R>
+where
+    T: Serialize + DeserializeOwned + Default,
+{
+    let _guard = UPDATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut value = load_json(path, file_name)?;
+    let result = update(&mut value)?;
+    store_json(path, file_name, &value)?;
+    Ok(result)
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Empty storage directory of a test.
+    pub(crate) fn test_dir() -> String {
+        let dir = std::env::temp_dir().join(format!("api-server-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_store_json_replaces_the_file() {
+        let path = test_dir();
+        let map: HashMap<String, u32> = load_json(&path, "map.json").unwrap();
+        assert!(map.is_empty());
+
+        store_json(&path, "map.json", &HashMap::from([("a".to_string(), 1u32)])).unwrap();
+        store_json(&path, "map.json", &HashMap::from([("b".to_string(), 2u32)])).unwrap();
+        let map: HashMap<String, u32> = load_json(&path, "map.json").unwrap();
+        assert_eq!(map, HashMap::from([("b".to_string(), 2u32)]));
+
+        // Only the stored file is left in the directory
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 1);
+        assert!(store_json(&format!("{}/missing", path), "map.json", &map).is_err());
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 1);
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_update_json_does_not_lose_updates() {
+        let path = test_dir();
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    update_json(&path, "map.json", |map: &mut HashMap<String, u32>| {
+                        map.insert(i.to_string(), i);
+                        Ok(())
+                    })
+                    .unwrap()
+                })
+            })
+            .collect();
+        threads.into_iter().for_each(|t| t.join().unwrap());
+        let map: HashMap<String, u32> = load_json(&path, "map.json").unwrap();
+        assert_eq!(map.len(), 8);
+
+        // A failed update stores nothing
+        let err = update_json::<_, ()>(&path, "map.json", |map: &mut HashMap<String, u32>| {
+            map.clear();
+            anyhow::bail!("rejected")
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "rejected");
+        let map: HashMap<String, u32> = load_json(&path, "map.json").unwrap();
+        assert_eq!(map.len(), 8);
+
+        let _ = fs::remove_dir_all(path);
+    }
+}
@@ -0,0 +1,26 @@
+use anyhow::{bail, Result};
+use mopro_bindings::proof::solidity_verifier::{
+    check_solidity_verifier, generate_solidity_verifier,
+};
+
+const USAGE: &str = "Usage:
+  solidity_verifier generate <circuit.json> <verifier.sol> [bb]
+  solidity_verifier check <circuit.json> <verifier.sol>";
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["generate", circuit_path, verifier_path] => {
+            generate_solidity_verifier("bb", None, circuit_path, verifier_path)?
+        }
+        ["generate", circuit_path, verifier_path, bb_path] => {
+            generate_solidity_verifier(bb_path, None, circuit_path, verifier_path)?
+        }
+        ["check", circuit_path, verifier_path] => {
+            check_solidity_verifier(None, circuit_path, verifier_path)?
+        }
+        _ => bail!(USAGE),
+    }
+    println!("{} matches {}", args[2], args[1]);
+    Ok(())
+}
@@ -2,8 +2,11 @@
 // write some functions and bind them to FFI type
 mopro_ffi::app!();
 
-use api_server::Member;
-use chrono::{DateTime, Utc};
+use api_server::{
+    nullifier::NullifierChainReader, AnonymousMessage, GroupMerkleProof, IdentityCommitment,
+    KeyRevocation, KeyRotation, Member, Message, RateLimitConfig,
+};
+use chrono::{DateTime, Duration, Utc};
 use noir::{
     barretenberg::{
         prove::prove_ultra_honk,
@@ -14,11 +17,35 @@ use noir::{
     witness::from_vec_str_to_witness_map,
 };
 use num_bigint::BigUint;
-use proof::ephemeral_key::EphemeralKey;
-use proof::jwt_proof::{generate_inputs, generate_jwt_proof, JsonWebKey, StorageBlock};
-use std::{collections::HashMap, str::FromStr};
+use proof::anon_post::{generate_anon_post_proof, strip_anon_post_public_inputs, AnonPostInputs};
+use proof::claim_disclosure::{
+    generate_jwt_disclosure_proof, ClaimDisclosure, DisclosedClaims,
+    MAX_DISCLOSURE_PARTIAL_DATA_LENGTH,
+};
+use proof::dkim::parse_dkim_dns_record;
+use proof::ephemeral_commitment::{field_to_biguint, EphemeralPubkeyCommitment};
+use proof::ephemeral_key::{ephemeral_key_lifetime, EphemeralKey};
+use proof::group_nullifier::{
+    generate_group_nullifier_proof, insert_group_nullifier_inputs, GroupNullifierPublicInputs,
+    NullifierScope,
+};
+use proof::jwt_es256::{
+    generate_es256_inputs, generate_es256_jwt_proof, verify_es256_jwt_proof, Es256PublicInputs,
+    P256PublicKey, ES256_CIRCUIT_NAME,
+};
+use proof::jwt_proof::{
+    decode_jwt_payload, generate_inputs, generate_jwt_proof_with_circuit,
+    generate_jwt_proof_with_transcript, insert_iat_input, insert_oauth_client_inputs,
+    pubkey_modulus_from_jwk, EphemeralExpiryPolicy, JsonWebKey, JwtPublicInputs, MembershipType,
+    ProofTranscript, RsaJwtKey, StorageBlock, MAX_JWT_PARTIAL_DATA_LENGTH,
+};
+use proof::key_store::{EncryptedFileKeyStore, KeyStore, PlatformBackedKeyStore, PlatformKeyStore};
+use proof::zkemail::{generate_zkemail_proof, verify_zkemail_proof, ZkEmailInputs};
+use std::{collections::HashMap, sync::Arc};
 
 mod api_server;
+#[cfg(feature = "onchain")]
+pub mod onchain;
 pub mod proof;  // @dev - Expose the proof module for FFI and the smart contract tests.
 
 #[uniffi::export]
@@ -56,94 +83,506 @@ pub fn prove() -> bool {
 }
 
 #[uniffi::export]
-pub fn prove_zkemail(srs_path: String, inputs: HashMap<String, Vec<String>>) -> Vec<u8> {
-    const ZKEMAIL_JSON: &str = include_str!("../circuit/zkemail_test.json");
-    let bytecode_json: serde_json::Value = serde_json::from_str(&ZKEMAIL_JSON).unwrap();
-    let bytecode = bytecode_json["bytecode"].as_str().unwrap();
-
-    // Setup SRS
-    setup_srs_from_bytecode(bytecode, Some(&srs_path), false).unwrap();
-
-    // Define the expected order of witness values based on the ZkEmailInput struct
-    let witness_key_order = [
-        "header_storage",
-        "header_len",
-        "pubkey_modulus",
-        "pubkey_redc",
-        "signature",
-        "date_index",
-        "subject_index",
-        "subject_length",
-        "from_header_index",
-        "from_header_length",
-        "from_address_index",
-        "from_address_length",
-    ];
-
-    let mut witness_vec_string: Vec<String> = Vec::new();
-    for key in witness_key_order {
-        match inputs.get(key) {
-            Some(values) => witness_vec_string.extend(values.iter().cloned()),
-            None => panic!("Missing required input key in HashMap: {}", key),
-        }
+pub fn prove_zkemail(
+    srs_path: String,
+    inputs: HashMap<String, Vec<String>>,
+) -> Result<Vec<u8>, ProofError> {
+    generate_zkemail_proof(Some(&srs_path), &inputs)
+        .map_err(|e| ProofError::InvalidCircuit(e.to_string()))
+}
+
+#[uniffi::export]
+pub fn verify_zkemail(srs_path: String, proof: Vec<u8>) -> Result<bool, ProofError> {
+    verify_zkemail_proof(Some(&srs_path), proof)
+        .map_err(|e| ProofError::InvalidProof(e.to_string()))
+}
+
+/// Inputs of `prove_zkemail` for `eml`, a raw DKIM-signed email, and `dkim_dns_record`, the TXT
+/// record of `<selector>._domainkey.<domain>` of the key of one of its DKIM signatures.
+#[uniffi::export]
+pub fn generate_zkemail_inputs(
+    eml: String,
+    dkim_dns_record: String,
+) -> Result<HashMap<String, Vec<String>>, ProofError> {
+    let modulus = parse_dkim_dns_record(&dkim_dns_record).map_err(invalid_proof_input)?;
+    Ok(ZkEmailInputs::from_eml(&eml, &modulus)
+        .map_err(invalid_proof_input)?
+        .to_circuit_inputs())
+}
+
+/// Subject of the email proving membership of an email domain for an ephemeral key: the nonce
+/// of the key, as in the JWT of `prove_jwt`.
+#[uniffi::export]
+pub fn email_membership_subject(
+    ephemeral_pubkey: String,
+    ephemeral_salt: String,
+    ephemeral_expiry: String,
+) -> Result<String, ProofError> {
+    Ok(EphemeralPubkeyCommitment::from_strings(
+        &ephemeral_pubkey,
+        &ephemeral_salt,
+        &ephemeral_expiry,
+    )
+    .map_err(invalid_proof_input)?
+    .nonce_decimal())
+}
+
+/// Proves membership of the email domain of the sender of `eml`, a raw DKIM-signed email whose
+/// subject is `email_membership_subject` of the ephemeral key. `dkim_pubkey_modulus` is the hex
+/// modulus of the DKIM key, from the `<selector>._domainkey` DNS record of the signing domain.
+#[uniffi::export]
+pub fn prove_email_membership(
+    srs_path: String,
+    eml: String,
+    dkim_pubkey_modulus: String,
+    ephemeral_pubkey: String,
+    ephemeral_salt: String,
+    ephemeral_expiry: String,
+) -> Result<Vec<u8>, ProofError> {
+    let modulus = dkim_pubkey_modulus.trim_start_matches("0x");
+    let modulus = BigUint::parse_bytes(modulus.as_bytes(), 16)
+        .ok_or_else(|| ProofError::InvalidInput("Invalid DKIM pubkey modulus".to_string()))?;
+    let inputs = ZkEmailInputs::from_eml(&eml, &modulus).map_err(invalid_proof_input)?;
+    let nonce = email_membership_subject(ephemeral_pubkey, ephemeral_salt, ephemeral_expiry)?;
+    if inputs.subject().trim() != nonce {
+        return Err(ProofError::InvalidInput(
+            "Email subject is not the nonce of the ephemeral key".to_string(),
+        ));
     }
+    generate_zkemail_proof(Some(&srs_path), &inputs.to_circuit_inputs())
+        .map_err(|e| ProofError::InvalidCircuit(e.to_string()))
+}
 
-    // Convert Vec<String> to Vec<&str> for the function call
-    let witness_vec_str: Vec<&str> = witness_vec_string.iter().map(AsRef::as_ref).collect();
+/// Verifies a proof of `prove_email_membership` and returns the nullifier of its sender as `0x`
+/// hex, the `nullifier` proof argument of the member to register. The ephemeral key must expire within
+/// `expiry_policy` of the date of the email.
+#[uniffi::export]
+pub fn verify_email_membership_proof(
+    srs_path: String,
+    proof: Vec<u8>,
+    domain: String,
+    ephemeral_pubkey: String,
+    ephemeral_salt: String,
+    ephemeral_pubkey_expiry: String,
+    trusted_dkim_pubkey_hashes: Vec<String>,
+    expiry_policy: EphemeralExpiryPolicy,
+) -> Result<String, ProofError> {
+    let commitment = EphemeralPubkeyCommitment::from_strings(
+        &ephemeral_pubkey,
+        &ephemeral_salt,
+        &ephemeral_pubkey_expiry,
+    )
+    .map_err(invalid_proof_input)?;
+    let outputs = proof::zkemail::verify_email_membership_proof(
+        Some(&srs_path),
+        proof,
+        &domain,
+        &commitment,
+        &trusted_dkim_pubkey_hashes,
+        &expiry_policy,
+        Utc::now(),
+    )
+    .map_err(|e| ProofError::InvalidProof(e.to_string()))?;
+    let nullifier = outputs
+        .sender_nullifier()
+        .map_err(|e| ProofError::InvalidProof(e.to_string()))?;
+    Ok(format!("0x{:0>64x}", nullifier))
+}
 
-    let initial_witness = from_vec_str_to_witness_map(witness_vec_str).unwrap();
+//
+// JWT proof functions
+//
 
-    // Start timing the proof generation
-    let start = std::time::Instant::now();
-    let proof = prove_ultra_honk(bytecode, initial_witness, false).unwrap();
+/// Error of the JWT proving exports for an input or a circuit artifact the app can correct.
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum ProofError {
+    #[error("Invalid proof input: {0}")]
+    InvalidInput(String),
+    #[error("Invalid circuit artifact: {0}")]
+    InvalidCircuit(String),
+    #[error("Invalid proof: {0}")]
+    InvalidProof(String),
+}
 
-    println!("Proof generation time: {:?}", start.elapsed());
+fn invalid_proof_input(e: anyhow::Error) -> ProofError {
+    ProofError::InvalidInput(e.to_string())
+}
 
-    proof
+#[uniffi::export]
+pub fn prove_jwt(
+    srs_path: String,
+    ephemeral_pubkey: String,
+    ephemeral_salt: String,
+    ephemeral_expiry: String,
+    token_id: String,
+    jwt: String, // jwt is a stringified JsonWebKey
+    domain: String,
+) -> Result<Vec<u8>, ProofError> {
+    prove_jwt_with_transcript(
+        srs_path,
+        ephemeral_pubkey,
+        ephemeral_salt,
+        ephemeral_expiry,
+        token_id,
+        jwt,
+        domain,
+        ProofTranscript::Poseidon2,
+    )
 }
 
+/// Same as `prove_jwt` with the given transcript. A `Keccak` proof verifies with the
+/// `HonkVerifier` contract, so it can be submitted on-chain as generated.
 #[uniffi::export]
-pub fn verify_zkemail(srs_path: String, proof: Vec<u8>) -> bool {
-    const ZKEMAIL_JSON: &str = include_str!("../circuit/zkemail_test.json");
-    let bytecode_json: serde_json::Value = serde_json::from_str(&ZKEMAIL_JSON).unwrap();
-    let bytecode = bytecode_json["bytecode"].as_str().unwrap();
+pub fn prove_jwt_with_transcript(
+    srs_path: String,
+    ephemeral_pubkey: String,
+    ephemeral_salt: String,
+    ephemeral_expiry: String,
+    token_id: String,
+    jwt: String, // jwt is a stringified JsonWebKey
+    domain: String,
+    transcript: ProofTranscript,
+) -> Result<Vec<u8>, ProofError> {
+    let commitment = EphemeralPubkeyCommitment::from_strings(
+        &ephemeral_pubkey,
+        &ephemeral_salt,
+        &ephemeral_expiry,
+    )
+    .map_err(invalid_proof_input)?;
+    let inputs = jwt_circuit_inputs(
+        &commitment,
+        &token_id,
+        &jwt,
+        &domain,
+        MembershipType::EmailDomain.sha_precompute_keys(),
+        MAX_JWT_PARTIAL_DATA_LENGTH,
+    )
+    .map_err(invalid_proof_input)?;
 
-    // Setup SRS
-    setup_srs_from_bytecode(bytecode, Some(&srs_path), false).unwrap();
+    generate_jwt_proof_with_transcript(srs_path, inputs, transcript)
+        .map_err(|e| ProofError::InvalidCircuit(e.to_string()))
+}
 
-    // Get the verification key
-    let vk = get_honk_verification_key(bytecode, false).unwrap();
+/// Same as `prove_jwt` with the workspace domain circuit at `circuit_path`, which proves the
+/// `hd` claim of a Google Workspace account rather than the domain of its email.
+#[uniffi::export]
+pub fn prove_jwt_workspace_domain(
+    srs_path: String,
+    circuit_path: String,
+    ephemeral_pubkey: String,
+    ephemeral_salt: String,
+    ephemeral_expiry: String,
+    token_id: String,
+    jwt: String, // jwt is a stringified JsonWebKey
+    domain: String,
+) -> Result<Vec<u8>, ProofError> {
+    let hd = decode_jwt_payload(&token_id)
+        .and_then(|payload| MembershipType::WorkspaceDomain.domain_of(&payload))
+        .map_err(invalid_proof_input)?;
+    if hd != domain {
+        return Err(ProofError::InvalidInput(format!(
+            "JWT hd claim {} does not match the domain {}",
+            hd, domain
+        )));
+    }
 
-    // Start timing the proof verification
-    let start = std::time::Instant::now();
-    let verdict = verify_ultra_honk(proof, vk).unwrap();
+    let commitment = EphemeralPubkeyCommitment::from_strings(
+        &ephemeral_pubkey,
+        &ephemeral_salt,
+        &ephemeral_expiry,
+    )
+    .map_err(invalid_proof_input)?;
+    let inputs = jwt_circuit_inputs(
+        &commitment,
+        &token_id,
+        &jwt,
+        &domain,
+        MembershipType::WorkspaceDomain.sha_precompute_keys(),
+        MAX_JWT_PARTIAL_DATA_LENGTH,
+    )
+    .map_err(invalid_proof_input)?;
 
-    println!("Proof verification time: {:?}", start.elapsed());
-    println!("Proof verification verdict: {}", verdict);
+    generate_jwt_proof_with_circuit(srs_path, circuit_path, inputs)
+        .map_err(|e| ProofError::InvalidCircuit(e.to_string()))
+}
 
-    verdict
+/// Name of the circuit proving JWTs signed with `jwt` (a stringified JsonWebKey), from
+/// its `alg` and modulus size, or the ES256 circuit for a P-256 key.
+#[uniffi::export]
+pub fn jwt_circuit_name(jwt: String) -> String {
+    let jwk: JsonWebKey = serde_json::from_str(&jwt).unwrap();
+    if jwk.kty == "EC" {
+        P256PublicKey::from_jwk(&jwk).unwrap();
+        return ES256_CIRCUIT_NAME.to_string();
+    }
+    RsaJwtKey::from_jwk(&jwk).unwrap().circuit_name()
 }
 
-//
-// JWT proof functions
-//
+fn signing_key_circuit_path(circuit_dir: &str, key: &RsaJwtKey) -> String {
+    std::path::Path::new(circuit_dir)
+        .join(format!("{}.json", key.circuit_name()))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Same as `prove_jwt` for a JWT signed with RS256, RS384 or RS512 and a 2048, 3072 or
+/// 4096-bit key. The circuit is `{circuit_dir}/{jwt_circuit_name(jwt)}.json`.
 #[uniffi::export]
-pub fn prove_jwt(
+pub fn prove_jwt_for_signing_key(
     srs_path: String,
+    circuit_dir: String,
     ephemeral_pubkey: String,
     ephemeral_salt: String,
     ephemeral_expiry: String,
     token_id: String,
     jwt: String, // jwt is a stringified JsonWebKey
     domain: String,
-) -> Vec<u8> {
-    let circuit_input = generate_inputs(
+) -> Result<Vec<u8>, ProofError> {
+    let jwk: JsonWebKey = serde_json::from_str(&jwt)
+        .map_err(|e| ProofError::InvalidInput(format!("Invalid JWK: {}", e)))?;
+    let key = RsaJwtKey::from_jwk(&jwk).map_err(invalid_proof_input)?;
+
+    let commitment = EphemeralPubkeyCommitment::from_strings(
+        &ephemeral_pubkey,
+        &ephemeral_salt,
+        &ephemeral_expiry,
+    )
+    .map_err(invalid_proof_input)?;
+    let inputs = jwt_circuit_inputs(
+        &commitment,
+        &token_id,
+        &jwt,
+        &domain,
+        MembershipType::EmailDomain.sha_precompute_keys(),
+        MAX_JWT_PARTIAL_DATA_LENGTH,
+    )
+    .map_err(invalid_proof_input)?;
+
+    generate_jwt_proof_with_circuit(
+        srs_path,
+        signing_key_circuit_path(&circuit_dir, &key),
+        inputs,
+    )
+    .map_err(|e| ProofError::InvalidCircuit(e.to_string()))
+}
+
+/// Verifies a proof of `prove_jwt_for_signing_key`. `jwt_alg` is the `alg` of the signing
+/// key of modulus `google_jwt_pubkey_modulus`, which select the circuit in `circuit_dir`;
+/// the other arguments are the same as `verify_jwt_proof`.
+#[uniffi::export]
+pub fn verify_jwt_proof_for_signing_key(
+    srs_path: String,
+    circuit_dir: String,
+    proof: Vec<u8>,
+    jwt_alg: String,
+    domain: String,
+    google_jwt_pubkey_modulus: String,
+    ephemeral_pubkey: String,
+    ephemeral_pubkey_expiry: String,
+    iss: String,
+    aud: String,
+    jwt_iat: u64,
+    allowed_client_ids: Vec<String>,
+    expiry_policy: EphemeralExpiryPolicy,
+) -> Result<bool, ProofError> {
+    let modulus = pubkey_modulus_from_jwk(&google_jwt_pubkey_modulus)
+        .map_err(|e| ProofError::InvalidInput(format!("Invalid JWT pubkey modulus: {}", e)))?;
+    let key = RsaJwtKey::new(&jwt_alg, &modulus).map_err(invalid_proof_input)?;
+
+    let public_inputs = JwtPublicInputs {
+        domain,
+        google_jwt_pubkey_modulus,
+        ephemeral_pubkey,
+        ephemeral_pubkey_expiry,
+        iss,
+        aud,
+        iat: jwt_iat,
+    };
+    proof::jwt_proof::verify_jwt_proof_with_circuit(
+        srs_path,
+        signing_key_circuit_path(&circuit_dir, &key),
+        proof,
+        &public_inputs,
+        &allowed_client_ids,
+        &expiry_policy,
+        Utc::now(),
+    )
+    .map_err(|e| ProofError::InvalidProof(e.to_string()))
+}
+
+/// Same as `prove_jwt` for a JWT signed with ES256 (ECDSA P-256), with the ES256 circuit
+/// at `circuit_path`.
+#[uniffi::export]
+pub fn prove_jwt_es256(
+    srs_path: String,
+    circuit_path: String,
+    ephemeral_pubkey: String,
+    ephemeral_salt: String,
+    ephemeral_expiry: String,
+    token_id: String,
+    jwt: String, // jwt is a stringified JsonWebKey of an EC key
+    domain: String,
+) -> Result<Vec<u8>, ProofError> {
+    let commitment = EphemeralPubkeyCommitment::from_strings(
+        &ephemeral_pubkey,
+        &ephemeral_salt,
+        &ephemeral_expiry,
+    )
+    .map_err(invalid_proof_input)?;
+    let jwk = serde_json::from_str(&jwt)
+        .map_err(|e| ProofError::InvalidInput(format!("Invalid JWK: {}", e)))?;
+    let mut inputs = generate_es256_inputs(
+        &token_id,
+        &jwk,
+        MembershipType::EmailDomain.sha_precompute_keys(),
+        MAX_JWT_PARTIAL_DATA_LENGTH,
+    )
+    .map_err(invalid_proof_input)?;
+    insert_membership_inputs(&commitment, &token_id, &domain, &mut inputs)
+        .map_err(invalid_proof_input)?;
+
+    generate_es256_jwt_proof(srs_path, circuit_path, inputs)
+        .map_err(|e| ProofError::InvalidCircuit(e.to_string()))
+}
+
+/// Verifies a proof of `prove_jwt_es256`. `jwt_pubkey_x` and `jwt_pubkey_y` are the base64url
+/// coordinates of the signing key and `allowed_issuers` the `iss` of the identity providers
+/// trusted to sign ES256 tokens; the other arguments are the same as `verify_jwt_proof`.
+#[uniffi::export]
+pub fn verify_jwt_es256_proof(
+    srs_path: String,
+    circuit_path: String,
+    proof: Vec<u8>,
+    domain: String,
+    jwt_pubkey_x: String,
+    jwt_pubkey_y: String,
+    ephemeral_pubkey: String,
+    ephemeral_pubkey_expiry: String,
+    iss: String,
+    aud: String,
+    jwt_iat: u64,
+    allowed_issuers: Vec<String>,
+    allowed_client_ids: Vec<String>,
+    expiry_policy: EphemeralExpiryPolicy,
+) -> Result<bool, ProofError> {
+    let jwt_pubkey = P256PublicKey::from_coordinates(&jwt_pubkey_x, &jwt_pubkey_y)
+        .map_err(invalid_proof_input)?;
+
+    let public_inputs = Es256PublicInputs {
+        jwt_pubkey,
+        claims: JwtPublicInputs {
+            domain,
+            google_jwt_pubkey_modulus: String::new(),
+            ephemeral_pubkey,
+            ephemeral_pubkey_expiry,
+            iss,
+            aud,
+            iat: jwt_iat,
+        },
+    };
+    verify_es256_jwt_proof(
+        srs_path,
+        circuit_path,
+        proof,
+        &public_inputs,
+        &allowed_issuers,
+        &allowed_client_ids,
+        &expiry_policy,
+        Utc::now(),
+    )
+    .map_err(|e| ProofError::InvalidProof(e.to_string()))
+}
+
+/// Same as `prove_jwt` with the claim disclosure circuit at `circuit_path`, which also
+/// proves the claims selected in `disclosure`. The proof keeps its public inputs, from
+/// which `verify_jwt_disclosure_proof` reads the disclosed claims.
+#[uniffi::export]
+pub fn prove_jwt_with_disclosure(
+    srs_path: String,
+    circuit_path: String,
+    ephemeral_pubkey: String,
+    ephemeral_salt: String,
+    ephemeral_expiry: String,
+    token_id: String,
+    jwt: String, // jwt is a stringified JsonWebKey
+    domain: String,
+    disclosure: ClaimDisclosure,
+) -> Result<Vec<u8>, ProofError> {
+    let commitment = EphemeralPubkeyCommitment::from_strings(
+        &ephemeral_pubkey,
+        &ephemeral_salt,
+        &ephemeral_expiry,
+    )
+    .map_err(invalid_proof_input)?;
+    let mut inputs = jwt_circuit_inputs(
+        &commitment,
         &token_id,
-        &serde_json::from_str(&jwt).unwrap(),
-        Some(vec!["email", "email_verified", "nonce"]),
-        640,
+        &jwt,
+        &domain,
+        disclosure.sha_precompute_keys(),
+        MAX_DISCLOSURE_PARTIAL_DATA_LENGTH,
     )
-    .unwrap();
+    .map_err(invalid_proof_input)?;
+    decode_jwt_payload(&token_id)
+        .and_then(|payload| disclosure.insert_circuit_inputs(&payload, &mut inputs))
+        .map_err(invalid_proof_input)?;
+
+    generate_jwt_disclosure_proof(srs_path, circuit_path, inputs)
+        .map_err(|e| ProofError::InvalidCircuit(e.to_string()))
+}
+
+/// Same as `prove_jwt` with the group nullifier circuit at `circuit_path`, whose nullifier
+/// is derived from the `sub` claim, the domain and `scope` rather than the ephemeral key.
+#[uniffi::export]
+pub fn prove_jwt_group_nullifier(
+    srs_path: String,
+    circuit_path: String,
+    ephemeral_pubkey: String,
+    ephemeral_salt: String,
+    ephemeral_expiry: String,
+    token_id: String,
+    jwt: String, // jwt is a stringified JsonWebKey
+    domain: String,
+    scope: NullifierScope,
+) -> Result<Vec<u8>, ProofError> {
+    let commitment = EphemeralPubkeyCommitment::from_strings(
+        &ephemeral_pubkey,
+        &ephemeral_salt,
+        &ephemeral_expiry,
+    )
+    .map_err(invalid_proof_input)?;
+    let mut inputs = jwt_circuit_inputs(
+        &commitment,
+        &token_id,
+        &jwt,
+        &domain,
+        proof::group_nullifier::sha_precompute_keys(),
+        MAX_JWT_PARTIAL_DATA_LENGTH,
+    )
+    .map_err(invalid_proof_input)?;
+    decode_jwt_payload(&token_id)
+        .and_then(|payload| insert_group_nullifier_inputs(&payload, &scope, &mut inputs))
+        .map_err(invalid_proof_input)?;
+
+    generate_group_nullifier_proof(srs_path, circuit_path, inputs)
+        .map_err(|e| ProofError::InvalidCircuit(e.to_string()))
+}
+
+fn jwt_circuit_inputs(
+    commitment: &EphemeralPubkeyCommitment,
+    token_id: &str,
+    jwt: &str,
+    domain: &str,
+    sha_precompute_keys: Vec<&str>,
+    max_partial_data_len: usize,
+) -> anyhow::Result<HashMap<String, Vec<String>>> {
+    let jwk = serde_json::from_str(jwt).map_err(|e| anyhow::anyhow!("Invalid JWK: {}", e))?;
+    let circuit_input = generate_inputs(
+        token_id,
+        &jwk,
+        Some(sha_precompute_keys),
+        max_partial_data_len,
+    )?;
 
     let mut inputs: HashMap<String, Vec<String>> = HashMap::new();
     inputs.insert(
@@ -205,37 +644,34 @@ pub fn prove_jwt(
             .collect(),
     );
 
-    let bignuint_public_key = BigUint::from_str(&ephemeral_pubkey).unwrap();
-    // Equivalent to JavaScript: publicKey >> 3n
-    let shifted_public_key = &bignuint_public_key >> 3u32;
-    inputs.insert(
-        "ephemeral_pubkey".to_string(),
-        vec![shifted_public_key.to_string()],
-    );
-
-    inputs.insert(
-        "ephemeral_pubkey_salt".to_string(),
-        vec![ephemeral_salt.to_string()],
-    );
+    insert_membership_inputs(commitment, token_id, domain, &mut inputs)?;
 
-    // Example: parse an ISO8601 datetime string
-    let expiry: DateTime<Utc> = ephemeral_expiry.parse().unwrap();
+    Ok(inputs)
+}
 
-    // Get UNIX timestamp in seconds (already floored)
-    let timestamp_secs = expiry.timestamp();
-    inputs.insert(
-        "ephemeral_pubkey_expiry".to_string(),
-        vec![timestamp_secs.to_string()],
-    );
+/// Adds the ephemeral key, claim and domain inputs shared by the JWT circuits.
+fn insert_membership_inputs(
+    commitment: &EphemeralPubkeyCommitment,
+    token_id: &str,
+    domain: &str,
+    inputs: &mut HashMap<String, Vec<String>>,
+) -> anyhow::Result<()> {
+    // The circuit only accepts a token whose nonce commits to this exact ephemeral key
+    let payload = decode_jwt_payload(token_id)?;
+    if payload["nonce"].as_str() != Some(commitment.nonce_decimal().as_str()) {
+        anyhow::bail!("JWT nonce does not match the ephemeral pubkey commitment");
+    }
+    commitment.insert_circuit_inputs(inputs);
+    insert_oauth_client_inputs(&payload, inputs)?;
+    insert_iat_input(&payload, inputs)?;
 
-    let field = encode_domain_field(domain.as_str(), 64);
+    let field = encode_domain_field(domain, 64);
     inputs.insert(
         "domain_storage".to_string(),
         field.storage.iter().map(|b| b.to_string()).collect(),
     );
     inputs.insert("domain_len".to_string(), vec![field.len.to_string()]);
-
-    generate_jwt_proof(srs_path, inputs)
+    Ok(())
 }
 
 fn encode_domain_field(domain: &str, fixed_len: usize) -> StorageBlock {
@@ -251,6 +687,9 @@ fn encode_domain_field(domain: &str, fixed_len: usize) -> StorageBlock {
     }
 }
 
+/// Verifies a proof of `prove_jwt`. `iss`, `aud` and `jwt_iat` are the issuer, OAuth client
+/// id and issue time the proof is bound to; it is rejected unless `aud` is one of
+/// `allowed_client_ids` and the ephemeral key expiry is within `expiry_policy` of now.
 #[uniffi::export]
 pub fn verify_jwt_proof(
     srs_path: String,
@@ -259,43 +698,529 @@ pub fn verify_jwt_proof(
     google_jwt_pubkey_modulus: String,
     ephemeral_pubkey: String,
     ephemeral_pubkey_expiry: String,
-) -> bool {
-    proof::jwt_proof::verify_jwt_proof(
+    iss: String,
+    aud: String,
+    jwt_iat: u64,
+    allowed_client_ids: Vec<String>,
+    expiry_policy: EphemeralExpiryPolicy,
+) -> Result<bool, ProofError> {
+    verify_jwt_proof_with_transcript(
         srs_path,
         proof,
         domain,
         google_jwt_pubkey_modulus,
         ephemeral_pubkey,
         ephemeral_pubkey_expiry,
+        iss,
+        aud,
+        jwt_iat,
+        allowed_client_ids,
+        expiry_policy,
+        ProofTranscript::Poseidon2,
     )
 }
 
+/// Same as `verify_jwt_proof` for a proof of `prove_jwt_with_transcript`, verified natively
+/// with the verification key of `transcript`.
 #[uniffi::export]
-pub fn generate_ephemeral_key() -> String {
-    let ephemeral_key = loop {
-        if let Some(key) = EphemeralKey::generate_ephemeral_key() {
-            break key;
-        } else {
-            println!("Failed to generate ephemeral key, retrying...");
-        }
+pub fn verify_jwt_proof_with_transcript(
+    srs_path: String,
+    proof: Vec<u8>,
+    domain: String,
+    google_jwt_pubkey_modulus: String,
+    ephemeral_pubkey: String,
+    ephemeral_pubkey_expiry: String,
+    iss: String,
+    aud: String,
+    jwt_iat: u64,
+    allowed_client_ids: Vec<String>,
+    expiry_policy: EphemeralExpiryPolicy,
+    transcript: ProofTranscript,
+) -> Result<bool, ProofError> {
+    let public_inputs = JwtPublicInputs {
+        domain,
+        google_jwt_pubkey_modulus,
+        ephemeral_pubkey,
+        ephemeral_pubkey_expiry,
+        iss,
+        aud,
+        iat: jwt_iat,
     };
-    let json_obj = HashMap::from([
-        (
-            "private_key".to_string(),
-            ephemeral_key.get_ephemeral_private_key(),
-        ),
-        (
-            "public_key".to_string(),
-            ephemeral_key.get_ephemeral_public_key(),
+    proof::jwt_proof::verify_jwt_proof_with_transcript(
+        srs_path,
+        proof,
+        &public_inputs,
+        &allowed_client_ids,
+        &expiry_policy,
+        Utc::now(),
+        transcript,
+    )
+    .map_err(|e| ProofError::InvalidProof(e.to_string()))
+}
+
+/// Verifies a JWT proof of the given membership type; `workspace_circuit_path` is the
+/// circuit of `prove_jwt_workspace_domain`. A proof of one type never verifies as the other.
+#[uniffi::export]
+pub fn verify_jwt_membership_proof(
+    srs_path: String,
+    workspace_circuit_path: String,
+    membership_type: MembershipType,
+    proof: Vec<u8>,
+    domain: String,
+    google_jwt_pubkey_modulus: String,
+    ephemeral_pubkey: String,
+    ephemeral_pubkey_expiry: String,
+    iss: String,
+    aud: String,
+    jwt_iat: u64,
+    allowed_client_ids: Vec<String>,
+    expiry_policy: EphemeralExpiryPolicy,
+) -> Result<bool, ProofError> {
+    let public_inputs = JwtPublicInputs {
+        domain,
+        google_jwt_pubkey_modulus,
+        ephemeral_pubkey,
+        ephemeral_pubkey_expiry,
+        iss,
+        aud,
+        iat: jwt_iat,
+    };
+    let verdict = match membership_type {
+        MembershipType::EmailDomain => proof::jwt_proof::verify_jwt_proof(
+            srs_path,
+            proof,
+            &public_inputs,
+            &allowed_client_ids,
+            &expiry_policy,
+            Utc::now(),
         ),
-        ("salt".to_string(), ephemeral_key.get_ephemeral_salt()),
-        ("expiry".to_string(), ephemeral_key.get_ephemeral_expiry()),
-        (
-            "pubkey_hash".to_string(),
-            ephemeral_key.get_ephemeral_pubkey_hash(),
+        MembershipType::WorkspaceDomain => proof::jwt_proof::verify_jwt_proof_with_circuit(
+            srs_path,
+            workspace_circuit_path,
+            proof,
+            &public_inputs,
+            &allowed_client_ids,
+            &expiry_policy,
+            Utc::now(),
         ),
-    ]);
-    serde_json::to_string(&json_obj).unwrap()
+    };
+    verdict.map_err(|e| ProofError::InvalidProof(e.to_string()))
+}
+
+/// Verifies a proof of `prove_jwt_with_disclosure` and returns the claims it discloses. Like
+/// `verify_jwt_proof`, it rejects a JWT of another issuer or OAuth client than
+/// `allowed_client_ids`, or an ephemeral key outside `expiry_policy`.
+#[uniffi::export]
+pub fn verify_jwt_disclosure_proof(
+    srs_path: String,
+    circuit_path: String,
+    proof: Vec<u8>,
+    domain: String,
+    google_jwt_pubkey_modulus: String,
+    ephemeral_pubkey: String,
+    ephemeral_pubkey_expiry: String,
+    iss: String,
+    aud: String,
+    jwt_iat: u64,
+    allowed_client_ids: Vec<String>,
+    expiry_policy: EphemeralExpiryPolicy,
+) -> Result<DisclosedClaims, ProofError> {
+    let public_inputs = JwtPublicInputs {
+        domain,
+        google_jwt_pubkey_modulus,
+        ephemeral_pubkey,
+        ephemeral_pubkey_expiry,
+        iss,
+        aud,
+        iat: jwt_iat,
+    };
+    proof::claim_disclosure::verify_jwt_disclosure_proof(
+        srs_path,
+        circuit_path,
+        proof,
+        &public_inputs,
+        &allowed_client_ids,
+        &expiry_policy,
+        Utc::now(),
+    )
+    .map_err(|e| ProofError::InvalidProof(e.to_string()))
+}
+
+/// Verifies a proof of `prove_jwt_group_nullifier` for `scope` and returns its nullifier as
+/// `0x` hex, the `nullifier` proof argument of the member to register.
+#[uniffi::export]
+pub fn verify_jwt_group_nullifier_proof(
+    srs_path: String,
+    circuit_path: String,
+    proof: Vec<u8>,
+    domain: String,
+    google_jwt_pubkey_modulus: String,
+    ephemeral_pubkey: String,
+    ephemeral_pubkey_expiry: String,
+    iss: String,
+    aud: String,
+    jwt_iat: u64,
+    scope: NullifierScope,
+    allowed_client_ids: Vec<String>,
+    expiry_policy: EphemeralExpiryPolicy,
+) -> Result<String, ProofError> {
+    let public_inputs = GroupNullifierPublicInputs {
+        jwt: JwtPublicInputs {
+            domain,
+            google_jwt_pubkey_modulus,
+            ephemeral_pubkey,
+            ephemeral_pubkey_expiry,
+            iss,
+            aud,
+            iat: jwt_iat,
+        },
+        scope,
+    };
+    let nullifier = proof::group_nullifier::verify_group_nullifier_proof(
+        srs_path,
+        circuit_path,
+        proof,
+        &public_inputs,
+        &allowed_client_ids,
+        &expiry_policy,
+        Utc::now(),
+    )
+    .map_err(|e| ProofError::InvalidProof(e.to_string()))?;
+    Ok(format!("0x{:0>64x}", nullifier))
+}
+
+//
+// Anonymous posting functions
+//
+
+/// Signs the member commitment of the stored ephemeral key `key_id` for the tree of
+/// `group_id`.
+#[uniffi::export]
+pub fn generate_identity_commitment(
+    store_path: String,
+    key_id: String,
+    passphrase: String,
+    group_id: String,
+) -> IdentityCommitment {
+    let store = EncryptedFileKeyStore::new(store_path, &passphrase);
+    store.load(&key_id).unwrap().identity_commitment(&group_id)
+}
+
+/// Proves membership of the group in `merkle_proof` with the stored ephemeral key `key_id`
+/// for `message` posted to `topic`, and returns the message with the proof and nullifiers to
+/// send instead of a signed message. `rate_limit_message_id` counts the posts of the key in
+/// the epoch of the message, from 0 up to `rate_limit_config.rate_limit`; reusing one within
+/// an epoch gets the key banned.
+#[uniffi::export]
+pub fn prove_anon_post(
+    srs_path: String,
+    circuit_path: String,
+    store_path: String,
+    key_id: String,
+    passphrase: String,
+    merkle_proof: GroupMerkleProof,
+    topic: String,
+    message: Message,
+    rate_limit_config: RateLimitConfig,
+    rate_limit_message_id: u32,
+) -> Result<AnonymousMessage, ProofError> {
+    let store = EncryptedFileKeyStore::new(store_path, &passphrase);
+    let ephemeral_key = store.load(&key_id).map_err(invalid_proof_input)?;
+    let timestamp: DateTime<Utc> = message
+        .timestamp
+        .parse()
+        .map_err(|e| ProofError::InvalidInput(format!("Invalid timestamp: {}", e)))?;
+    let inputs = AnonPostInputs::new(
+        ephemeral_key.identity_secret(),
+        merkle_proof
+            .to_merkle_proof()
+            .map_err(invalid_proof_input)?,
+        &topic,
+        &message,
+        rate_limit_config.epoch_at(timestamp),
+        rate_limit_message_id,
+        rate_limit_config.rate_limit,
+    )
+    .map_err(invalid_proof_input)?;
+
+    let proof = generate_anon_post_proof(srs_path, circuit_path, inputs.to_circuit_inputs())
+        .map_err(|e| ProofError::InvalidCircuit(e.to_string()))?;
+    let public_inputs = inputs.public_inputs();
+
+    Ok(AnonymousMessage {
+        id: message.id,
+        anonGroupId: message.anonGroupId,
+        anonGroupProvider: message.anonGroupProvider,
+        text: message.text,
+        timestamp: message.timestamp,
+        internal: message.internal,
+        likes: message.likes,
+        topic,
+        merkleRoot: merkle_proof.root,
+        nullifier: field_to_biguint(public_inputs.nullifier).to_string(),
+        epoch: public_inputs.epoch,
+        rateLimitNullifier: field_to_biguint(public_inputs.rate_limit_nullifier).to_string(),
+        shareY: field_to_biguint(public_inputs.share_y).to_string(),
+        proof: strip_anon_post_public_inputs(&proof),
+    })
+}
+
+/// Verifies the proof of an anonymous message, without checking the root against the group
+/// or the nullifiers against the ones already used.
+#[uniffi::export]
+pub fn verify_anon_post(
+    srs_path: String,
+    circuit_path: String,
+    message: AnonymousMessage,
+    rate_limit: u32,
+) -> bool {
+    proof::anon_post::verify_anon_post_proof(
+        srs_path,
+        circuit_path,
+        message.proof.clone(),
+        &message.public_inputs(rate_limit).unwrap(),
+    )
+}
+
+#[uniffi::export]
+pub fn default_rate_limit_config() -> RateLimitConfig {
+    RateLimitConfig::default()
+}
+
+#[uniffi::export]
+pub fn default_ephemeral_expiry_policy() -> EphemeralExpiryPolicy {
+    EphemeralExpiryPolicy::default()
+}
+
+/// Error of the ephemeral key and key store exports.
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum EphemeralKeyError {
+    #[error("Invalid ephemeral key lifetime: {0}")]
+    InvalidLifetime(String),
+    #[error("An ephemeral key is already stored as {0}")]
+    KeyIdInUse(String),
+    #[error("Invalid ephemeral key argument: {0}")]
+    InvalidInput(String),
+    #[error("Key store error: {0}")]
+    KeyStore(String),
+    #[error("Ephemeral key rejected: {0}")]
+    Rejected(String),
+}
+
+/// Lifetime of `expiry_secs` seconds, rejected beyond `MAX_EPHEMERAL_KEY_LIFETIME_SECS`.
+fn ephemeral_lifetime(expiry_secs: u64) -> Result<Duration, EphemeralKeyError> {
+    ephemeral_key_lifetime(expiry_secs)
+        .map_err(|e| EphemeralKeyError::InvalidLifetime(e.to_string()))
+}
+
+fn key_store_error(e: anyhow::Error) -> EphemeralKeyError {
+    EphemeralKeyError::KeyStore(e.to_string())
+}
+
+//
+// Key store functions
+//
+// The private key is generated, encrypted and used for signing in Rust; only the key id and
+// the public fields of the ephemeral key are returned to the app.
+
+fn ephemeral_public_key_json(key_id: &str, ephemeral_key: &EphemeralKey) -> serde_json::Value {
+    serde_json::json!({
+        "key_id": key_id,
+        "public_key": ephemeral_key.get_ephemeral_public_key(),
+        "salt": ephemeral_key.get_ephemeral_salt(),
+        "expiry": ephemeral_key.get_ephemeral_expiry(),
+        "pubkey_hash": ephemeral_key.get_ephemeral_pubkey_hash(),
+    })
+}
+
+/// Saves `ephemeral_key` as `key_id`, unless a key is already stored there.
+fn save_new_key(
+    store: &impl KeyStore,
+    key_id: &str,
+    ephemeral_key: &EphemeralKey,
+) -> Result<(), EphemeralKeyError> {
+    if store.contains(key_id).map_err(key_store_error)? {
+        return Err(EphemeralKeyError::KeyIdInUse(key_id.to_string()));
+    }
+    store.save(key_id, ephemeral_key).map_err(key_store_error)
+}
+
+fn generate_in_store(
+    store: &impl KeyStore,
+    key_id: &str,
+    expiry_secs: u64,
+) -> Result<String, EphemeralKeyError> {
+    let ephemeral_key =
+        EphemeralKey::generate_ephemeral_key_with_expiry(ephemeral_lifetime(expiry_secs)?)
+            .ok_or_else(|| {
+                EphemeralKeyError::KeyStore("Failed to generate ephemeral key".into())
+            })?;
+    save_new_key(store, key_id, &ephemeral_key)?;
+    Ok(ephemeral_public_key_json(key_id, &ephemeral_key).to_string())
+}
+
+fn sign_with_store(
+    store: &impl KeyStore,
+    key_id: &str,
+    anon_group_id: String,
+    text: String,
+    internal: bool,
+) -> Result<String, EphemeralKeyError> {
+    let ephemeral_key = store.load(key_id).map_err(key_store_error)?;
+    Ok(api_server::message::sign_message(
+        anon_group_id,
+        text,
+        internal,
+        ephemeral_key.get_ephemeral_public_key(),
+        ephemeral_key.get_ephemeral_private_key(),
+        ephemeral_key.get_ephemeral_expiry(),
+    ))
+}
+
+#[uniffi::export]
+pub fn generate_stored_ephemeral_key(
+    store_path: String,
+    key_id: String,
+    passphrase: String,
+    expiry_secs: u64,
+) -> Result<String, EphemeralKeyError> {
+    let store = EncryptedFileKeyStore::new(store_path, &passphrase);
+    generate_in_store(&store, &key_id, expiry_secs)
+}
+
+/// Re-derives the ephemeral key at `index` from a hex-encoded seed and stores it as `key_id`.
+#[uniffi::export]
+pub fn derive_stored_ephemeral_key(
+    store_path: String,
+    key_id: String,
+    passphrase: String,
+    seed: String,
+    index: u32,
+    expiry: String,
+) -> Result<String, EphemeralKeyError> {
+    let seed = hex::decode(seed.trim_start_matches("0x"))
+        .map_err(|e| EphemeralKeyError::InvalidInput(format!("Invalid seed hex: {}", e)))?;
+    let expiry: DateTime<Utc> = expiry
+        .parse()
+        .map_err(|e| EphemeralKeyError::InvalidInput(format!("Invalid expiry: {}", e)))?;
+    let ephemeral_key = EphemeralKey::derive_ephemeral_key(&seed, index, expiry)
+        .ok_or_else(|| EphemeralKeyError::InvalidInput("Seed is too short".into()))?;
+
+    let store = EncryptedFileKeyStore::new(store_path, &passphrase);
+    save_new_key(&store, &key_id, &ephemeral_key)?;
+    Ok(ephemeral_public_key_json(&key_id, &ephemeral_key).to_string())
+}
+
+#[uniffi::export]
+pub fn get_stored_ephemeral_key(
+    store_path: String,
+    key_id: String,
+    passphrase: String,
+) -> Result<String, EphemeralKeyError> {
+    let store = EncryptedFileKeyStore::new(store_path, &passphrase);
+    let ephemeral_key = store.load(&key_id).map_err(key_store_error)?;
+    Ok(ephemeral_public_key_json(&key_id, &ephemeral_key).to_string())
+}
+
+#[uniffi::export]
+pub fn sign_message_with_stored_key(
+    store_path: String,
+    key_id: String,
+    passphrase: String,
+    anon_group_id: String,
+    text: String,
+    internal: bool,
+) -> Result<String, EphemeralKeyError> {
+    let store = EncryptedFileKeyStore::new(store_path, &passphrase);
+    sign_with_store(&store, &key_id, anon_group_id, text, internal)
+}
+
+/// Generates the next ephemeral key of the stored key `key_id`, stores it as `next_key_id` and
+/// returns its public fields with a `KeyRotation` signed by the current key. The new key's
+/// `pubkey_hash` is the nonce for the JWT used to prove its membership.
+#[uniffi::export]
+pub fn rotate_stored_ephemeral_key(
+    store_path: String,
+    key_id: String,
+    next_key_id: String,
+    passphrase: String,
+    expiry_secs: u64,
+) -> Result<String, EphemeralKeyError> {
+    let lifetime = ephemeral_lifetime(expiry_secs)?;
+    let store = EncryptedFileKeyStore::new(store_path, &passphrase);
+    let mut ephemeral_key = store.load(&key_id).map_err(key_store_error)?;
+    let (next_key, rotation) = ephemeral_key
+        .rotate(lifetime)
+        .ok_or_else(|| EphemeralKeyError::KeyStore("Failed to rotate ephemeral key".into()))?;
+    save_new_key(&store, &next_key_id, &next_key)?;
+
+    Ok(serde_json::json!({
+        "ephemeral_key": ephemeral_public_key_json(&next_key_id, &next_key),
+        "rotation": rotation,
+    })
+    .to_string())
+}
+
+#[uniffi::export]
+pub fn revoke_stored_ephemeral_key(
+    store_path: String,
+    key_id: String,
+    passphrase: String,
+) -> Result<KeyRevocation, EphemeralKeyError> {
+    let store = EncryptedFileKeyStore::new(store_path, &passphrase);
+    Ok(store.load(&key_id).map_err(key_store_error)?.revoke())
+}
+
+#[uniffi::export]
+pub fn export_stored_ephemeral_key(
+    store_path: String,
+    key_id: String,
+    passphrase: String,
+    export_passphrase: String,
+) -> Result<String, EphemeralKeyError> {
+    let store = EncryptedFileKeyStore::new(store_path, &passphrase);
+    store
+        .export_key(&key_id, &export_passphrase)
+        .map_err(key_store_error)
+}
+
+#[uniffi::export]
+pub fn import_stored_ephemeral_key(
+    store_path: String,
+    key_id: String,
+    passphrase: String,
+    exported_key: String,
+    export_passphrase: String,
+) -> Result<String, EphemeralKeyError> {
+    let store = EncryptedFileKeyStore::new(store_path, &passphrase);
+    let ephemeral_key = store
+        .import_key(&key_id, &exported_key, &export_passphrase)
+        .map_err(key_store_error)?;
+    Ok(ephemeral_public_key_json(&key_id, &ephemeral_key).to_string())
+}
+
+#[uniffi::export]
+pub fn generate_platform_stored_ephemeral_key(
+    platform_store: Arc<dyn PlatformKeyStore>,
+    key_id: String,
+    passphrase: String,
+    expiry_secs: u64,
+) -> Result<String, EphemeralKeyError> {
+    let store = PlatformBackedKeyStore::new(platform_store, &passphrase);
+    generate_in_store(&store, &key_id, expiry_secs)
+}
+
+#[uniffi::export]
+pub fn sign_message_with_platform_stored_key(
+    platform_store: Arc<dyn PlatformKeyStore>,
+    key_id: String,
+    passphrase: String,
+    anon_group_id: String,
+    text: String,
+    internal: bool,
+) -> Result<String, EphemeralKeyError> {
+    let store = PlatformBackedKeyStore::new(platform_store, &passphrase);
+    sign_with_store(&store, &key_id, anon_group_id, text, internal)
 }
 
 //
@@ -307,31 +1232,248 @@ pub fn create_membership(member: Member, path: String) -> bool {
     api_server::membership::create_membership(member, path).unwrap()
 }
 
+/// Same as `create_membership`, also rejecting a proof whose nullifier `chain_reader` reads
+/// as recorded by `ZkJwtProofManager`. Google proofs are not verified yet, so their nullifier
+/// is taken from the proof as given.
+#[uniffi::export]
+pub fn create_membership_with_chain_reader(
+    member: Member,
+    path: String,
+    chain_reader: Arc<dyn NullifierChainReader>,
+) -> bool {
+    api_server::membership::create_membership_with_chain_reader(
+        member,
+        path,
+        Some(chain_reader.as_ref()),
+    )
+    .unwrap()
+}
+
+#[uniffi::export]
+pub fn is_membership_nullifier_used(nullifier: String, path: String) -> bool {
+    api_server::nullifier::is_nullifier_used(&nullifier, &path).unwrap()
+}
+
+/// Marks the membership nullifiers `chain_reader` reads as recorded by `ZkJwtProofManager`
+/// since the last sync, and returns how many there were.
+#[uniffi::export]
+pub fn sync_membership_nullifiers(
+    path: String,
+    chain_reader: Arc<dyn NullifierChainReader>,
+) -> u32 {
+    api_server::nullifier::sync_nullifiers(&path, chain_reader.as_ref()).unwrap()
+}
+
+/// Trusts the DKIM key of `pubkey_hash` for the email members of `domain`.
+#[uniffi::export]
+pub fn register_dkim_pubkey_hash(domain: String, pubkey_hash: String, path: String) -> bool {
+    api_server::dkim_registry::register_dkim_pubkey_hash(&domain, &pubkey_hash, &path).unwrap();
+    true
+}
+
+#[uniffi::export]
+pub fn rotate_membership(
+    member: Member,
+    rotation: KeyRotation,
+    path: String,
+) -> Result<bool, EphemeralKeyError> {
+    api_server::membership::rotate_membership(member, rotation, path)
+        .map_err(|e| EphemeralKeyError::Rejected(e.to_string()))
+}
+
+#[uniffi::export]
+pub fn revoke_key(revocation: KeyRevocation, path: String) -> Result<bool, EphemeralKeyError> {
+    api_server::revocation::revoke_key(revocation, path)
+        .map_err(|e| EphemeralKeyError::Rejected(e.to_string()))
+}
+
 #[uniffi::export]
 pub fn post_likes(pub_key: String, msg_id: u32, like: bool, path: String) -> u32 {
     api_server::likes::post_likes(pub_key, msg_id, like, path).unwrap()
 }
 
+#[uniffi::export]
+pub fn create_anonymous_membership(
+    member: Member,
+    commitment: IdentityCommitment,
+    path: String,
+) -> u64 {
+    api_server::membership::create_anonymous_membership(member, commitment, path).unwrap()
+}
+
+#[uniffi::export]
+pub fn post_anonymous_message(
+    message: AnonymousMessage,
+    rate_limit_config: RateLimitConfig,
+    srs_path: String,
+    circuit_path: String,
+    path: String,
+) -> bool {
+    api_server::anon_post::post_anonymous_message(
+        message,
+        rate_limit_config,
+        srs_path,
+        circuit_path,
+        path,
+    )
+    .unwrap()
+}
+
+#[uniffi::export]
+pub fn add_member_commitment(group_id: String, commitment: String, path: String) -> u64 {
+    api_server::group_tree::add_member_commitment(group_id, commitment, path).unwrap()
+}
+
+#[uniffi::export]
+pub fn get_group_root(group_id: String, path: String) -> String {
+    api_server::group_tree::get_group_root(group_id, path).unwrap()
+}
+
+#[uniffi::export]
+pub fn is_known_group_root(group_id: String, root: String, path: String) -> bool {
+    api_server::group_tree::is_known_group_root(group_id, root, path).unwrap()
+}
+
+#[uniffi::export]
+pub fn get_group_merkle_proof(
+    group_id: String,
+    commitment: String,
+    path: String,
+) -> GroupMerkleProof {
+    api_server::group_tree::get_group_merkle_proof(group_id, commitment, path).unwrap()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::proof::jwt_proof::{verify_jwt, JsonWebKey};
+    use crate::proof::jwt_proof::{
+        generate_jwt_proof, verify_jwt, verify_jwt_with_transcript, JsonWebKey,
+    };
+    use crate::proof::solidity::{SolidityProof, JWT_CLAIM_PUBLIC_INPUTS};
 
     use super::*;
     use serde::Deserialize;
     use std::fs;
 
     #[test]
-    #[serial_test::serial]
-    fn test_generate_ephemeral_key() {
-        let ephemeral_key = generate_ephemeral_key();
-        println!("ephemeral_key: {}", ephemeral_key);
+    fn test_stored_ephemeral_key_exports_keep_the_private_key() {
+        let dir = std::env::temp_dir().join(format!("stored-key-{}", uuid::Uuid::new_v4()));
+        let store_path = dir.to_string_lossy().to_string();
+        let passphrase = "passphrase".to_string();
+        let stored = generate_stored_ephemeral_key(
+            store_path.clone(),
+            "current".to_string(),
+            passphrase.clone(),
+            3600,
+        )
+        .unwrap();
+        let private_key = EncryptedFileKeyStore::new(&dir, &passphrase)
+            .load("current")
+            .unwrap()
+            .get_ephemeral_private_key();
+        assert!(!stored.contains(&private_key));
+        assert!(!stored.contains("private_key"));
+
+        let rotated = rotate_stored_ephemeral_key(
+            store_path.clone(),
+            "current".to_string(),
+            "next".to_string(),
+            passphrase.clone(),
+            3600,
+        )
+        .unwrap();
+        assert!(!rotated.contains("private_key"));
+        let rotated: serde_json::Value = serde_json::from_str(&rotated).unwrap();
+        assert_eq!(rotated["ephemeral_key"]["key_id"], "next");
+        let next: serde_json::Value = serde_json::from_str(
+            &get_stored_ephemeral_key(store_path.clone(), "next".to_string(), passphrase.clone())
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(next, rotated["ephemeral_key"]);
+        assert_eq!(rotated["rotation"]["new_pubkey"], next["public_key"]);
+
+        let revocation =
+            revoke_stored_ephemeral_key(store_path.clone(), "current".to_string(), passphrase)
+                .unwrap();
+        let current: serde_json::Value = serde_json::from_str(&stored).unwrap();
+        assert_eq!(current["public_key"], revocation.pubkey);
+        assert!(matches!(
+            revoke_stored_ephemeral_key(
+                store_path,
+                "missing".to_string(),
+                "passphrase".to_string()
+            ),
+            Err(EphemeralKeyError::KeyStore(_))
+        ));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_generate_stored_ephemeral_key_keeps_existing_key() {
+        let dir = std::env::temp_dir().join(format!("stored-key-{}", uuid::Uuid::new_v4()));
+        let store_path = dir.to_string_lossy().to_string();
+        let generate = || {
+            generate_stored_ephemeral_key(
+                store_path.clone(),
+                "default".to_string(),
+                "passphrase".to_string(),
+                3600,
+            )
+        };
+
+        let stored = generate().unwrap();
+        assert!(matches!(
+            generate(),
+            Err(EphemeralKeyError::KeyIdInUse(key_id)) if key_id == "default"
+        ));
+        let loaded =
+            get_stored_ephemeral_key(store_path, "default".to_string(), "passphrase".to_string())
+                .unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&loaded).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&stored).unwrap()
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    /// Google ID token of the `pse.dev` workspace whose nonce commits to the ephemeral key of
+    /// `test_prove_jwt_with_real_data`.
+    const ID_TOKEN: &str = "eyJhbGciOiJSUzI1NiIsImtpZCI6IjA3YjgwYTM2NTQyODUyNWY4YmY3Y2QwODQ2ZDc0YThlZTRlZjM2MjUiLCJ0eXAiOiJKV1QifQ.eyJpc3MiOiJodHRwczovL2FjY291bnRzLmdvb2dsZS5jb20iLCJhenAiOiIxMDA2NzAxMjkzNzQ4LTFpcm1ndTkxMHAybjd2am1vYTQ0MXJhbW02ZGNydmViLmFwcHMuZ29vZ2xldXNlcmNvbnRlbnQuY29tIiwiYXVkIjoiMTAwNjcwMTI5Mzc0OC0xaXJtZ3U5MTBwMm43dmptb2E0NDFyYW1tNmRjcnZlYi5hcHBzLmdvb2dsZXVzZXJjb250ZW50LmNvbSIsInN1YiI6IjEwODUyMjA3NzcyMTgyNjQzOTM2NCIsImhkIjoicHNlLmRldiIsImVtYWlsIjoidml2aWFuamVuZ0Bwc2UuZGV2IiwiZW1haWxfdmVyaWZpZWQiOnRydWUsIm5vbmNlIjoiNjIyNjE4NzE4OTI2NDIwNDg2NDk4MTI3MDAxMDcxODU2NTA0MzIyNDkyNjUwNjU2MjgzOTM2NTk2NDc3ODY5OTY1NDU5ODg3NTQ2IiwibmJmIjoxNzQ2MDAzNzgwLCJpYXQiOjE3NDYwMDQwODAsImV4cCI6MTc0NjAwNzY4MCwianRpIjoiZmZhNGNhMWQ1NDZlZGZlOWI1Mjc0NDY3ZTE5ODJhOTgyMTU5MjRkOSJ9.naERF4rIB5L3a6I3FBC--_b25O2P6zbymSKkXHgOy44PvZU1LLSQ5ORzxHT93YIpbSzx5eF_FAMuXeN9uwLPrpFRw5Zlt9RlrbfQVNHZj1izHxj0IEYBudGESMRKjef7vfvtsYm_s_iHwE5M6H9UATi9xJw4U34iVn664xZFxhtdqbvCXW-YrjNliNK7dSEKAdHgi4MxiASlHXishGVwmFwe116c3HfEcyAJMxv9pGZEhmh4IZ7jVuwiUFEjroZ7svpGLiNx1grEnqGCJa8gcHEI4t1Lpip9d9CMuEctudLiH0Bk_bFofV-s-VvEOdFfEW8WYdE_YhKS0G9qYnevlQ";
+
+    #[test]
+    fn test_prove_exports_reject_mismatched_tokens() {
+        let ephemeral_expiry = "2025-05-07T09:07:57.379Z";
+        let commitment = EphemeralPubkeyCommitment::from_strings(
+            "17302102366996071265028731047581517700208166805377449770193522591062772282670",
+            "1",
+            ephemeral_expiry,
+        )
+        .unwrap();
+        let err = insert_membership_inputs(&commitment, ID_TOKEN, "pse.dev", &mut HashMap::new())
+            .unwrap_err();
+        assert!(err.to_string().contains("nonce does not match"));
+
+        let err = prove_jwt_workspace_domain(
+            "public/jwt-srs.local".to_string(),
+            "circuit/missing.json".to_string(),
+            "1".to_string(),
+            "1".to_string(),
+            ephemeral_expiry.to_string(),
+            ID_TOKEN.to_string(),
+            "{}".to_string(),
+            "example.com".to_string(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ProofError::InvalidInput(e) if e.contains("hd claim")));
     }
 
     #[test]
     #[serial_test::serial]
     fn test_prove_jwt_with_real_data() {
         let srs_path = "public/jwt-srs.local".to_string();
-        let id_token = "eyJhbGciOiJSUzI1NiIsImtpZCI6IjA3YjgwYTM2NTQyODUyNWY4YmY3Y2QwODQ2ZDc0YThlZTRlZjM2MjUiLCJ0eXAiOiJKV1QifQ.eyJpc3MiOiJodHRwczovL2FjY291bnRzLmdvb2dsZS5jb20iLCJhenAiOiIxMDA2NzAxMjkzNzQ4LTFpcm1ndTkxMHAybjd2am1vYTQ0MXJhbW02ZGNydmViLmFwcHMuZ29vZ2xldXNlcmNvbnRlbnQuY29tIiwiYXVkIjoiMTAwNjcwMTI5Mzc0OC0xaXJtZ3U5MTBwMm43dmptb2E0NDFyYW1tNmRjcnZlYi5hcHBzLmdvb2dsZXVzZXJjb250ZW50LmNvbSIsInN1YiI6IjEwODUyMjA3NzcyMTgyNjQzOTM2NCIsImhkIjoicHNlLmRldiIsImVtYWlsIjoidml2aWFuamVuZ0Bwc2UuZGV2IiwiZW1haWxfdmVyaWZpZWQiOnRydWUsIm5vbmNlIjoiNjIyNjE4NzE4OTI2NDIwNDg2NDk4MTI3MDAxMDcxODU2NTA0MzIyNDkyNjUwNjU2MjgzOTM2NTk2NDc3ODY5OTY1NDU5ODg3NTQ2IiwibmJmIjoxNzQ2MDAzNzgwLCJpYXQiOjE3NDYwMDQwODAsImV4cCI6MTc0NjAwNzY4MCwianRpIjoiZmZhNGNhMWQ1NDZlZGZlOWI1Mjc0NDY3ZTE5ODJhOTgyMTU5MjRkOSJ9.naERF4rIB5L3a6I3FBC--_b25O2P6zbymSKkXHgOy44PvZU1LLSQ5ORzxHT93YIpbSzx5eF_FAMuXeN9uwLPrpFRw5Zlt9RlrbfQVNHZj1izHxj0IEYBudGESMRKjef7vfvtsYm_s_iHwE5M6H9UATi9xJw4U34iVn664xZFxhtdqbvCXW-YrjNliNK7dSEKAdHgi4MxiASlHXishGVwmFwe116c3HfEcyAJMxv9pGZEhmh4IZ7jVuwiUFEjroZ7svpGLiNx1grEnqGCJa8gcHEI4t1Lpip9d9CMuEctudLiH0Bk_bFofV-s-VvEOdFfEW8WYdE_YhKS0G9qYnevlQ";
+        let id_token = ID_TOKEN;
 
         let ephemeral_pubkey =
             "17302102366996071265028731047581517700208166805377449770193522591062772282670";
@@ -346,6 +1488,9 @@ mod tests {
             alg: "RS256".to_string(),
             kty: "RSA".to_string(),
             e: "AQAB".to_string(),
+            crv: String::new(),
+            x: String::new(),
+            y: String::new(),
         };
 
         let domain = "pse.dev".to_string();
@@ -360,7 +1505,8 @@ mod tests {
             id_token.to_string(),
             pubkey_str,
             domain,
-        );
+        )
+        .unwrap();
         assert!(!proof.is_empty(), "Proof should not be empty");
 
         // Call verify_jwt as before
@@ -397,6 +1543,9 @@ mod tests {
             ephemeral_pubkey_salt: String,
             ephemeral_pubkey_expiry: String,
             domain: DomainTest,
+            iss: DomainTest,
+            aud: DomainTest,
+            iat: String,
         }
         #[derive(Deserialize, Debug)]
         struct PartialDataTest {
@@ -492,21 +1641,45 @@ mod tests {
             "domain_len".to_string(),
             vec![input_data.domain.len.to_string()],
         );
+        for (key, field) in [("iss", &input_data.iss), ("aud", &input_data.aud)] {
+            inputs.insert(
+                format!("{}_storage", key),
+                field.storage.iter().map(|b| b.to_string()).collect(),
+            );
+            inputs.insert(format!("{}_len", key), vec![field.len.to_string()]);
+        }
+        inputs.insert("iat".to_string(), vec![input_data.iat]);
 
         // Write inputs HashMap to JSON snapshot file
         let json_snapshot = serde_json::to_string_pretty(&inputs).unwrap();
         std::fs::write("public/jwt_input_snapshot_real.json", json_snapshot).unwrap();
 
         // Call prove_jwt
-        let proof = generate_jwt_proof(srs_path.clone(), inputs);
+        let proof = generate_jwt_proof(srs_path.clone(), inputs.clone()).unwrap();
 
         // Ensure proof is not empty (basic check)
         assert!(!proof.is_empty(), "Generated proof is empty");
 
         // Call verify_jwt
-        let is_valid = verify_jwt(srs_path, proof);
+        let is_valid = verify_jwt(srs_path.clone(), proof);
 
         // Assert that verification returns true
         assert!(is_valid, "Proof verification failed");
+
+        // A Keccak proof for the HonkVerifier only verifies with the Keccak verification key
+        let keccak_proof =
+            generate_jwt_proof_with_transcript(srs_path.clone(), inputs, ProofTranscript::Keccak)
+                .unwrap();
+        assert!(SolidityProof::from_honk_proof(
+            &keccak_proof,
+            input_data.jwt_pubkey_modulus_limbs.len() + JWT_CLAIM_PUBLIC_INPUTS
+        )
+        .is_ok());
+        assert!(verify_jwt_with_transcript(
+            srs_path.clone(),
+            keccak_proof.clone(),
+            ProofTranscript::Keccak
+        ));
+        assert!(!verify_jwt(srs_path, keccak_proof));
     }
 }
@@ -0,0 +1,296 @@
+use alloy::{
+    primitives::{keccak256, Address},
+    providers::{DynProvider, Provider, ProviderBuilder},
+    rpc::types::Filter,
+    signers::local::PrivateKeySigner,
+    sol,
+    sol_types::SolEvent,
+};
+use anyhow::Result;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+use super::{connect, contract_error, OnchainError};
+use crate::api_server::{storage, Message};
+
+sol! {
+    #[sol(rpc)]
+    interface IAnonMessageBoard {
+        event MessagePosted(
+            uint256 indexed messageId,
+            bytes32 indexed domainHash,
+            string domain,
+            string text,
+            uint256 timestamp
+        );
+
+        function postMessage(string calldata text) external returns (uint256 messageId);
+    }
+}
+
+/// Provider of the messages posted on the board.
+pub const BOARD_PROVIDER: &str = "google-oauth";
+
+/// Client of an `AnonMessageBoard` deployed at `board_address`. Messages are posted by the
+/// `signer` of the client, which must have recorded a nullifier in `ZkJwtProofManager`. The
+/// posts do not carry the nullifier, but the posts of a signer share its account.
+pub struct AnonMessageBoardClient {
+    provider: DynProvider,
+    board_address: Address,
+}
+
+impl AnonMessageBoardClient {
+    pub fn new(
+        rpc_url: &str,
+        board_address: Address,
+        signer: PrivateKeySigner,
+    ) -> Result<Self, OnchainError> {
+        Ok(AnonMessageBoardClient {
+            provider: connect(rpc_url, signer)?,
+            board_address,
+        })
+    }
+
+    /// Posts `text` as the member of the domain of the nullifier the signer recorded, and
+    /// returns the id of the message once the transaction is mined.
+    pub async fn post_message(&self, text: &str) -> Result<u64, OnchainError> {
+        let board = IAnonMessageBoard::new(self.board_address, &self.provider);
+        let receipt = board
+            .postMessage(text.to_string())
+            .send()
+            .await
+            .map_err(contract_error)?
+            .get_receipt()
+            .await
+            .map_err(|e| OnchainError::Rpc(e.to_string()))?;
+
+        match receipt.decoded_log::<IAnonMessageBoard::MessagePosted>() {
+            Some(log) if receipt.status() => Ok(log.messageId.to::<u64>()),
+            _ => Err(OnchainError::Reverted(format!(
+                "Transaction {} failed",
+                receipt.transaction_hash
+            ))),
+        }
+    }
+}
+
+/// Messages indexed from the board, and the next block to index.
+#[derive(Serialize, Deserialize, Default)]
+struct BoardIndex {
+    next_block: u64,
+    messages: Vec<Message>,
+}
+
+fn load_board_index(path: &str) -> Result<BoardIndex> {
+    storage::load_json(path, "board_messages.json")
+}
+
+fn store_board_index(path: &str, index: &BoardIndex) -> Result<()> {
+    storage::store_json(path, "board_messages.json", index)
+}
+
+/// Messages indexed from the board, oldest first, of `domain` or of every domain.
+pub fn get_board_messages(domain: Option<&str>, path: &str) -> Result<Vec<Message>> {
+    Ok(load_board_index(path)?
+        .messages
+        .into_iter()
+        .filter(|message| domain.map_or(true, |domain| message.anonGroupId == domain))
+        .collect())
+}
+
+/// Follows the `MessagePosted` events of an `AnonMessageBoard`, of `domain` or of every
+/// domain, into the storage at `path`.
+pub struct MessageBoardIndexer {
+    provider: DynProvider,
+    board_address: Address,
+    domain: Option<String>,
+}
+
+impl MessageBoardIndexer {
+    pub fn new(
+        rpc_url: &str,
+        board_address: Address,
+        domain: Option<String>,
+    ) -> Result<Self, OnchainError> {
+        let rpc_url = rpc_url
+            .parse()
+            .map_err(|e| OnchainError::Rpc(format!("Invalid RPC url {}: {}", rpc_url, e)))?;
+        Ok(MessageBoardIndexer {
+            provider: ProviderBuilder::new().on_http(rpc_url).erased(),
+            board_address,
+            domain,
+        })
+    }
+
+    /// Stores the messages posted since the last sync, and returns how many there were.
+    pub async fn sync(&self, path: &str) -> Result<usize> {
+        let mut index = load_board_index(path)?;
+        let latest_block = self
+            .provider
+            .get_block_number()
+            .await
+            .map_err(|e| OnchainError::Rpc(e.to_string()))?;
+        if index.next_block > latest_block {
+            return Ok(0);
+        }
+
+        let mut filter = Filter::new()
+            .address(self.board_address)
+            .event_signature(IAnonMessageBoard::MessagePosted::SIGNATURE_HASH)
+            .from_block(index.next_block)
+            .to_block(latest_block);
+        if let Some(domain) = &self.domain {
+            filter = filter.topic2(keccak256(domain.as_bytes()));
+        }
+        let logs = self
+            .provider
+            .get_logs(&filter)
+            .await
+            .map_err(|e| OnchainError::Rpc(e.to_string()))?;
+
+        let mut indexed = 0;
+        for log in logs {
+            let event = log
+                .log_decode::<IAnonMessageBoard::MessagePosted>()?
+                .inner
+                .data;
+            let timestamp = DateTime::from_timestamp(event.timestamp.to::<i64>(), 0)
+                .ok_or_else(|| anyhow::anyhow!("Invalid message timestamp"))?;
+            index.messages.push(Message {
+                id: event.messageId.to_string(),
+                anonGroupId: event.domain,
+                anonGroupProvider: BOARD_PROVIDER.to_string(),
+                text: event.text,
+                timestamp: timestamp.to_rfc3339(),
+                internal: false,
+                likes: 0,
+            });
+            indexed += 1;
+        }
+
+        index.next_block = latest_block + 1;
+        store_board_index(path, &index)?;
+        Ok(indexed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::api_server::storage::tests::test_dir;
+    use crate::onchain::{
+        proof_manager::{tests::sample_proof, ZkJwtProofManagerClient},
+        tests::{deploy, deploy_proof_manager},
+    };
+    use alloy::node_bindings::Anvil;
+    use chrono::Utc;
+
+    #[test]
+    fn test_get_board_messages_filters_by_domain() {
+        let path = test_dir();
+        assert!(get_board_messages(None, &path).unwrap().is_empty());
+
+        let message = |id: &str, domain: &str| Message {
+            id: id.to_string(),
+            anonGroupId: domain.to_string(),
+            anonGroupProvider: BOARD_PROVIDER.to_string(),
+            text: "hello".to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+            internal: false,
+            likes: 0,
+        };
+        let index = BoardIndex {
+            next_block: 5,
+            messages: vec![message("1", "pse.dev"), message("2", "example.com")],
+        };
+        store_board_index(&path, &index).unwrap();
+
+        assert_eq!(load_board_index(&path).unwrap().next_block, 5);
+        assert_eq!(get_board_messages(None, &path).unwrap().len(), 2);
+        let messages = get_board_messages(Some("example.com"), &path).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, "2");
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[tokio::test]
+    async fn test_message_board_on_anvil() {
+        let anvil = Anvil::new().spawn();
+        let member: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let other: PrivateKeySigner = anvil.keys()[1].clone().into();
+        let provider = connect(&anvil.endpoint(), member.clone()).unwrap();
+        let manager = deploy_proof_manager(&provider, true).await;
+        let board = deploy(
+            &provider,
+            "AnonMessageBoard.sol/AnonMessageBoard.json",
+            Some(manager),
+        )
+        .await;
+
+        // Record the nullifier of a proof for pse.dev, which the verifier accepts
+        let proof = sample_proof("pse.dev", 7);
+        let manager_client =
+            ZkJwtProofManagerClient::new(&anvil.endpoint(), manager, member.clone()).unwrap();
+        let receipt = manager_client
+            .record_membership(&proof, "pse.dev", Utc::now())
+            .await
+            .unwrap();
+        assert!(manager_client
+            .is_nullifier_used(receipt.nullifier_hash)
+            .await
+            .unwrap());
+        assert!(matches!(
+            manager_client
+                .record_membership(&proof, "pse.dev", Utc::now())
+                .await,
+            Err(OnchainError::NullifierAlreadyUsed)
+        ));
+
+        // Only the account that recorded the nullifier posts with it
+        let board_client = AnonMessageBoardClient::new(&anvil.endpoint(), board, member).unwrap();
+        assert_eq!(
+            board_client
+                .post_message("Hello from pse.dev")
+                .await
+                .unwrap(),
+            1
+        );
+        let other_client = AnonMessageBoardClient::new(&anvil.endpoint(), board, other).unwrap();
+        assert!(matches!(
+            other_client.post_message("Not a member").await,
+            Err(OnchainError::NotNullifierHolder)
+        ));
+
+        // The indexer follows the posts of its domain into the storage
+        let path = test_dir();
+        let indexer =
+            MessageBoardIndexer::new(&anvil.endpoint(), board, Some("pse.dev".to_string()))
+                .unwrap();
+        let other_indexer =
+            MessageBoardIndexer::new(&anvil.endpoint(), board, Some("example.com".to_string()))
+                .unwrap();
+        assert_eq!(indexer.sync(&path).await.unwrap(), 1);
+        let other_path = test_dir();
+        assert_eq!(other_indexer.sync(&other_path).await.unwrap(), 0);
+        assert!(get_board_messages(None, &other_path).unwrap().is_empty());
+
+        let messages = get_board_messages(Some("pse.dev"), &path).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, "1");
+        assert_eq!(messages[0].text, "Hello from pse.dev");
+
+        // Later syncs only index the new posts
+        board_client.post_message("Second post").await.unwrap();
+        assert_eq!(indexer.sync(&path).await.unwrap(), 1);
+        assert_eq!(indexer.sync(&path).await.unwrap(), 0);
+        let messages = get_board_messages(None, &path).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].text, "Second post");
+
+        let _ = fs::remove_dir_all(path);
+        let _ = fs::remove_dir_all(other_path);
+    }
+}
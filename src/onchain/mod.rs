@@ -0,0 +1,259 @@
+use alloy::{
+    network::EthereumWallet,
+    providers::{DynProvider, Provider, ProviderBuilder},
+    signers::local::PrivateKeySigner,
+    sol,
+    sol_types::{decode_revert_reason, SolError},
+};
+
+pub mod message_board;
+pub mod proof_manager;
+
+sol! {
+    /// Errors of the generated `HonkVerifier`.
+    interface IHonkVerifier {
+        error ProofLengthWrong();
+        error PublicInputsLengthWrong();
+        error SumcheckFailed();
+        error ShpleminiFailed();
+    }
+}
+
+/// Revert reason of `ZkJwtProofManager` for a nullifier recorded before.
+const NULLIFIER_USED_REASON: &str = "nullifierHash is already used";
+/// Revert reasons of `ZkJwtProofVerifier` and `ZkJwtProofManager` for an invalid proof.
+const INVALID_PROOF_REASONS: [&str; 2] = ["Proof is not valid", "ZK-JWT proof is not valid"];
+/// Revert reason of `ZkJwtProofManager` for a domain or nullifier other than the ones
+/// decoded from the public inputs by `AbiDecoder`.
+const PUBLIC_INPUT_MISMATCH_REASON: &str = "does not match the publicInputs";
+/// Revert reason of `AbiDecoder` for public inputs of another layout.
+const INVALID_PUBLIC_INPUTS_REASON: &str = "A given publicInputs has an invalid";
+/// Revert reason of `AnonMessageBoard` for a sender that recorded no nullifier.
+const NOT_NULLIFIER_HOLDER_REASON: &str = "nullifierHash is not recorded by the sender";
+/// Revert reason of `ZkJwtProofManager` for a proof whose nullifier the sender did not commit
+/// to in an earlier block.
+const NOT_COMMITTED_REASON: &str = "nullifierHash is not committed by the sender";
+
+#[derive(Debug, thiserror::Error)]
+pub enum OnchainError {
+    #[error("The nullifier of the proof is already used")]
+    NullifierAlreadyUsed,
+    #[error("The nullifier is not recorded by the sender")]
+    NotNullifierHolder,
+    #[error("The nullifier is not committed to by the sender in an earlier block")]
+    NotCommitted,
+    #[error("The proof is not valid: {0}")]
+    InvalidProof(String),
+    #[error("The domain or nullifier does not match the proof: {0}")]
+    PublicInputMismatch(String),
+    #[error("Transaction reverted: {0}")]
+    Reverted(String),
+    #[error("Invalid submission: {0}")]
+    InvalidSubmission(String),
+    #[error("RPC error: {0}")]
+    Rpc(String),
+}
+
+/// Provider of the RPC endpoint at `rpc_url`, signing transactions with `signer`.
+pub(crate) fn connect(
+    rpc_url: &str,
+    signer: PrivateKeySigner,
+) -> Result<DynProvider, OnchainError> {
+    let rpc_url = rpc_url
+        .parse()
+        .map_err(|e| OnchainError::Rpc(format!("Invalid RPC url {}: {}", rpc_url, e)))?;
+    Ok(ProviderBuilder::new()
+        .wallet(EthereumWallet::from(signer))
+        .on_http(rpc_url)
+        .erased())
+}
+
+/// Decodes the revert of a call, which the node reports when estimating its gas.
+pub(crate) fn contract_error(error: alloy::contract::Error) -> OnchainError {
+    match error {
+        alloy::contract::Error::TransportError(e) => {
+            match e
+                .as_error_resp()
+                .and_then(|payload| payload.as_revert_data())
+            {
+                Some(data) => decode_revert(&data),
+                None => OnchainError::Rpc(e.to_string()),
+            }
+        }
+        e => OnchainError::Rpc(e.to_string()),
+    }
+}
+
+fn decode_revert(data: &[u8]) -> OnchainError {
+    if data.len() >= 4 {
+        let selector = &data[..4];
+        for (error_selector, name) in [
+            (
+                IHonkVerifier::ProofLengthWrong::SELECTOR,
+                "ProofLengthWrong",
+            ),
+            (
+                IHonkVerifier::PublicInputsLengthWrong::SELECTOR,
+                "PublicInputsLengthWrong",
+            ),
+            (IHonkVerifier::SumcheckFailed::SELECTOR, "SumcheckFailed"),
+            (IHonkVerifier::ShpleminiFailed::SELECTOR, "ShpleminiFailed"),
+        ] {
+            if selector == error_selector {
+                return OnchainError::InvalidProof(name.to_string());
+            }
+        }
+    }
+
+    let reason = match decode_revert_reason(data) {
+        Some(reason) => reason,
+        None => return OnchainError::Reverted(format!("0x{}", hex::encode(data))),
+    };
+    if reason.contains(NULLIFIER_USED_REASON) {
+        OnchainError::NullifierAlreadyUsed
+    } else if reason.contains(NOT_NULLIFIER_HOLDER_REASON) {
+        OnchainError::NotNullifierHolder
+    } else if reason.contains(NOT_COMMITTED_REASON) {
+        OnchainError::NotCommitted
+    } else if reason.contains(PUBLIC_INPUT_MISMATCH_REASON) {
+        OnchainError::PublicInputMismatch(reason)
+    } else if reason.contains(INVALID_PUBLIC_INPUTS_REASON) {
+        OnchainError::InvalidSubmission(reason)
+    } else if INVALID_PROOF_REASONS
+        .iter()
+        .any(|invalid| reason.contains(invalid))
+    {
+        OnchainError::InvalidProof(reason)
+    } else {
+        OnchainError::Reverted(reason)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloy::{
+        network::TransactionBuilder, primitives::Address, rpc::types::TransactionRequest,
+        sol_types::Revert,
+    };
+
+    /// Creation code of a contract returning `true` to any call, standing in for the
+    /// `HonkVerifier` where a valid proof is needed.
+    const ACCEPTING_VERIFIER_CODE: &str = "600a600c600039600a6000f3600160005260206000f3";
+
+    fn revert(reason: &str) -> Vec<u8> {
+        Revert {
+            reason: reason.to_string(),
+        }
+        .abi_encode()
+    }
+
+    async fn deploy_code(provider: &DynProvider, code: Vec<u8>) -> Address {
+        let tx = TransactionRequest::default().with_deploy_code(code);
+        let receipt = provider
+            .send_transaction(tx)
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap();
+        receipt.contract_address.unwrap()
+    }
+
+    /// Deploys the artifact of `contracts/out` with a constructor taking an address.
+    pub(crate) async fn deploy(
+        provider: &DynProvider,
+        artifact: &str,
+        constructor_arg: Option<Address>,
+    ) -> Address {
+        let artifact: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(format!("contracts/out/{}", artifact)).unwrap(),
+        )
+        .unwrap();
+        let mut code = hex::decode(
+            artifact["bytecode"]["object"]
+                .as_str()
+                .unwrap()
+                .trim_start_matches("0x"),
+        )
+        .unwrap();
+        if let Some(address) = constructor_arg {
+            code.extend_from_slice(&[0u8; 12]);
+            code.extend_from_slice(address.as_slice());
+        }
+        deploy_code(provider, code).await
+    }
+
+    /// Deploys a `ZkJwtProofManager` whose `ZkJwtProofVerifier` verifies proofs with the
+    /// generated `HonkVerifier`, or accepts any proof when `accept_any_proof` is set.
+    pub(crate) async fn deploy_proof_manager(
+        provider: &DynProvider,
+        accept_any_proof: bool,
+    ) -> Address {
+        let honk_verifier = if accept_any_proof {
+            deploy_code(provider, hex::decode(ACCEPTING_VERIFIER_CODE).unwrap()).await
+        } else {
+            deploy(provider, "honk_vk.sol/HonkVerifier.json", None).await
+        };
+        let verifier = deploy(
+            provider,
+            "ZkJwtProofVerifier.sol/ZkJwtProofVerifier.json",
+            Some(honk_verifier),
+        )
+        .await;
+        deploy(
+            provider,
+            "ZkJwtProofManager.sol/ZkJwtProofManager.json",
+            Some(verifier),
+        )
+        .await
+    }
+
+    #[test]
+    fn test_decode_revert() {
+        assert!(matches!(
+            decode_revert(&revert(
+                "A given nullifierHash is already used, which means a given proof is already used"
+            )),
+            OnchainError::NullifierAlreadyUsed
+        ));
+        assert!(matches!(
+            decode_revert(&revert(
+                "A given nullifierHash is not recorded by the sender"
+            )),
+            OnchainError::NotNullifierHolder
+        ));
+        assert!(matches!(
+            decode_revert(&revert(
+                "A given nullifierHash is not committed by the sender in an earlier block"
+            )),
+            OnchainError::NotCommitted
+        ));
+        assert!(matches!(
+            decode_revert(&revert("Proof is not valid")),
+            OnchainError::InvalidProof(_)
+        ));
+        assert!(matches!(
+            decode_revert(&IHonkVerifier::SumcheckFailed {}.abi_encode()),
+            OnchainError::InvalidProof(name) if name == "SumcheckFailed"
+        ));
+        assert!(matches!(
+            decode_revert(&revert(
+                "A given domain does not match the publicInputs of a given proof"
+            )),
+            OnchainError::PublicInputMismatch(_)
+        ));
+        assert!(matches!(
+            decode_revert(&revert("A given publicInputs has an invalid length")),
+            OnchainError::InvalidSubmission(_)
+        ));
+        assert!(matches!(
+            decode_revert(&revert("Ownable: caller is not the owner")),
+            OnchainError::Reverted(reason) if reason == "Ownable: caller is not the owner"
+        ));
+        assert!(matches!(
+            decode_revert(&[0xde, 0xad, 0xbe, 0xef]),
+            OnchainError::Reverted(_)
+        ));
+    }
+}
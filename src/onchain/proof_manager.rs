@@ -0,0 +1,371 @@
+use alloy::{
+    primitives::{keccak256, Address, Bytes, FixedBytes, B256},
+    providers::{DynProvider, Provider, ProviderBuilder},
+    rpc::types::TransactionReceipt,
+    signers::local::PrivateKeySigner,
+    sol,
+    sol_types::SolValue,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::{connect, contract_error, OnchainError};
+use crate::{api_server::nullifier::NullifierChainReader, proof::solidity::SolidityProof};
+
+sol! {
+    #[sol(rpc)]
+    interface IZkJwtProofManager {
+        /// `DataType.PublicInput`
+        struct PublicInput {
+            string domain;
+            bytes32 nullifierHash;
+            string createdAt;
+        }
+
+        function recordPublicInputsOfZkJwtProof(
+            bytes calldata proof,
+            bytes32[] calldata publicInputs,
+            PublicInput memory separatedPublicInputs
+        ) external returns (bool);
+
+        function commitToProof(bytes32 commitment) external;
+
+        function nullifiers(bytes32 nullifierHash) external view returns (bool isNullified);
+
+        function commitmentsOfProofs(bytes32 commitment) external view returns (uint256 blockNumber);
+    }
+}
+
+/// `ZkJwtProofManager.proofCommitment` of `recorder` to `nullifier_hash`.
+pub fn proof_commitment(nullifier_hash: B256, recorder: Address) -> B256 {
+    keccak256((nullifier_hash, recorder).abi_encode())
+}
+
+/// Receipt of a proof recorded by `ZkJwtProofManager`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MembershipReceipt {
+    pub transaction_hash: B256,
+    pub block_number: Option<u64>,
+    pub nullifier_hash: B256,
+}
+
+/// Client of a `ZkJwtProofManager` deployed at `manager_address`, which verifies JWT
+/// membership proofs on-chain and records their public inputs. Transactions are signed by
+/// the `signer` of the client, which becomes the holder of the nullifiers it records.
+pub struct ZkJwtProofManagerClient {
+    provider: DynProvider,
+    manager_address: Address,
+    recorder: Address,
+}
+
+impl ZkJwtProofManagerClient {
+    pub fn new(
+        rpc_url: &str,
+        manager_address: Address,
+        signer: PrivateKeySigner,
+    ) -> Result<Self, OnchainError> {
+        Ok(ZkJwtProofManagerClient {
+            recorder: signer.address(),
+            provider: connect(rpc_url, signer)?,
+            manager_address,
+        })
+    }
+
+    /// Records a JWT membership proof for `domain`, and waits for the transactions to be
+    /// mined. The nullifier of the proof is committed to in a transaction of its own first,
+    /// so that the proof is only revealed once the commitment is mined. The contract rejects
+    /// a `domain` other than the one of the proof; the nullifier recorded is the one returned
+    /// by the circuit.
+    pub async fn record_membership(
+        &self,
+        proof: &SolidityProof,
+        domain: &str,
+        created_at: DateTime<Utc>,
+    ) -> Result<MembershipReceipt, OnchainError> {
+        let nullifier_hash = B256::from(proof.nullifier().ok_or_else(|| {
+            OnchainError::InvalidSubmission("Proof has no public inputs".to_string())
+        })?);
+        let separated_public_inputs = IZkJwtProofManager::PublicInput {
+            domain: domain.to_string(),
+            nullifierHash: nullifier_hash,
+            createdAt: created_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+        };
+
+        let manager = IZkJwtProofManager::new(self.manager_address, &self.provider);
+        let commitment = proof_commitment(nullifier_hash, self.recorder);
+        let committed_at = manager
+            .commitmentsOfProofs(commitment)
+            .call()
+            .await
+            .map_err(contract_error)?
+            .blockNumber;
+        if committed_at.is_zero() {
+            let receipt = manager
+                .commitToProof(commitment)
+                .send()
+                .await
+                .map_err(contract_error)?
+                .get_receipt()
+                .await
+                .map_err(|e| OnchainError::Rpc(e.to_string()))?;
+            check_status(&receipt)?;
+        }
+
+        let receipt = manager
+            .recordPublicInputsOfZkJwtProof(
+                Bytes::from(proof.proof.clone()),
+                proof
+                    .public_inputs
+                    .iter()
+                    .map(|field| FixedBytes::from(*field))
+                    .collect(),
+                separated_public_inputs,
+            )
+            .send()
+            .await
+            .map_err(contract_error)?
+            .get_receipt()
+            .await
+            .map_err(|e| OnchainError::Rpc(e.to_string()))?;
+        check_status(&receipt)?;
+
+        Ok(MembershipReceipt {
+            transaction_hash: receipt.transaction_hash,
+            block_number: receipt.block_number,
+            nullifier_hash,
+        })
+    }
+
+    /// Whether a proof with `nullifier_hash` was already recorded.
+    pub async fn is_nullifier_used(&self, nullifier_hash: B256) -> Result<bool, OnchainError> {
+        let manager = IZkJwtProofManager::new(self.manager_address, &self.provider);
+        Ok(manager
+            .nullifiers(nullifier_hash)
+            .call()
+            .await
+            .map_err(contract_error)?
+            .isNullified)
+    }
+}
+
+fn check_status(receipt: &TransactionReceipt) -> Result<(), OnchainError> {
+    if !receipt.status() {
+        return Err(OnchainError::Reverted(format!(
+            "Transaction {} failed",
+            receipt.transaction_hash
+        )));
+    }
+    Ok(())
+}
+
+/// `NullifierChainReader` of the `nullifiers` of a `ZkJwtProofManager` deployed at
+/// `manager_address`, for the off-chain nullifier registry. It blocks on a runtime of its
+/// own, so it is not to be used from async code.
+pub struct ProofManagerNullifierReader {
+    provider: DynProvider,
+    manager_address: Address,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl ProofManagerNullifierReader {
+    pub fn new(rpc_url: &str, manager_address: Address) -> Result<Self, OnchainError> {
+        let rpc_url = rpc_url
+            .parse()
+            .map_err(|e| OnchainError::Rpc(format!("Invalid RPC url {}: {}", rpc_url, e)))?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| OnchainError::Rpc(e.to_string()))?;
+        Ok(ProofManagerNullifierReader {
+            provider: ProviderBuilder::new().on_http(rpc_url).erased(),
+            manager_address,
+            runtime,
+        })
+    }
+}
+
+impl NullifierChainReader for ProofManagerNullifierReader {
+    fn is_nullifier_used(&self, nullifier_hash: String) -> Option<bool> {
+        let nullifier_hash: B256 = nullifier_hash.parse().ok()?;
+        let manager = IZkJwtProofManager::new(self.manager_address, &self.provider);
+        self.runtime
+            .block_on(manager.nullifiers(nullifier_hash).call())
+            .ok()
+            .map(|result| result.isNullified)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::api_server::nullifier::{get_nullifier, record_nullifier, sync_nullifiers};
+    use crate::onchain::tests::deploy_proof_manager;
+    use crate::proof::jwt_proof::{JwtPublicInputs, GOOGLE_ISSUERS};
+    use alloy::node_bindings::Anvil;
+    use num_bigint::BigUint;
+
+    const GOOGLE_JWT_PUBKEY_MODULUS: &str = "03Cww27F2O7JxB5Ji9iT9szfKZ4MK-iPzVpQkdLjCuGKfpjaCVAz9zIQ0-7gbZ-8cJRaSLfByWTGMIHRYiX2efdjz1Z9jck0DK9W3mapFrBPvM7AlRni4lPlwUigDd8zxAMDCheqyK3vCOLFW-1xYHt_YGwv8b0dP7rjujarEYlWjeppO_QMNtXdKdT9eZtBEcj_9ms9W0aLdCFNR5AAR3y0kLkKR1H4DW7vncB46rqCJLenhlCbcW0MZ3asqcjqBQ2t9QMRnY83Zf_pNEsCcXlKp4uOQqEvzjAc9ZSr2sOmd_ESZ_3jMlNkCZ4J41TuG-My5illFcW5LajSKvxD3w";
+
+    /// A proof of the wrong size, with public inputs in the layout of the JWT circuit.
+    pub(crate) fn sample_proof(domain: &str, nullifier: u8) -> SolidityProof {
+        SolidityProof::from_jwt_public_inputs(
+            vec![0u8; 64],
+            &JwtPublicInputs {
+                domain: domain.to_string(),
+                google_jwt_pubkey_modulus: GOOGLE_JWT_PUBKEY_MODULUS.to_string(),
+                ephemeral_pubkey: "1".to_string(),
+                ephemeral_pubkey_expiry: "2025-05-07T09:07:57.379Z".to_string(),
+                iss: GOOGLE_ISSUERS[0].to_string(),
+                aud: "client-id".to_string(),
+                iat: 1746004080,
+            },
+            &BigUint::from(nullifier),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_record_membership_on_anvil() {
+        let anvil = Anvil::new().spawn();
+        let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let provider = connect(&anvil.endpoint(), signer.clone()).unwrap();
+        let manager = deploy_proof_manager(&provider, false).await;
+
+        let client = ZkJwtProofManagerClient::new(&anvil.endpoint(), manager, signer).unwrap();
+        let nullifier_hash = B256::with_last_byte(7);
+        assert!(!client.is_nullifier_used(nullifier_hash).await.unwrap());
+
+        let proof = sample_proof("pse.dev", 7);
+        assert_eq!(proof.nullifier(), Some(nullifier_hash.0));
+
+        // A domain other than the one of the public inputs is rejected
+        let error = client
+            .record_membership(&proof, "example.com", Utc::now())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, OnchainError::PublicInputMismatch(_)),
+            "{:?}",
+            error
+        );
+
+        // So is a nullifier other than the returned one
+        let mismatched_nullifier = IZkJwtProofManager::new(manager, &provider)
+            .recordPublicInputsOfZkJwtProof(
+                Bytes::from(proof.proof.clone()),
+                proof
+                    .public_inputs
+                    .iter()
+                    .map(|field| FixedBytes::from(*field))
+                    .collect(),
+                IZkJwtProofManager::PublicInput {
+                    domain: "pse.dev".to_string(),
+                    nullifierHash: B256::repeat_byte(1),
+                    createdAt: "2025-07-16T07:20:30.000Z".to_string(),
+                },
+            )
+            .send()
+            .await
+            .map_err(contract_error);
+        assert!(
+            matches!(
+                mismatched_nullifier,
+                Err(OnchainError::PublicInputMismatch(_))
+            ),
+            "{:?}",
+            mismatched_nullifier.err()
+        );
+
+        // An account that did not commit to the nullifier cannot record the proof
+        let other: PrivateKeySigner = anvil.keys()[1].clone().into();
+        let uncommitted =
+            IZkJwtProofManager::new(manager, connect(&anvil.endpoint(), other).unwrap())
+                .recordPublicInputsOfZkJwtProof(
+                    Bytes::from(proof.proof.clone()),
+                    proof
+                        .public_inputs
+                        .iter()
+                        .map(|field| FixedBytes::from(*field))
+                        .collect(),
+                    IZkJwtProofManager::PublicInput {
+                        domain: "pse.dev".to_string(),
+                        nullifierHash: nullifier_hash,
+                        createdAt: "2025-07-16T07:20:30.000Z".to_string(),
+                    },
+                )
+                .send()
+                .await
+                .map_err(contract_error);
+        assert!(
+            matches!(uncommitted, Err(OnchainError::NotCommitted)),
+            "{:?}",
+            uncommitted.err()
+        );
+
+        // The matching domain gets to the verifier, which rejects the proof of the wrong size
+        let error = client
+            .record_membership(&proof, "pse.dev", Utc::now())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, OnchainError::InvalidProof(_)),
+            "{:?}",
+            error
+        );
+        assert!(!client.is_nullifier_used(nullifier_hash).await.unwrap());
+
+        let empty = SolidityProof {
+            proof: vec![],
+            public_inputs: vec![],
+        };
+        assert!(matches!(
+            client
+                .record_membership(&empty, "pse.dev", Utc::now())
+                .await,
+            Err(OnchainError::InvalidSubmission(_))
+        ));
+    }
+
+    #[test]
+    fn test_nullifier_reader_on_anvil() {
+        let anvil = Anvil::new().spawn();
+        let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let proof = sample_proof("pse.dev", 9);
+        let manager = runtime.block_on(async {
+            let provider = connect(&anvil.endpoint(), signer.clone()).unwrap();
+            deploy_proof_manager(&provider, true).await
+        });
+
+        // A membership registered off-chain, then recorded on-chain
+        let path = std::env::temp_dir().join(format!("nullifiers-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+        let path = path.to_string_lossy().to_string();
+        let reader = ProofManagerNullifierReader::new(&anvil.endpoint(), manager).unwrap();
+        record_nullifier("9", "pse.dev", &path, Some(&reader)).unwrap();
+        assert_eq!(sync_nullifiers(&path, &reader).unwrap(), 0);
+
+        runtime.block_on(async {
+            let client = ZkJwtProofManagerClient::new(&anvil.endpoint(), manager, signer).unwrap();
+            client
+                .record_membership(&proof, "pse.dev", Utc::now())
+                .await
+                .unwrap();
+        });
+        assert_eq!(sync_nullifiers(&path, &reader).unwrap(), 1);
+        assert!(get_nullifier("9", &path).unwrap().unwrap().onchain);
+
+        // Nor is it registered again by a backend that has not seen it
+        let other_path = std::env::temp_dir().join(format!("nullifiers-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&other_path).unwrap();
+        let other_path = other_path.to_string_lossy().to_string();
+        assert!(record_nullifier("9", "pse.dev", &other_path, Some(&reader)).is_err());
+        assert!(record_nullifier("10", "pse.dev", &other_path, Some(&reader)).is_ok());
+        assert_eq!(
+            reader.is_nullifier_used("not a nullifier".to_string()),
+            None
+        );
+
+        let _ = std::fs::remove_dir_all(path);
+        let _ = std::fs::remove_dir_all(other_path);
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+
+use acir::{acir_field::FieldElement, AcirField};
+use anyhow::{bail, Result};
+use noir::barretenberg::{
+    srs::setup_srs_from_bytecode, utils::get_honk_verification_key, verify::verify_ultra_honk,
+};
+
+use super::{
+    ephemeral_commitment::field_to_biguint,
+    jwt_proof::{
+        extract_proof, flatten_fields_as_array, load_bytecode, prove_circuit_with_witness_keys,
+        reconstruct_honk_proof, ProofTranscript,
+    },
+    merkle_tree::{MerkleProof, MERKLE_TREE_DEPTH},
+    poseidon2::Poseidon2,
+    rate_limit::RateLimitShare,
+};
+use crate::api_server::{message::hash_message, Message};
+
+/// Domain of the member commitments stored in the group trees.
+pub const MEMBER_DOMAIN: &str = "member";
+/// Domain of the identity secret derived from an ephemeral private key.
+pub const IDENTITY_DOMAIN: &str = "identity";
+/// Domain of the posting nullifier.
+pub const POST_NULLIFIER_DOMAIN: &str = "post-nullifier";
+
+/// Public inputs of the anonymous posting circuit: merkle_root, topic, message_hash, epoch,
+/// rate_limit and the returned nullifier, rate_limit_nullifier and share_y.
+pub const ANON_POST_PUBLIC_INPUTS: usize = 8;
+
+/// Identity secret of the holder of an ephemeral key, derived from its private key, which
+/// never leaves the device. The salt of the key is no part of it: the email membership proof
+/// discloses the salt to the server, which could otherwise recompute the secret.
+pub fn identity_secret(ephemeral_private_key: &[u8; 32]) -> FieldElement {
+    // Two 128-bit halves, so no bit of the key is lost to the field modulus
+    Poseidon2::hash_with_domain(
+        IDENTITY_DOMAIN,
+        &[
+            FieldElement::from_be_bytes_reduce(&ephemeral_private_key[..16]),
+            FieldElement::from_be_bytes_reduce(&ephemeral_private_key[16..]),
+        ],
+    )
+}
+
+/// Leaf of the group tree for an identity secret.
+pub fn identity_commitment(identity_secret: FieldElement) -> FieldElement {
+    Poseidon2::hash_with_domain(MEMBER_DOMAIN, &[identity_secret])
+}
+
+/// Nullifier of the post of a member in a topic with message id `rate_limit_message_id` of
+/// `epoch`. A proof is only accepted once, but the `rate_limit` posts of a member per epoch
+/// each have their own nullifier, so they cannot be linked by it.
+pub fn post_nullifier(
+    identity_secret: FieldElement,
+    topic: FieldElement,
+    epoch: u64,
+    rate_limit_message_id: u32,
+) -> FieldElement {
+    Poseidon2::hash_with_domain(
+        POST_NULLIFIER_DOMAIN,
+        &[
+            identity_secret,
+            topic,
+            FieldElement::from(epoch),
+            FieldElement::from(rate_limit_message_id as u64),
+        ],
+    )
+}
+
+pub fn topic_field(topic: &str) -> FieldElement {
+    Poseidon2::hash_bytes(topic.as_bytes())
+}
+
+/// Field the proof is bound to, derived from the same digest the ed25519 signature covers.
+pub fn message_field(message: &Message) -> FieldElement {
+    Poseidon2::hash_bytes(&hash_message(message.clone()))
+}
+
+fn to_decimal(value: FieldElement) -> String {
+    field_to_biguint(value).to_string()
+}
+
+fn to_hex(value: FieldElement) -> String {
+    format!("0x{:0>64x}", field_to_biguint(value))
+}
+
+/// Witness of the anonymous posting circuit (`circuit/anon_post_circuit`).
+#[derive(Clone, Debug)]
+pub struct AnonPostInputs {
+    pub identity_secret: FieldElement,
+    pub merkle_proof: MerkleProof,
+    pub topic: FieldElement,
+    pub message_hash: FieldElement,
+    pub epoch: u64,
+    pub rate_limit_message_id: u32,
+    pub rate_limit: u32,
+}
+
+impl AnonPostInputs {
+    /// `rate_limit_message_id` is the index of the post among the posts of the member in
+    /// `epoch`, below `rate_limit`.
+    pub fn new(
+        identity_secret: FieldElement,
+        merkle_proof: MerkleProof,
+        topic: &str,
+        message: &Message,
+        epoch: u64,
+        rate_limit_message_id: u32,
+        rate_limit: u32,
+    ) -> Result<Self> {
+        if merkle_proof.siblings.len() != MERKLE_TREE_DEPTH {
+            bail!("Merkle proof must have {} siblings", MERKLE_TREE_DEPTH);
+        }
+        if !merkle_proof.verify(identity_commitment(identity_secret)) {
+            bail!("Identity is not a member of the Merkle tree");
+        }
+        if rate_limit_message_id >= rate_limit {
+            bail!(
+                "Message id {} exceeds the rate limit of {} posts per epoch",
+                rate_limit_message_id,
+                rate_limit
+            );
+        }
+
+        Ok(AnonPostInputs {
+            identity_secret,
+            merkle_proof,
+            topic: topic_field(topic),
+            message_hash: message_field(message),
+            epoch,
+            rate_limit_message_id,
+            rate_limit,
+        })
+    }
+
+    pub fn nullifier(&self) -> FieldElement {
+        post_nullifier(
+            self.identity_secret,
+            self.topic,
+            self.epoch,
+            self.rate_limit_message_id,
+        )
+    }
+
+    /// RLN share of the post; its x coordinate is the message hash.
+    pub fn rate_limit_share(&self) -> RateLimitShare {
+        RateLimitShare::new(
+            self.identity_secret,
+            self.epoch,
+            self.rate_limit_message_id,
+            self.rate_limit,
+            self.message_hash,
+        )
+        .unwrap()
+    }
+
+    pub fn public_inputs(&self) -> AnonPostPublicInputs {
+        let share = self.rate_limit_share();
+        AnonPostPublicInputs {
+            merkle_root: self.merkle_proof.root,
+            topic: self.topic,
+            message_hash: self.message_hash,
+            epoch: self.epoch,
+            rate_limit: self.rate_limit,
+            nullifier: self.nullifier(),
+            rate_limit_nullifier: share.nullifier,
+            share_y: share.share_y,
+        }
+    }
+
+    pub fn to_circuit_inputs(&self) -> HashMap<String, Vec<String>> {
+        let mut inputs = HashMap::new();
+        inputs.insert(
+            "identity_secret".to_string(),
+            vec![to_decimal(self.identity_secret)],
+        );
+        inputs.insert(
+            "merkle_leaf_index".to_string(),
+            vec![self.merkle_proof.leaf_index.to_string()],
+        );
+        inputs.insert(
+            "merkle_siblings".to_string(),
+            self.merkle_proof
+                .siblings
+                .iter()
+                .map(|s| to_decimal(*s))
+                .collect(),
+        );
+        inputs.insert(
+            "merkle_root".to_string(),
+            vec![to_decimal(self.merkle_proof.root)],
+        );
+        inputs.insert("topic".to_string(), vec![to_decimal(self.topic)]);
+        inputs.insert(
+            "message_hash".to_string(),
+            vec![to_decimal(self.message_hash)],
+        );
+        inputs.insert(
+            "rate_limit_message_id".to_string(),
+            vec![self.rate_limit_message_id.to_string()],
+        );
+        inputs.insert("epoch".to_string(), vec![self.epoch.to_string()]);
+        inputs.insert("rate_limit".to_string(), vec![self.rate_limit.to_string()]);
+        inputs
+    }
+}
+
+/// Public inputs of an anonymous post, in the order of the circuit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnonPostPublicInputs {
+    pub merkle_root: FieldElement,
+    pub topic: FieldElement,
+    pub message_hash: FieldElement,
+    pub epoch: u64,
+    pub rate_limit: u32,
+    pub nullifier: FieldElement,
+    pub rate_limit_nullifier: FieldElement,
+    pub share_y: FieldElement,
+}
+
+impl AnonPostPublicInputs {
+    pub fn rate_limit_share(&self) -> RateLimitShare {
+        RateLimitShare {
+            epoch: self.epoch,
+            nullifier: self.rate_limit_nullifier,
+            share_x: self.message_hash,
+            share_y: self.share_y,
+        }
+    }
+
+    pub fn to_hex_strings(&self) -> Vec<String> {
+        vec![
+            to_hex(self.merkle_root),
+            to_hex(self.topic),
+            to_hex(self.message_hash),
+            to_hex(FieldElement::from(self.epoch)),
+            to_hex(FieldElement::from(self.rate_limit as u64)),
+            to_hex(self.nullifier),
+            to_hex(self.rate_limit_nullifier),
+            to_hex(self.share_y),
+        ]
+    }
+}
+
+/// Proves an anonymous post. `circuit_path` is the artifact compiled by
+/// `circuit/anon_post_circuit/build.sh`.
+pub fn generate_anon_post_proof(
+    srs_path: String,
+    circuit_path: String,
+    inputs: HashMap<String, Vec<String>>,
+) -> Result<Vec<u8>> {
+    let bytecode = load_bytecode(&circuit_path)?;
+
+    let witness_key_order = [
+        "identity_secret",
+        "merkle_leaf_index",
+        "merkle_siblings",
+        "merkle_root",
+        "topic",
+        "message_hash",
+        "rate_limit_message_id",
+        "epoch",
+        "rate_limit",
+    ];
+
+    prove_circuit_with_witness_keys(
+        &bytecode,
+        Some(&srs_path),
+        &inputs,
+        &witness_key_order,
+        ProofTranscript::Poseidon2,
+    )
+}
+
+/// Strips the public inputs from a proof returned by `generate_anon_post_proof`; the
+/// verifier rebuilds them from the message as `AnonPostPublicInputs`.
+pub fn strip_anon_post_public_inputs(proof: &[u8]) -> Vec<u8> {
+    extract_proof(proof, ANON_POST_PUBLIC_INPUTS * 32).to_vec()
+}
+
+/// Verifies a proof without public inputs against the given public inputs.
+pub fn verify_anon_post_proof(
+    srs_path: String,
+    circuit_path: String,
+    proof: Vec<u8>,
+    public_inputs: &AnonPostPublicInputs,
+) -> bool {
+    let bytecode = load_bytecode(&circuit_path).unwrap();
+
+    // Setup SRS
+    setup_srs_from_bytecode(&bytecode, Some(&srs_path), false).unwrap();
+    let vk = get_honk_verification_key(&bytecode, false).unwrap();
+
+    let proof = reconstruct_honk_proof(
+        &flatten_fields_as_array(&public_inputs.to_hex_strings()),
+        &proof,
+        32,
+    );
+
+    let start = std::time::Instant::now();
+    let verdict = verify_ultra_honk(proof, vk).unwrap();
+    println!("Proof verification time: {:?}", start.elapsed());
+
+    verdict
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proof::merkle_tree::IncrementalMerkleTree;
+    use acir::AcirField;
+
+    fn hex(value: &str) -> FieldElement {
+        FieldElement::from_hex(value).unwrap()
+    }
+
+    fn sample_message() -> Message {
+        Message {
+            id: "a1b2c3d4".to_string(),
+            anonGroupId: "pse.dev".to_string(),
+            anonGroupProvider: "google-oauth".to_string(),
+            text: "Hello, anonymous world!".to_string(),
+            timestamp: "2025-05-01T09:07:57.379Z".to_string(),
+            internal: false,
+            likes: 0,
+        }
+    }
+
+    // Same vector as `test_anon_post` in `circuit/anon_post_circuit/src/main.nr`
+    #[test]
+    fn test_anon_post_vector() {
+        let secret = FieldElement::from(12345u64);
+        let tree = IncrementalMerkleTree::from_leaves(
+            MERKLE_TREE_DEPTH,
+            &[
+                FieldElement::from(7u64),
+                identity_commitment(secret),
+                FieldElement::from(9u64),
+            ],
+        )
+        .unwrap();
+        let proof = tree.proof(1).unwrap();
+
+        assert_eq!(
+            proof.root,
+            hex("0x0a1f20e502e22d6ac9ac93cfac42dda9c5dec72a15204d73e093e0d26b741298")
+        );
+        assert_eq!(proof.siblings[0], FieldElement::from(7u64));
+        assert_eq!(
+            proof.siblings[1],
+            hex("0x2c17542f99762c7898e94e9b124749ed3c113a801d913f970e215cb5790794d2")
+        );
+        assert_eq!(
+            post_nullifier(secret, FieldElement::from(42u64), 1000, 0),
+            hex("0x11ed88959083dd9da4a6dc6726030d1005552dee24ae3ac5db5fc7dc9347522f")
+        );
+
+        let share = RateLimitShare::new(secret, 1000, 0, 10, FieldElement::from(8u64)).unwrap();
+        assert_eq!(
+            share.share_y,
+            hex("0x2b5c12743826fd2ed86e5be4939d98248a0805fbe8df52e8f3181d30db8ab5d3")
+        );
+    }
+
+    #[test]
+    fn test_anon_post_inputs() {
+        let secret = identity_secret(&[1u8; 32]);
+        let mut tree = IncrementalMerkleTree::new(MERKLE_TREE_DEPTH);
+        tree.insert(FieldElement::from(7u64)).unwrap();
+        tree.insert(identity_commitment(secret)).unwrap();
+
+        let message = sample_message();
+        let proof = tree.proof(1).unwrap();
+        let inputs =
+            AnonPostInputs::new(secret, proof.clone(), "general", &message, 485025, 2, 10).unwrap();
+        assert_eq!(
+            inputs.nullifier(),
+            post_nullifier(secret, topic_field("general"), 485025, 2)
+        );
+
+        let circuit_inputs = inputs.to_circuit_inputs();
+        assert_eq!(circuit_inputs["merkle_leaf_index"], vec!["1".to_string()]);
+        assert_eq!(circuit_inputs["merkle_siblings"].len(), MERKLE_TREE_DEPTH);
+        assert_eq!(circuit_inputs["merkle_siblings"][0], "7");
+        assert_eq!(
+            circuit_inputs["rate_limit_message_id"],
+            vec!["2".to_string()]
+        );
+        assert_eq!(circuit_inputs["epoch"], vec!["485025".to_string()]);
+
+        let public_inputs = inputs.public_inputs();
+        assert_eq!(
+            public_inputs.to_hex_strings().len(),
+            ANON_POST_PUBLIC_INPUTS
+        );
+        assert_eq!(public_inputs.rate_limit_share(), inputs.rate_limit_share());
+
+        // A secret that is not in the tree cannot produce a witness
+        let other = identity_secret(&[3u8; 32]);
+        assert!(
+            AnonPostInputs::new(other, proof.clone(), "general", &message, 485025, 2, 10).is_err()
+        );
+        // Neither can a post over the rate limit
+        assert!(AnonPostInputs::new(secret, proof, "general", &message, 485025, 10, 10).is_err());
+    }
+
+    #[test]
+    fn test_nullifier_is_scoped_to_post() {
+        let secret = FieldElement::from(12345u64);
+        let other = FieldElement::from(54321u64);
+        let topic = topic_field("general");
+        let nullifier = post_nullifier(secret, topic, 1000, 0);
+
+        assert_eq!(post_nullifier(secret, topic, 1000, 0), nullifier);
+        assert_ne!(
+            post_nullifier(secret, topic_field("random"), 1000, 0),
+            nullifier
+        );
+        assert_ne!(post_nullifier(other, topic, 1000, 0), nullifier);
+        // Posts of the same member in a topic do not share it
+        assert_ne!(post_nullifier(secret, topic, 1000, 1), nullifier);
+        assert_ne!(post_nullifier(secret, topic, 1001, 0), nullifier);
+    }
+}
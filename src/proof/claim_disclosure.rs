@@ -0,0 +1,415 @@
+use std::{collections::HashMap, str::FromStr};
+
+use acir::acir_field::FieldElement;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use noir::barretenberg::srs::setup_srs_from_bytecode;
+use num_bigint::BigUint;
+
+use super::{
+    ephemeral_commitment::EphemeralPubkeyCommitment,
+    jwt_proof::{
+        bounded_vec_inputs, check_public_inputs, claim_public_inputs, claim_str,
+        flatten_fields_as_array, load_bytecode, modulus_public_inputs,
+        prove_circuit_with_witness_keys, pubkey_modulus_from_jwk, EphemeralExpiryPolicy,
+        JwtPublicInputs, ProofTranscript, EMAIL_DOMAIN_CLAIM_KEYS, IAT_CLAIM_KEY, JWT_WITNESS_KEYS,
+        OAUTH_CLIENT_CLAIM_KEYS,
+    },
+    poseidon2::{pack_bytes, Poseidon2},
+};
+
+/// Signed data length after partial SHA in the disclosure circuit, from `iss` as in the JWT
+/// circuit.
+pub const MAX_DISCLOSURE_PARTIAL_DATA_LENGTH: usize = 1024;
+pub const MAX_HD_LENGTH: usize = 64;
+pub const MAX_SUB_LENGTH: usize = 93;
+
+/// Domain of the `sub` claim hash.
+pub const SUB_DOMAIN: &str = "sub";
+
+/// Public inputs of the disclosure circuit after those of the JWT circuit (`JwtPublicInputs`):
+/// the `hd` flag and bounded vector, the `sub` flag, and the returned nullifier and `sub` hash.
+pub const DISCLOSURE_PUBLIC_INPUTS: usize = (1 + MAX_HD_LENGTH + 1) + 1 + 2;
+
+/// Witness of the disclosure circuit after the JWT circuit one.
+const DISCLOSURE_WITNESS_KEYS: [&str; 4] = ["disclose_hd", "hd_storage", "hd_len", "disclose_sub"];
+
+/// Claims to disclose in a JWT proof, on top of the email domain, `iss`, `aud` and `iat`
+/// which every JWT proof binds.
+#[derive(uniffi::Record, Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClaimDisclosure {
+    pub hd: bool,
+    /// Discloses `hash_sub(sub)`, a stable pseudonym of the account, rather than `sub`.
+    pub sub: bool,
+}
+
+/// Claims read from the public inputs of a disclosure proof; `None` when not disclosed.
+#[derive(uniffi::Record, Clone, Debug, Default, PartialEq)]
+pub struct DisclosedClaims {
+    pub hd: Option<String>,
+    pub sub_hash: Option<String>,
+}
+
+/// Hash of the `sub` claim, `hash_sub` in `circuit/zkjwt_disclosure_circuit/src/utils.nr`.
+pub fn hash_sub(sub: &str) -> Result<FieldElement> {
+    if sub.len() > MAX_SUB_LENGTH {
+        bail!("sub claim longer than {} bytes", MAX_SUB_LENGTH);
+    }
+
+    let mut padded = sub.as_bytes().to_vec();
+    padded.resize(MAX_SUB_LENGTH, 0);
+    let mut input = vec![FieldElement::from(sub.len() as u64)];
+    input.extend(pack_bytes(&padded));
+    Ok(Poseidon2::hash_with_domain(SUB_DOMAIN, &input))
+}
+
+impl ClaimDisclosure {
+    /// Claims the disclosure circuit reads. `generate_inputs` stops the partial SHA before the
+    /// first of them in the payload.
+    pub fn sha_precompute_keys(&self) -> Vec<&'static str> {
+        let mut keys = EMAIL_DOMAIN_CLAIM_KEYS.to_vec();
+        keys.extend(OAUTH_CLIENT_CLAIM_KEYS);
+        keys.push(IAT_CLAIM_KEY);
+        for (disclosed, key) in [(self.hd, "hd"), (self.sub, "sub")] {
+            if disclosed {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    /// Adds the disclosure witness of the JWT `payload` to the JWT circuit inputs.
+    pub fn insert_circuit_inputs(
+        &self,
+        payload: &serde_json::Value,
+        inputs: &mut HashMap<String, Vec<String>>,
+    ) -> Result<()> {
+        let hd = if self.hd {
+            claim_str(payload, "hd")?
+        } else {
+            ""
+        };
+        let (storage, len) = bounded_vec_inputs(hd, MAX_HD_LENGTH)?;
+        inputs.insert("disclose_hd".to_string(), vec![(self.hd as u8).to_string()]);
+        inputs.insert("hd_storage".to_string(), storage);
+        inputs.insert("hd_len".to_string(), vec![len]);
+
+        if self.sub {
+            // Checked here rather than failing in the circuit
+            hash_sub(claim_str(payload, "sub")?)?;
+        }
+        inputs.insert(
+            "disclose_sub".to_string(),
+            vec![(self.sub as u8).to_string()],
+        );
+        Ok(())
+    }
+}
+
+/// Proves a JWT with the disclosure circuit (`circuit/zkjwt_disclosure_circuit`) at
+/// `circuit_path`. The proof keeps its public inputs.
+pub fn generate_jwt_disclosure_proof(
+    srs_path: String,
+    circuit_path: String,
+    inputs: HashMap<String, Vec<String>>,
+) -> Result<Vec<u8>> {
+    let bytecode = load_bytecode(&circuit_path)?;
+    let witness_key_order = [&JWT_WITNESS_KEYS[..], &DISCLOSURE_WITNESS_KEYS[..]].concat();
+
+    prove_circuit_with_witness_keys(
+        &bytecode,
+        Some(&srs_path),
+        &inputs,
+        &witness_key_order,
+        ProofTranscript::Poseidon2,
+    )
+}
+
+fn read_flag(field: &BigUint) -> Result<bool> {
+    match u8::try_from(field) {
+        Ok(0) => Ok(false),
+        Ok(1) => Ok(true),
+        _ => bail!("Invalid disclosure flag {}", field),
+    }
+}
+
+/// Reads a flag followed by a bounded vector of `max_len` bytes, and returns the string when
+/// the flag is set.
+fn read_claim(fields: &[BigUint], max_len: usize) -> Result<Option<String>> {
+    let disclosed = read_flag(&fields[0])?;
+    let len = usize::try_from(&fields[1 + max_len])
+        .ok()
+        .filter(|len| *len <= max_len)
+        .ok_or_else(|| anyhow!("Invalid claim length {}", fields[1 + max_len]))?;
+    let bytes = fields[1..1 + len]
+        .iter()
+        .map(|byte| u8::try_from(byte).map_err(|_| anyhow!("Invalid claim byte {}", byte)))
+        .collect::<Result<Vec<_>>>()?;
+
+    if !disclosed {
+        if len != 0 {
+            bail!("Claim is not disclosed but not empty");
+        }
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8(bytes)?))
+}
+
+/// Disclosed claims in the public inputs of a disclosure proof after the JWT circuit ones,
+/// the last `DISCLOSURE_PUBLIC_INPUTS` of the proof.
+pub fn parse_disclosed_claims(public_inputs: &[BigUint]) -> Result<DisclosedClaims> {
+    if public_inputs.len() != DISCLOSURE_PUBLIC_INPUTS {
+        bail!(
+            "Expected {} disclosure public inputs, got {}",
+            DISCLOSURE_PUBLIC_INPUTS,
+            public_inputs.len()
+        );
+    }
+
+    let hd = read_claim(public_inputs, MAX_HD_LENGTH)?;
+    // disclose_sub, then the returned nullifier and sub hash
+    let fields = &public_inputs[MAX_HD_LENGTH + 2..];
+    let sub_hash = if read_flag(&fields[0])? {
+        Some(fields[2].to_string())
+    } else {
+        None
+    };
+    Ok(DisclosedClaims { hd, sub_hash })
+}
+
+/// Verifies a proof of `generate_jwt_disclosure_proof` for `public_inputs`, and returns the
+/// claims it discloses. As for `verify_jwt_proof`, the proof is rejected unless
+/// `check_public_inputs` accepts its `iss`, `aud` and ephemeral key expiry at `now`.
+pub fn verify_jwt_disclosure_proof(
+    srs_path: String,
+    circuit_path: String,
+    proof: Vec<u8>,
+    public_inputs: &JwtPublicInputs,
+    allowed_client_ids: &[String],
+    expiry_policy: &EphemeralExpiryPolicy,
+    now: DateTime<Utc>,
+) -> Result<DisclosedClaims> {
+    check_public_inputs(public_inputs, allowed_client_ids, expiry_policy, now)?;
+    EphemeralPubkeyCommitment::encode_pubkey(&BigUint::from_str(&public_inputs.ephemeral_pubkey)?)?;
+
+    let jwt_pubkey = pubkey_modulus_from_jwk(&public_inputs.google_jwt_pubkey_modulus)
+        .map_err(|e| anyhow!("Invalid JWT pubkey modulus: {}", e))?;
+    let mut expected = modulus_public_inputs(&jwt_pubkey);
+    expected.extend(claim_public_inputs(public_inputs));
+    let expected = flatten_fields_as_array(&expected);
+
+    let disclosure_start = 4 + expected.len();
+    let disclosure_end = disclosure_start + DISCLOSURE_PUBLIC_INPUTS * 32;
+    if proof.len() < disclosure_end || proof[4..disclosure_start] != expected[..] {
+        bail!("Proof is not for these JWT key, claims and ephemeral key");
+    }
+    let claims = parse_disclosed_claims(
+        &proof[disclosure_start..disclosure_end]
+            .chunks(32)
+            .map(BigUint::from_bytes_be)
+            .collect::<Vec<_>>(),
+    )?;
+
+    let bytecode = load_bytecode(&circuit_path)?;
+    setup_srs_from_bytecode(&bytecode, Some(&srs_path), false).map_err(|e| anyhow!(e))?;
+    let transcript = ProofTranscript::Poseidon2;
+    if !transcript.verify(proof, transcript.verification_key(&bytecode)?)? {
+        bail!("Invalid JWT disclosure proof");
+    }
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proof::ephemeral_commitment::field_to_biguint;
+    use crate::proof::jwt_proof::{decode_jwt_payload, generate_inputs, JsonWebKey};
+    use acir::AcirField;
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+    // Claims of the Google ID token used in `test_prove_jwt_with_real_data`, in the same order
+    const PAYLOAD: &str = r#"{"iss":"https://accounts.google.com","azp":"1006701293748-1irmgu910p2n7vjmoa441ramm6dcrveb.apps.googleusercontent.com","aud":"1006701293748-1irmgu910p2n7vjmoa441ramm6dcrveb.apps.googleusercontent.com","sub":"108522077721826439364","hd":"pse.dev","email":"vivianjeng@pse.dev","email_verified":true,"nonce":"622618718926420486498127001071856504322492650656283936596477869965459887546","nbf":1746003780,"iat":1746004080,"exp":1746007680,"jti":"ffa4ca1d546edfe9b5274467e1982a98215924d9"}"#;
+
+    fn sample_jwt() -> String {
+        format!(
+            "{}.{}.{}",
+            URL_SAFE_NO_PAD.encode(
+                r#"{"alg":"RS256","kid":"07b80a365428525f8bf7cd0846d74a8ee4ef3625","typ":"JWT"}"#
+            ),
+            URL_SAFE_NO_PAD.encode(PAYLOAD),
+            URL_SAFE_NO_PAD.encode([7u8; 256]),
+        )
+    }
+
+    fn sample_jwk() -> JsonWebKey {
+        JsonWebKey {
+            kid: "07b80a365428525f8bf7cd0846d74a8ee4ef3625".to_string(),
+            n: URL_SAFE_NO_PAD.encode([0xffu8; 256]),
+            use_: "sig".to_string(),
+            alg: "RS256".to_string(),
+            kty: "RSA".to_string(),
+            e: "AQAB".to_string(),
+            crv: String::new(),
+            x: String::new(),
+            y: String::new(),
+        }
+    }
+
+    fn field(value: &str) -> BigUint {
+        field_to_biguint(FieldElement::from_hex(value).unwrap())
+    }
+
+    /// Disclosure public inputs of a proof with the given witness.
+    fn public_inputs(inputs: &HashMap<String, Vec<String>>, sub_hash: &str) -> Vec<BigUint> {
+        let mut fields = Vec::new();
+        for key in DISCLOSURE_WITNESS_KEYS {
+            fields.extend(inputs[key].iter().map(|v| BigUint::from_str(v).unwrap()));
+        }
+        fields.push(BigUint::from(1u8)); // nullifier
+        fields.push(BigUint::from_str(sub_hash).unwrap());
+        fields
+    }
+
+    // Same vector as `test_hash_sub` in `circuit/zkjwt_disclosure_circuit/src/utils.nr`
+    #[test]
+    fn test_hash_sub_vector() {
+        assert_eq!(
+            hash_sub("108522077721826439364").unwrap(),
+            FieldElement::from_hex(
+                "0x171925e6451657c4fea669b9d33214c3ec67314f090ec20a460b5f245ce06c23"
+            )
+            .unwrap()
+        );
+        assert!(hash_sub(&"1".repeat(MAX_SUB_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_precompute_cutoff_is_before_iss() {
+        let jwt = sample_jwt();
+        let signed_data_len = jwt.rfind('.').unwrap();
+        let payload_start = jwt.find('.').unwrap() + 1;
+        for disclosure in [
+            ClaimDisclosure::default(),
+            ClaimDisclosure {
+                hd: true,
+                sub: true,
+            },
+        ] {
+            let inputs = generate_inputs(
+                &jwt,
+                &sample_jwk(),
+                Some(disclosure.sha_precompute_keys()),
+                MAX_DISCLOSURE_PARTIAL_DATA_LENGTH,
+            )
+            .unwrap();
+            let cutoff = signed_data_len - inputs.partial_data.unwrap().len;
+
+            // `iss` is the first claim, so the cut-off is in the header whatever is disclosed
+            assert!(cutoff < payload_start);
+            assert_eq!(cutoff % 64, 0);
+            assert_eq!(inputs.base64_decode_offset, payload_start - cutoff);
+        }
+    }
+
+    #[test]
+    fn test_disclosed_claims_round_trip() {
+        let payload = decode_jwt_payload(&sample_jwt()).unwrap();
+        let disclosure = ClaimDisclosure {
+            hd: true,
+            sub: true,
+        };
+        let mut inputs = HashMap::new();
+        disclosure
+            .insert_circuit_inputs(&payload, &mut inputs)
+            .unwrap();
+        assert_eq!(inputs["hd_storage"].len(), MAX_HD_LENGTH);
+
+        let sub_hash = field_to_biguint(hash_sub("108522077721826439364").unwrap()).to_string();
+        let claims = parse_disclosed_claims(&public_inputs(&inputs, &sub_hash)).unwrap();
+        assert_eq!(
+            claims,
+            DisclosedClaims {
+                hd: Some("pse.dev".to_string()),
+                sub_hash: Some(sub_hash),
+            }
+        );
+
+        let mut inputs = HashMap::new();
+        ClaimDisclosure::default()
+            .insert_circuit_inputs(&payload, &mut inputs)
+            .unwrap();
+        assert_eq!(
+            parse_disclosed_claims(&public_inputs(&inputs, "0")).unwrap(),
+            DisclosedClaims::default()
+        );
+
+        // A claim that is not disclosed must be empty
+        let mut fields = public_inputs(&inputs, "0");
+        fields[1] = field("0x61");
+        fields[1 + MAX_HD_LENGTH] = field("0x01");
+        assert!(parse_disclosed_claims(&fields).is_err());
+        assert!(parse_disclosed_claims(&fields[1..]).is_err());
+    }
+
+    #[test]
+    fn test_verify_checks_public_inputs() {
+        let public_inputs = JwtPublicInputs {
+            domain: "pse.dev".to_string(),
+            google_jwt_pubkey_modulus: URL_SAFE_NO_PAD.encode([0xffu8; 256]),
+            ephemeral_pubkey: "1".to_string(),
+            ephemeral_pubkey_expiry: "2025-05-01T10:08:00.000Z".to_string(),
+            iss: "https://accounts.google.com".to_string(),
+            aud: "client-id".to_string(),
+            iat: 1746004080,
+        };
+        let now = "2025-05-01T09:10:00Z".parse::<DateTime<Utc>>().unwrap();
+        let verify = |public_inputs: &JwtPublicInputs, proof: Vec<u8>| {
+            verify_jwt_disclosure_proof(
+                String::new(),
+                "missing.json".to_string(),
+                proof,
+                public_inputs,
+                &["client-id".to_string()],
+                &EphemeralExpiryPolicy::default(),
+                now,
+            )
+            .unwrap_err()
+            .to_string()
+        };
+
+        // The claims are checked against the allow-list and the expiry policy first
+        let other_client = JwtPublicInputs {
+            aud: "other-client-id".to_string(),
+            ..public_inputs.clone()
+        };
+        assert!(verify(&other_client, vec![]).contains("not an allowed OAuth client id"));
+        let long_lived = JwtPublicInputs {
+            ephemeral_pubkey_expiry: "2026-05-01T10:08:00.000Z".to_string(),
+            ..public_inputs.clone()
+        };
+        assert!(verify(&long_lived, vec![]).contains("seconds after the JWT was issued"));
+
+        // Then the public inputs of the proof against them
+        let mut fields = modulus_public_inputs(
+            &pubkey_modulus_from_jwk(&public_inputs.google_jwt_pubkey_modulus).unwrap(),
+        );
+        fields.extend(claim_public_inputs(&other_client));
+        let mut proof = vec![0u8; 4];
+        proof.extend(flatten_fields_as_array(&fields));
+        proof.extend(vec![0u8; DISCLOSURE_PUBLIC_INPUTS * 32]);
+        assert!(verify(&public_inputs, proof).contains("not for these JWT key"));
+    }
+
+    #[test]
+    fn test_missing_claim_cannot_be_disclosed() {
+        let payload: serde_json::Value =
+            serde_json::from_str(r#"{"email":"alice@gmail.com","email_verified":true}"#).unwrap();
+        let disclosure = ClaimDisclosure {
+            hd: true,
+            ..Default::default()
+        };
+        assert!(disclosure
+            .insert_circuit_inputs(&payload, &mut HashMap::new())
+            .is_err());
+    }
+}
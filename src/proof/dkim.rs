@@ -0,0 +1,525 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use num_bigint::BigUint;
+
+/// Public exponent of the RSA DKIM keys the zkemail circuit verifies.
+const DKIM_RSA_EXPONENT: u32 = 65537;
+/// DER of the `rsaEncryption` object identifier of a SubjectPublicKeyInfo.
+const RSA_ENCRYPTION_OID: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+/// Header field of an email as it appears in the message, folding included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderField {
+    pub name: String,
+    /// Value after the colon, with its folding `\r\n` kept
+    pub value: String,
+}
+
+/// Tags of a `DKIM-Signature` header field read by the zkemail input generator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DkimSignature {
+    /// `a=`, the signing algorithm
+    pub algorithm: String,
+    /// Header part of `c=`, the header canonicalization
+    pub header_canonicalization: String,
+    /// `d=`, the signing domain
+    pub domain: String,
+    /// `s=`, the selector of the DNS record of the public key
+    pub selector: String,
+    /// `h=`, the names of the signed header fields in lowercase
+    pub signed_headers: Vec<String>,
+    /// `b=`, the RSA signature
+    pub signature: Vec<u8>,
+}
+
+/// Index and length of a byte sequence in the canonicalized header, `Sequence` in zkemail.nr.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeaderSequence {
+    pub index: usize,
+    pub length: usize,
+}
+
+impl DkimSignature {
+    /// Parses the value of a `DKIM-Signature` header field.
+    pub fn parse(value: &str) -> Result<Self> {
+        let tags = parse_tag_list(value, "DKIM-Signature")?;
+        let tag = |name: &str| {
+            tags.get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("DKIM-Signature has no {}= tag", name))
+        };
+
+        if tag("v")? != "1" {
+            bail!("Unsupported DKIM-Signature version {}", tag("v")?);
+        }
+        let header_canonicalization = match tags.get("c") {
+            Some(c) => c.split('/').next().unwrap_or_default().to_string(),
+            None => "simple".to_string(),
+        };
+
+        Ok(DkimSignature {
+            algorithm: tag("a")?,
+            header_canonicalization,
+            domain: tag("d")?.to_lowercase(),
+            selector: tag("s")?,
+            signed_headers: tag("h")?
+                .split(':')
+                .map(|name| name.to_lowercase())
+                .collect(),
+            signature: STANDARD
+                .decode(tag("b")?)
+                .map_err(|e| anyhow!("Invalid DKIM-Signature b= tag: {}", e))?,
+        })
+    }
+}
+
+/// Tag list of a `DKIM-Signature` field or of a DKIM key record, RFC 6376 section 3.2.
+fn parse_tag_list(value: &str, kind: &str) -> Result<HashMap<String, String>> {
+    let mut tags = HashMap::new();
+    for tag in value.split(';') {
+        // Whitespace is allowed anywhere in the tag values the generator reads
+        let tag: String = tag.split_whitespace().collect();
+        if tag.is_empty() {
+            continue;
+        }
+        let (name, value) = tag
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid {} tag: {}", kind, tag))?;
+        if tags.insert(name.to_string(), value.to_string()).is_some() {
+            bail!("Duplicate {} tag {}", kind, name);
+        }
+    }
+    Ok(tags)
+}
+
+/// Reads the modulus of the RSA key of a DKIM key record, the TXT record of
+/// `<selector>._domainkey.<domain>`. The `p=` key may be a SubjectPublicKeyInfo or a PKCS#1
+/// RSAPublicKey, and the TXT strings of the record may still be quoted.
+pub fn parse_dkim_dns_record(record: &str) -> Result<BigUint> {
+    let tags = parse_tag_list(&record.replace('"', ""), "DKIM key record")?;
+    if let Some(version) = tags.get("v") {
+        if version != "DKIM1" {
+            bail!("Unsupported DKIM key record version {}", version);
+        }
+    }
+    if let Some(key_type) = tags.get("k") {
+        if key_type != "rsa" {
+            bail!("Unsupported DKIM key type {}", key_type);
+        }
+    }
+    let key = match tags.get("p") {
+        Some(key) if !key.is_empty() => STANDARD
+            .decode(key)
+            .map_err(|e| anyhow!("Invalid DKIM key record p= tag: {}", e))?,
+        Some(_) => bail!("DKIM key is revoked"),
+        None => bail!("DKIM key record has no p= tag"),
+    };
+
+    let (modulus, exponent) = parse_rsa_public_key(&key)?;
+    if exponent != BigUint::from(DKIM_RSA_EXPONENT) {
+        bail!("Unsupported DKIM key exponent {}", exponent);
+    }
+    Ok(modulus)
+}
+
+/// Reads a DER element of tag `tag` at the start of `der`, and returns its content and the
+/// bytes after it.
+fn der_element(der: &[u8], tag: u8) -> Result<(&[u8], &[u8])> {
+    let invalid = || anyhow!("Invalid DER in the DKIM key");
+    if der.len() < 2 || der[0] != tag {
+        return Err(invalid());
+    }
+    let (len, header_len) = match der[1] {
+        len if len < 0x80 => (len as usize, 2),
+        0x81 | 0x82 => {
+            let len_bytes = (der[1] - 0x80) as usize;
+            let len = der
+                .get(2..2 + len_bytes)
+                .ok_or_else(invalid)?
+                .iter()
+                .fold(0usize, |len, b| (len << 8) | *b as usize);
+            (len, 2 + len_bytes)
+        }
+        _ => return Err(invalid()),
+    };
+    let content = der.get(header_len..header_len + len).ok_or_else(invalid)?;
+    Ok((content, &der[header_len + len..]))
+}
+
+/// Modulus and exponent of a SubjectPublicKeyInfo or PKCS#1 RSAPublicKey of an RSA key.
+fn parse_rsa_public_key(der: &[u8]) -> Result<(BigUint, BigUint)> {
+    const SEQUENCE: u8 = 0x30;
+    const INTEGER: u8 = 0x02;
+    const BIT_STRING: u8 = 0x03;
+    const OBJECT_IDENTIFIER: u8 = 0x06;
+
+    let (key, _) = der_element(der, SEQUENCE)?;
+    let key = if key.first() == Some(&SEQUENCE) {
+        let (algorithm, rest) = der_element(key, SEQUENCE)?;
+        let (oid, _) = der_element(algorithm, OBJECT_IDENTIFIER)?;
+        if oid != RSA_ENCRYPTION_OID {
+            bail!("DKIM key is not an RSA key");
+        }
+        // The bit string starts with its number of unused bits, 0
+        let (bits, _) = der_element(rest, BIT_STRING)?;
+        let (key, _) = der_element(bits.get(1..).unwrap_or_default(), SEQUENCE)?;
+        key
+    } else {
+        key
+    };
+    let (modulus, rest) = der_element(key, INTEGER)?;
+    let (exponent, _) = der_element(rest, INTEGER)?;
+    Ok((
+        BigUint::from_bytes_be(modulus),
+        BigUint::from_bytes_be(exponent),
+    ))
+}
+
+/// Header fields of a raw email, in the order of the message. Lines may end with `\r\n` or `\n`.
+pub fn parse_header_fields(eml: &str) -> Result<Vec<HeaderField>> {
+    let mut fields: Vec<HeaderField> = Vec::new();
+    for line in eml
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+    {
+        if line.is_empty() {
+            return Ok(fields);
+        }
+
+        if line.starts_with([' ', '\t']) {
+            let field = fields
+                .last_mut()
+                .ok_or_else(|| anyhow!("Email starts with a folded line"))?;
+            field.value.push_str("\r\n");
+            field.value.push_str(line);
+        } else {
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Invalid email header line: {}", line))?;
+            fields.push(HeaderField {
+                name: name.to_string(),
+                value: value.to_string(),
+            });
+        }
+    }
+    bail!("Email has no body separator")
+}
+
+/// `relaxed` header canonicalization of RFC 6376 section 3.4.2, without the trailing CRLF.
+pub fn relaxed_header(field: &HeaderField) -> String {
+    let value = field.value.replace("\r\n", "");
+    let value: Vec<&str> = value
+        .split([' ', '\t'])
+        .filter(|word| !word.is_empty())
+        .collect();
+    format!(
+        "{}:{}",
+        field.name.trim_end_matches([' ', '\t']).to_lowercase(),
+        value.join(" ")
+    )
+}
+
+/// `simple` header canonicalization of RFC 6376 section 3.4.1: the field unchanged, without the
+/// trailing CRLF.
+pub fn simple_header(field: &HeaderField) -> String {
+    format!("{}:{}", field.name, field.value)
+}
+
+fn canonicalize_header(field: &HeaderField, canonicalization: &str) -> Result<String> {
+    match canonicalization {
+        "simple" => Ok(simple_header(field)),
+        "relaxed" => Ok(relaxed_header(field)),
+        c => bail!("Unsupported DKIM header canonicalization {}", c),
+    }
+}
+
+/// Canonicalized `DKIM-Signature` field with the value of its `b=` tag removed.
+fn strip_signature_value(canonical: &str) -> String {
+    canonical
+        .split(';')
+        .map(|tag| match tag.split_once('=') {
+            Some((name, _)) if name.trim() == "b" || name.ends_with(":b") => {
+                format!("{}=", name)
+            }
+            _ => tag.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Header the DKIM signature of `dkim_field` signs: the fields of `h=`, each the last one of
+/// its name not selected yet, then the signature field without its `b=` value and last CRLF.
+pub fn signed_header(
+    fields: &[HeaderField],
+    dkim_field: &HeaderField,
+    signature: &DkimSignature,
+) -> Result<Vec<u8>> {
+    let canonicalization = &signature.header_canonicalization;
+    let mut selected = vec![false; fields.len()];
+    let mut header = String::new();
+    for name in &signature.signed_headers {
+        // Names without a field are signed as absent
+        if let Some(i) = (0..fields.len())
+            .rev()
+            .find(|&i| !selected[i] && fields[i].name.trim().eq_ignore_ascii_case(name))
+        {
+            selected[i] = true;
+            header.push_str(&canonicalize_header(&fields[i], canonicalization)?);
+            header.push_str("\r\n");
+        }
+    }
+    header.push_str(&strip_signature_value(&canonicalize_header(
+        dkim_field,
+        canonicalization,
+    )?));
+    Ok(header.into_bytes())
+}
+
+/// Parses a raw email and returns its DKIM signatures, in the order of the message, each with
+/// the header it signs.
+pub fn parse_dkim_signed_headers(eml: &str) -> Result<Vec<(DkimSignature, Vec<u8>)>> {
+    let fields = parse_header_fields(eml)?;
+    let mut signatures = Vec::new();
+    for dkim_field in fields
+        .iter()
+        .filter(|field| field.name.trim().eq_ignore_ascii_case("dkim-signature"))
+    {
+        let signature = DkimSignature::parse(&dkim_field.value)?;
+        let header = signed_header(&fields, dkim_field, &signature)?;
+        signatures.push((signature, header));
+    }
+    if signatures.is_empty() {
+        bail!("Email has no DKIM-Signature");
+    }
+    Ok(signatures)
+}
+
+/// Sequence of the `name:value` field of a canonicalized header, without its CRLF. The circuit
+/// matches lowercase names, so with `simple` canonicalization the field must be written in
+/// lowercase in the email.
+pub fn header_field_sequence(header: &[u8], name: &str) -> Result<HeaderSequence> {
+    let prefix = format!("{}:", name.to_lowercase());
+    let index = (0..header.len())
+        .find(|&i| {
+            header[i..].starts_with(prefix.as_bytes()) && (i == 0 || header[..i].ends_with(b"\r\n"))
+        })
+        .ok_or_else(|| anyhow!("Signed header has no {} field", name))?;
+    let length = header[index..]
+        .windows(2)
+        .position(|crlf| crlf == b"\r\n")
+        .unwrap_or(header.len() - index);
+    Ok(HeaderSequence { index, length })
+}
+
+/// Sequence of the address of a `from:` field sequence: between `<` and `>`, or the whole
+/// value for a bare address.
+pub fn from_address_sequence(header: &[u8], from: HeaderSequence) -> Result<HeaderSequence> {
+    let field = &header[from.index..from.index + from.length];
+    let (start, end) = match (
+        field.iter().rposition(|&b| b == b'<'),
+        field.iter().rposition(|&b| b == b'>'),
+    ) {
+        (Some(start), Some(end)) if start < end => (start + 1, end),
+        _ => {
+            let start = "from:".len();
+            let value = &field[start..];
+            let leading = value.iter().take_while(|b| b.is_ascii_whitespace()).count();
+            let trailing = value
+                .iter()
+                .rev()
+                .take_while(|b| b.is_ascii_whitespace())
+                .count();
+            (start + leading, field.len() - trailing)
+        }
+    };
+    if start >= end || !field[start..end].contains(&b'@') {
+        bail!("From field has no email address");
+    }
+    Ok(HeaderSequence {
+        index: from.index + start,
+        length: end - start,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const SAMPLE_EML_PATH: &str = "public/zkemail_sample.eml";
+    /// Email signed by example.org with `simple` canonicalization, then by its sending service
+    /// with `relaxed` canonicalization.
+    const MULTISIG_EML_PATH: &str = "public/zkemail_sample_multisig.eml";
+    const DKIM_RECORDS_PATH: &str = "public/zkemail_dkim_records.json";
+
+    pub(crate) fn sample_dkim_record(name: &str) -> String {
+        let records: HashMap<String, String> =
+            serde_json::from_str(&std::fs::read_to_string(DKIM_RECORDS_PATH).unwrap()).unwrap();
+        records[name].clone()
+    }
+
+    #[test]
+    fn test_parse_header_fields() {
+        let fields =
+            parse_header_fields("Subject: a\n\tb\r\nFrom:  <x@y.z> \r\n\r\nbody\r\nTo: c\r\n")
+                .unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].value, " a\r\n\tb");
+        assert_eq!(relaxed_header(&fields[0]), "subject:a b");
+        assert_eq!(relaxed_header(&fields[1]), "from:<x@y.z>");
+
+        assert!(parse_header_fields(" folded\r\n\r\n").is_err());
+        assert!(parse_header_fields("Subject: a").is_err());
+    }
+
+    #[test]
+    fn test_parse_dkim_signed_header() {
+        let eml = std::fs::read_to_string(SAMPLE_EML_PATH).unwrap();
+        let mut signatures = parse_dkim_signed_headers(&eml).unwrap();
+        assert_eq!(signatures.len(), 1);
+        let (signature, header) = signatures.remove(0);
+        assert_eq!(signature.algorithm, "rsa-sha256");
+        assert_eq!(signature.header_canonicalization, "relaxed");
+        assert_eq!(signature.domain, "gmail.com");
+        assert_eq!(signature.selector, "20230601");
+        assert_eq!(signature.signed_headers.len(), 13);
+        assert_eq!(signature.signature.len(), 256);
+
+        // The repeated and absent names of h= add no field; the folded h= keeps a space
+        let header = String::from_utf8(header).unwrap();
+        let lines: Vec<&str> = header.split("\r\n").collect();
+        assert_eq!(
+            lines[..6],
+            [
+                "to:jp4g@mach34.space",
+                "subject:This is a test email",
+                "message-id:<CA+rJj=zTWd8s_4FxjOEKujc0oLfU5Ln0=K=+iaTPg1GUqO0x2g@mail.gmail.com>",
+                "date:Tue, 14 Jan 2025 23:00:42 -0700",
+                "from:Jack Gilcrest <gilcrest.jack@gmail.com>",
+                "mime-version:1.0",
+            ]
+        );
+        assert!(lines[6]
+            .contains("h=to:subject:message-id:date:from:mime-version:from:to:cc:subject :date"));
+        assert!(lines[6].ends_with("; b="));
+    }
+
+    #[test]
+    fn test_header_field_sequences() {
+        let header = b"to:a@b.c\r\nfrom:Name <me@example.com>\r\nx-from:z\r\nsubject:hi";
+        let from = header_field_sequence(header, "from").unwrap();
+        assert_eq!(
+            from,
+            HeaderSequence {
+                index: 10,
+                length: 26
+            }
+        );
+        let address = from_address_sequence(header, from).unwrap();
+        assert_eq!(
+            &header[address.index..address.index + address.length],
+            b"me@example.com"
+        );
+        assert_eq!(
+            header_field_sequence(header, "subject").unwrap(),
+            HeaderSequence {
+                index: 48,
+                length: 10
+            }
+        );
+        assert!(header_field_sequence(header, "date").is_err());
+
+        let bare = b"from: me@example.com";
+        let address =
+            from_address_sequence(bare, header_field_sequence(bare, "from").unwrap()).unwrap();
+        assert_eq!(
+            address,
+            HeaderSequence {
+                index: 6,
+                length: 14
+            }
+        );
+        let none = b"from:Name";
+        assert!(from_address_sequence(none, header_field_sequence(none, "from").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_dkim_signature_requires_tags() {
+        assert!(DkimSignature::parse("v=1; a=rsa-sha256; d=x; s=y; h=from").is_err());
+        assert!(DkimSignature::parse("v=2; a=rsa-sha256; d=x; s=y; h=from; b=").is_err());
+        assert!(DkimSignature::parse("v=1; v=1; a=rsa-sha256; d=x; s=y; h=from; b=").is_err());
+        let signature =
+            DkimSignature::parse("v=1; a=rsa-sha256; d=X.com; s=y; h=From; b=AA==").unwrap();
+        assert_eq!(signature.header_canonicalization, "simple");
+        assert_eq!(signature.domain, "x.com");
+        assert_eq!(signature.signed_headers, ["from"]);
+    }
+
+    #[test]
+    fn test_parse_dkim_signed_headers_canonicalizations() {
+        let eml = std::fs::read_to_string(MULTISIG_EML_PATH).unwrap();
+        let signatures = parse_dkim_signed_headers(&eml).unwrap();
+        assert_eq!(signatures.len(), 2);
+
+        // The signature of the sending service, added last, comes first
+        let (signature, header) = &signatures[0];
+        assert_eq!(signature.domain, "esp.example.net");
+        assert_eq!(signature.header_canonicalization, "relaxed");
+        let header = String::from_utf8(header.clone()).unwrap();
+        assert!(header
+            .starts_with("from:Alice Doe <alice@example.org>\r\nto:Bob <bob@example.com>\r\n"));
+        let bh = "gKWteNAoa7VUzfXM5vMEYwbH0Xm0xfObycgn/gwhqn0=";
+        assert!(header.ends_with(&format!(
+            "\r\ndkim-signature:v=1; a=rsa-sha256; c=relaxed/relaxed; d=esp.example.net; s=esp1; \
+             h=from:to:subject:date; bh={}; b=",
+            bh
+        )));
+
+        // `simple` keeps the case, whitespace and folding of the fields
+        let (signature, header) = &signatures[1];
+        assert_eq!(signature.domain, "example.org");
+        assert_eq!(signature.header_canonicalization, "simple");
+        assert_eq!(
+            signature.signed_headers,
+            ["from", "to", "subject", "date", "message-id"]
+        );
+        let header = String::from_utf8(header.clone()).unwrap();
+        let lines: Vec<&str> = header.split("\r\n").collect();
+        assert_eq!(
+            lines,
+            [
+                "from:Alice Doe <alice@example.org>",
+                "To:   Bob   <bob@example.com>",
+                "subject:12345",
+                "date:Wed, 5 Mar 2025 10:15:00 +0000",
+                "message-id:<20250305101500.1@example.org>",
+                "DKIM-Signature: v=1; a=rsa-sha256; c=simple/simple; d=example.org; s=mail2025;",
+                &format!("\th=from:to:subject:date:message-id; bh={};", bh),
+                "\tb=",
+            ]
+        );
+
+        assert!(parse_dkim_signed_headers("From: a@b.c\r\n\r\nbody").is_err());
+    }
+
+    #[test]
+    fn test_parse_dkim_dns_record() {
+        let gmail = parse_dkim_dns_record(&sample_dkim_record("20230601._domainkey.gmail.com"));
+        assert_eq!(gmail.unwrap().bits(), 2048);
+
+        // A PKCS#1 key, split in quoted TXT strings
+        let record = sample_dkim_record("esp1._domainkey.esp.example.net");
+        let (start, end) = record.split_at(100);
+        let quoted = format!("\"{}\" \"{}\"", start, end);
+        assert_eq!(
+            parse_dkim_dns_record(&quoted).unwrap(),
+            parse_dkim_dns_record(&record).unwrap()
+        );
+
+        assert!(parse_dkim_dns_record("v=DKIM1; k=rsa; p=").is_err());
+        assert!(parse_dkim_dns_record("v=DKIM1; k=ed25519; p=AAAA").is_err());
+        assert!(parse_dkim_dns_record("v=DKIM1; k=rsa").is_err());
+        assert!(parse_dkim_dns_record("v=DKIM1; k=rsa; p=MAMCAQE=").is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use acir::{acir_field::FieldElement, AcirField};
+use anyhow::{anyhow, bail, Result};
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use chrono::{DateTime, Utc};
+use num_bigint::BigUint;
+use std::{collections::HashMap, str::FromStr};
+
+use super::poseidon2::Poseidon2;
+
+/// Maximum number of decimal digits of the JWT `nonce` claim read by the circuit (`NONCE_LENGTH`).
+pub const NONCE_LENGTH: usize = 77;
+
+/// Converts a field element to its canonical integer value.
+///
+/// Do not go through `FieldElement`'s `Display`: it prints "small" negative values as `-x`
+/// and powers of two in exponent form, neither of which parses back as a decimal.
+pub fn field_to_biguint(field: FieldElement) -> BigUint {
+    BigUint::from_bytes_be(&field.to_be_bytes())
+}
+
+/// Converts an integer to a field element, rejecting values that would be reduced.
+pub fn biguint_to_field(value: &BigUint) -> Result<FieldElement> {
+    let modulus: BigUint = Fr::MODULUS.into();
+    if value >= &modulus {
+        bail!("Value does not fit in a BN254 field element: {}", value);
+    }
+    Ok(FieldElement::from_be_bytes_reduce(&value.to_bytes_be()))
+}
+
+/// Parses a decimal string into a field element, rejecting values that would be reduced.
+pub fn decimal_to_field(value: &str) -> Result<FieldElement> {
+    let value = BigUint::from_str(value).map_err(|e| anyhow!("Invalid field element: {}", e))?;
+    biguint_to_field(&value)
+}
+
+/// The `(ephemeral_pubkey, ephemeral_pubkey_salt, ephemeral_pubkey_expiry)` triple exactly as
+/// `main.nr` receives it, and the nonce the circuit checks against the JWT `nonce` claim:
+///
+/// ```text
+/// Poseidon2::hash([ephemeral_pubkey, ephemeral_pubkey_salt, ephemeral_pubkey_expiry as Field], 3)
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EphemeralPubkeyCommitment {
+    pub pubkey: FieldElement,
+    pub salt: FieldElement,
+    pub expiry: u32,
+}
+
+impl EphemeralPubkeyCommitment {
+    pub fn new(pubkey: &BigUint, salt: &BigUint, expiry: DateTime<Utc>) -> Result<Self> {
+        Ok(EphemeralPubkeyCommitment {
+            pubkey: Self::encode_pubkey(pubkey)?,
+            salt: biguint_to_field(salt)?,
+            expiry: Self::encode_expiry(expiry)?,
+        })
+    }
+
+    /// Builds the commitment from the decimal pubkey, decimal salt and ISO 8601 expiry
+    /// strings returned by `generate_stored_ephemeral_key`.
+    pub fn from_strings(pubkey: &str, salt: &str, expiry: &str) -> Result<Self> {
+        let pubkey =
+            BigUint::from_str(pubkey).map_err(|e| anyhow!("Invalid ephemeral pubkey: {}", e))?;
+        let salt = BigUint::from_str(salt).map_err(|e| anyhow!("Invalid ephemeral salt: {}", e))?;
+        let expiry: DateTime<Utc> = expiry
+            .parse()
+            .map_err(|e| anyhow!("Invalid ephemeral expiry: {}", e))?;
+        Self::new(&pubkey, &salt, expiry)
+    }
+
+    /// A 256-bit ed25519 pubkey does not fit in a BN254 field, so the circuit takes it
+    /// shifted right by 3 bits.
+    pub fn encode_pubkey(pubkey: &BigUint) -> Result<FieldElement> {
+        if pubkey.bits() > 256 {
+            bail!("Ephemeral pubkey is larger than 256 bits");
+        }
+        biguint_to_field(&(pubkey >> 3u32))
+    }
+
+    /// The circuit takes the expiry as a `u32` UNIX timestamp in seconds.
+    pub fn encode_expiry(expiry: DateTime<Utc>) -> Result<u32> {
+        u32::try_from(expiry.timestamp())
+            .map_err(|_| anyhow!("Ephemeral expiry out of range: {}", expiry))
+    }
+
+    pub fn nonce(&self) -> FieldElement {
+        Poseidon2::hash(
+            &[
+                self.pubkey,
+                self.salt,
+                FieldElement::from(self.expiry as u64),
+            ],
+            false,
+        )
+    }
+
+    /// The nonce as the decimal string expected in the JWT `nonce` claim.
+    pub fn nonce_decimal(&self) -> String {
+        field_to_biguint(self.nonce()).to_string()
+    }
+
+    /// Inserts the ephemeral key witnesses of the JWT circuit.
+    pub fn insert_circuit_inputs(&self, inputs: &mut HashMap<String, Vec<String>>) {
+        inputs.insert(
+            "ephemeral_pubkey".to_string(),
+            vec![field_to_biguint(self.pubkey).to_string()],
+        );
+        inputs.insert(
+            "ephemeral_pubkey_salt".to_string(),
+            vec![field_to_biguint(self.salt).to_string()],
+        );
+        inputs.insert(
+            "ephemeral_pubkey_expiry".to_string(),
+            vec![self.expiry.to_string()],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Port of `decimal_string_to_field` in `circuit/zkjwt_circuit/src/zkjwt/utils.nr`,
+    /// applied to the nonce claim padded with zeros to `NONCE_LENGTH`.
+    fn circuit_decimal_string_to_field(nonce: &str) -> FieldElement {
+        let mut storage = nonce.as_bytes().to_vec();
+        assert!(storage.len() <= NONCE_LENGTH);
+        storage.resize(NONCE_LENGTH, 0);
+
+        let mut field = FieldElement::zero();
+        let mut multiplier = FieldElement::one();
+        for ascii_char in storage.iter().rev() {
+            if ascii_char.is_ascii_digit() {
+                field += FieldElement::from((ascii_char - b'0') as u64) * multiplier;
+                multiplier = multiplier * FieldElement::from(10u64);
+            }
+        }
+        field
+    }
+
+    #[test]
+    fn test_nonce_matches_real_jwt() {
+        // Ephemeral key and nonce of the Google ID token used in `test_prove_jwt_with_real_data`,
+        // which the circuit accepts.
+        let commitment = EphemeralPubkeyCommitment::from_strings(
+            "17302102366996071265028731047581517700208166805377449770193522591062772282670",
+            "646645587996092179008704451306999156519169540151959619716525865713892520",
+            "2025-05-07T09:07:57.379Z",
+        )
+        .unwrap();
+
+        assert_eq!(
+            field_to_biguint(commitment.pubkey).to_string(),
+            "2162762795874508908128591380947689712526020850672181221274190323882846535333"
+        );
+        assert_eq!(commitment.expiry, 1746608877);
+        assert_eq!(
+            commitment.nonce_decimal(),
+            "622618718926420486498127001071856504322492650656283936596477869965459887546"
+        );
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_values() {
+        let modulus: BigUint = Fr::MODULUS.into();
+        assert!(biguint_to_field(&modulus).is_err());
+        assert!(EphemeralPubkeyCommitment::encode_pubkey(&(BigUint::from(1u8) << 256u32)).is_err());
+
+        let expiry: DateTime<Utc> = "2106-02-08T00:00:00Z".parse().unwrap();
+        assert!(EphemeralPubkeyCommitment::encode_expiry(expiry).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_nonce_satisfies_circuit_check(
+            pubkey in any::<[u8; 32]>(),
+            salt in any::<[u8; 30]>(),
+            expiry in any::<u32>(),
+        ) {
+            let pubkey = BigUint::from_bytes_be(&pubkey);
+            let salt = BigUint::from_bytes_be(&salt);
+            let expiry = DateTime::<Utc>::from_timestamp(expiry as i64, 0).unwrap();
+            let commitment = EphemeralPubkeyCommitment::new(&pubkey, &salt, expiry).unwrap();
+
+            prop_assert_eq!(field_to_biguint(commitment.pubkey), &pubkey >> 3u32);
+            prop_assert_eq!(field_to_biguint(commitment.salt), salt);
+
+            let nonce = commitment.nonce_decimal();
+            prop_assert!(nonce.len() <= NONCE_LENGTH);
+            prop_assert_eq!(circuit_decimal_string_to_field(&nonce), commitment.nonce());
+        }
+    }
+}
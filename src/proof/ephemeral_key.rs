@@ -1,7 +1,8 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::api_server::{Message, SignedMessage};
+use crate::api_server::{IdentityCommitment, KeyRevocation, KeyRotation, Message, SignedMessage};
 use acir::acir_field::FieldElement;
+use anyhow::{anyhow, bail, Result};
 use ark_bn254::Fr;
 use ark_ff::PrimeField;
 use chrono::{DateTime, Duration, Utc};
@@ -9,13 +10,22 @@ use ed25519::signature::SignerMut;
 use ed25519::Signature;
 use ed25519_dalek::Verifier;
 use ed25519_dalek::{SigningKey, VerifyingKey};
+use hkdf::Hkdf;
 use num_bigint::BigUint;
 use rand::rngs::OsRng;
 use sha2::{Digest, Sha256};
 use sha256;
 use std::str::FromStr;
 
-use super::poseidon2::Poseidon2;
+use super::anon_post::{identity_commitment, identity_secret};
+use super::ephemeral_commitment::{field_to_biguint, EphemeralPubkeyCommitment};
+
+/// HKDF salt for deterministic ephemeral key derivation. Changing it changes every derived key.
+const DERIVATION_SALT: &[u8] = b"verified-anonymous-sns/ephemeral-key/v1";
+/// Minimum seed length accepted by `derive_ephemeral_key`.
+pub const MIN_SEED_LEN: usize = 16;
+/// Longest lifetime of an ephemeral key generated from a lifetime in seconds.
+pub const MAX_EPHEMERAL_KEY_LIFETIME_SECS: u64 = 365 * 24 * 3600;
 
 #[derive(Clone, Debug)]
 pub struct EphemeralKey {
@@ -34,55 +44,175 @@ fn bytes_to_biguint(bytes: &[u8]) -> BigUint {
     result
 }
 
+/// Lifetime of an ephemeral key of `secs` seconds, at most `MAX_EPHEMERAL_KEY_LIFETIME_SECS`.
+pub fn ephemeral_key_lifetime(secs: u64) -> Result<Duration> {
+    if secs == 0 || secs > MAX_EPHEMERAL_KEY_LIFETIME_SECS {
+        bail!(
+            "Ephemeral key lifetime of {} seconds is not between 1 and {}",
+            secs,
+            MAX_EPHEMERAL_KEY_LIFETIME_SECS
+        );
+    }
+    Duration::try_seconds(secs as i64)
+        .ok_or_else(|| anyhow!("Ephemeral key lifetime of {} seconds is out of range", secs))
+}
+
+/// Left-pads a big-endian integer into a fixed-size byte array.
+fn to_fixed_bytes<const N: usize>(value: &BigUint) -> Option<[u8; N]> {
+    let bytes = value.to_bytes_be();
+    if bytes.len() > N {
+        return None;
+    }
+    let mut padded = [0u8; N];
+    padded[N - bytes.len()..].copy_from_slice(&bytes);
+    Some(padded)
+}
+
+/// Parses an ephemeral public key in the decimal form used by `SignedMessage.ephemeralPubkey`.
+pub fn parse_ephemeral_public_key(pubkey: &str) -> Result<VerifyingKey> {
+    let value =
+        BigUint::from_str(pubkey).map_err(|e| anyhow!("Invalid ephemeral pubkey: {}", e))?;
+    let bytes =
+        to_fixed_bytes::<32>(&value).ok_or_else(|| anyhow!("Ephemeral pubkey too large"))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| anyhow!("Invalid ephemeral pubkey: {}", e))
+}
+
+/// Parses an ed25519 signature in the decimal form produced by `sign_message`.
+pub fn parse_signature(signature: &str) -> Result<Signature> {
+    let value = BigUint::from_str(signature).map_err(|e| anyhow!("Invalid signature: {}", e))?;
+    let bytes = to_fixed_bytes::<64>(&value).ok_or_else(|| anyhow!("Signature too large"))?;
+    Ok(Signature::from_bytes(&bytes))
+}
+
 impl EphemeralKey {
     pub fn generate_ephemeral_key() -> Option<Self> {
-        for _ in 0..10 {
-            let mut csprng = OsRng;
-            let signing_key: SigningKey = SigningKey::generate(&mut csprng);
-            let verifying_key = signing_key.verifying_key();
-
-            let salt: SigningKey = SigningKey::generate(&mut csprng);
-
-            let now = Utc::now();
-            let one_week_later = now + Duration::weeks(1);
-            let expiry_iso_string =
-                one_week_later.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
-            let dt: DateTime<Utc> = match expiry_iso_string.parse() {
-                Ok(dt) => dt,
-                Err(_) => continue,
-            };
-
-            let expiry = dt.timestamp() as u32;
-
-            let public_key = bytes_to_biguint(&verifying_key.to_bytes());
-            let public_key_shifted = (public_key >> 3u8).to_string();
-
-            let salt_str = bytes_to_biguint(&salt.to_bytes()[0..30]).to_string();
-            let expiry_str = expiry.to_string();
-            let input1_field_element = FieldElement::try_from_str(&public_key_shifted).unwrap();
-            let input2_field_element = FieldElement::try_from_str(&salt_str).unwrap();
-            let input3_field_element = FieldElement::try_from_str(&expiry_str).unwrap();
-
-            let hash = Poseidon2::hash(
-                &[
-                    input1_field_element,
-                    input2_field_element,
-                    input3_field_element,
-                ],
-                false,
-            );
-
-            if let Ok(ephemeral_pubkey_hash) = BigUint::from_str(&hash.to_string()) {
-                return Some(EphemeralKey {
-                    private_key: signing_key,
-                    public_key: verifying_key,
-                    salt: salt_str,
-                    expiry: expiry_iso_string,
-                    ephemeral_pubkey_hash,
-                });
-            }
+        Self::generate_ephemeral_key_with_expiry(Duration::weeks(1))
+    }
+
+    /// Generates a fresh ephemeral key that expires `lifetime` from now.
+    pub fn generate_ephemeral_key_with_expiry(lifetime: Duration) -> Option<Self> {
+        if lifetime <= Duration::zero() {
+            return None;
+        }
+
+        let mut csprng = OsRng;
+        let signing_key: SigningKey = SigningKey::generate(&mut csprng);
+
+        let salt: SigningKey = SigningKey::generate(&mut csprng);
+        let salt_str = bytes_to_biguint(&salt.to_bytes()[0..30]).to_string();
+
+        let expiry = Utc::now() + lifetime;
+        let expiry_iso_string = expiry.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+        Self::from_parts(signing_key, salt_str, expiry_iso_string)
+    }
+
+    /// Derives the ephemeral key at `index` from `seed` with HKDF-SHA256, so the same key
+    /// can be re-derived on another device. The expiry is part of the pubkey hash and must
+    /// be supplied (and backed up) alongside the seed.
+    pub fn derive_ephemeral_key(seed: &[u8], index: u32, expiry: DateTime<Utc>) -> Option<Self> {
+        if seed.len() < MIN_SEED_LEN {
+            return None;
         }
-        None
+        let hkdf = Hkdf::<Sha256>::new(Some(DERIVATION_SALT), seed);
+
+        let mut signing_key_bytes = [0u8; 32];
+        hkdf.expand(
+            format!("signing-key/{}", index).as_bytes(),
+            &mut signing_key_bytes,
+        )
+        .ok()?;
+
+        // 30 bytes keeps the salt below the BN254 field modulus
+        let mut salt_bytes = [0u8; 30];
+        hkdf.expand(format!("salt/{}", index).as_bytes(), &mut salt_bytes)
+            .ok()?;
+
+        let expiry_iso_string = expiry.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        Self::from_parts(
+            SigningKey::from_bytes(&signing_key_bytes),
+            bytes_to_biguint(&salt_bytes).to_string(),
+            expiry_iso_string,
+        )
+    }
+
+    /// Rebuilds an ephemeral key from the decimal strings kept in a key store envelope.
+    pub fn from_private_key(private_key: &str, salt: &str, expiry: &str) -> Option<Self> {
+        let private_key = BigUint::from_str(private_key).ok()?;
+        let signing_key = SigningKey::from_bytes(&to_fixed_bytes::<32>(&private_key)?);
+        Self::from_parts(signing_key, salt.to_string(), expiry.to_string())
+    }
+
+    fn from_parts(private_key: SigningKey, salt: String, expiry: String) -> Option<Self> {
+        let verifying_key = private_key.verifying_key();
+
+        let public_key = bytes_to_biguint(&verifying_key.to_bytes());
+        let commitment = EphemeralPubkeyCommitment::new(
+            &public_key,
+            &BigUint::from_str(&salt).ok()?,
+            expiry.parse().ok()?,
+        )
+        .ok()?;
+
+        let ephemeral_pubkey_hash = field_to_biguint(commitment.nonce());
+        Some(EphemeralKey {
+            private_key,
+            public_key: verifying_key,
+            salt,
+            expiry,
+            ephemeral_pubkey_hash,
+        })
+    }
+
+    /// Generates the next ephemeral key and a rotation record signed by the current key,
+    /// so the server can link the new key to this one and retire it.
+    pub fn rotate(&mut self, lifetime: Duration) -> Option<(EphemeralKey, KeyRotation)> {
+        let next = Self::generate_ephemeral_key_with_expiry(lifetime)?;
+
+        let mut rotation = KeyRotation {
+            previous_pubkey: self.get_ephemeral_public_key(),
+            new_pubkey: next.get_ephemeral_public_key(),
+            new_pubkey_hash: next.get_ephemeral_pubkey_hash(),
+            new_pubkey_expiry: next.get_ephemeral_expiry(),
+            timestamp: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            signature: String::new(),
+        };
+        let signature = self.private_key.sign(&rotation.signing_payload());
+        rotation.signature = BigUint::from_bytes_be(&signature.to_bytes()).to_string();
+
+        Some((next, rotation))
+    }
+
+    /// Signs a revocation of this key, to be recorded by the server.
+    pub fn revoke(&mut self) -> KeyRevocation {
+        let mut revocation = KeyRevocation {
+            pubkey: self.get_ephemeral_public_key(),
+            timestamp: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            signature: String::new(),
+        };
+        let signature = self.private_key.sign(&revocation.signing_payload());
+        revocation.signature = BigUint::from_bytes_be(&signature.to_bytes()).to_string();
+
+        revocation
+    }
+
+    /// Secret behind this key's member commitment in the anonymous posting trees.
+    pub fn identity_secret(&self) -> FieldElement {
+        identity_secret(&self.private_key.to_bytes())
+    }
+
+    /// Signs the member commitment of this key for the tree of `group_id`.
+    pub fn identity_commitment(&mut self, group_id: &str) -> IdentityCommitment {
+        let mut commitment = IdentityCommitment {
+            pubkey: self.get_ephemeral_public_key(),
+            group_id: group_id.to_string(),
+            commitment: field_to_biguint(identity_commitment(self.identity_secret())).to_string(),
+            signature: String::new(),
+        };
+        let signature = self.private_key.sign(&commitment.signing_payload());
+        commitment.signature = BigUint::from_bytes_be(&signature.to_bytes()).to_string();
+
+        commitment
     }
 
     pub fn sign_message(&mut self, message: Message) -> (BigUint, String, Signature) {
@@ -164,6 +294,7 @@ impl EphemeralKey {
 mod tests {
     use super::*;
     use crate::api_server::{Message, Provider, SignedMessage};
+    use crate::proof::poseidon2::Poseidon2;
 
     #[test]
     fn test_poseidon2_hash() {
@@ -186,6 +317,120 @@ mod tests {
         assert!(key.get_ephemeral_expiry() > "0".to_string());
     }
 
+    #[test]
+    fn test_ephemeral_key_with_expiry() {
+        let key = EphemeralKey::generate_ephemeral_key_with_expiry(Duration::hours(1)).unwrap();
+        let expiry: DateTime<Utc> = key.get_ephemeral_expiry().parse().unwrap();
+        assert!(expiry > Utc::now() + Duration::minutes(59));
+        assert!(expiry <= Utc::now() + Duration::hours(1));
+
+        assert!(EphemeralKey::generate_ephemeral_key_with_expiry(Duration::zero()).is_none());
+    }
+
+    #[test]
+    fn test_ephemeral_key_lifetime() {
+        assert_eq!(ephemeral_key_lifetime(3600).unwrap(), Duration::hours(1));
+        assert_eq!(
+            ephemeral_key_lifetime(MAX_EPHEMERAL_KEY_LIFETIME_SECS).unwrap(),
+            Duration::days(365)
+        );
+        assert!(ephemeral_key_lifetime(0).is_err());
+        assert!(ephemeral_key_lifetime(MAX_EPHEMERAL_KEY_LIFETIME_SECS + 1).is_err());
+        // Would wrap to a negative lifetime as an i64
+        assert!(ephemeral_key_lifetime(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_ephemeral_key_from_private_key() {
+        let key = EphemeralKey::generate_ephemeral_key().unwrap();
+        let restored = EphemeralKey::from_private_key(
+            &key.get_ephemeral_private_key(),
+            &key.get_ephemeral_salt(),
+            &key.get_ephemeral_expiry(),
+        )
+        .unwrap();
+        assert_eq!(
+            restored.get_ephemeral_public_key(),
+            key.get_ephemeral_public_key()
+        );
+        assert_eq!(
+            restored.get_ephemeral_pubkey_hash(),
+            key.get_ephemeral_pubkey_hash()
+        );
+    }
+
+    #[test]
+    fn test_derive_ephemeral_key() {
+        let seed = [7u8; 32];
+        let expiry: DateTime<Utc> = "2025-05-07T09:07:57.379Z".parse().unwrap();
+
+        // Pinned test vector: HKDF-SHA256 over a seed of 32 0x07 bytes, index 0
+        let key = EphemeralKey::derive_ephemeral_key(&seed, 0, expiry).unwrap();
+        assert_eq!(
+            key.get_ephemeral_private_key(),
+            "93297698167522232485562935971102004937213920864935337665695631030595410960726"
+        );
+        assert_eq!(
+            key.get_ephemeral_public_key(),
+            "55841227677130619900016773734946772403702195473019105832792805789639352444876"
+        );
+        assert_eq!(
+            key.get_ephemeral_salt(),
+            "591688347561414467958414151161092417469701201796139533136376234871749052"
+        );
+        assert_eq!(key.get_ephemeral_expiry(), "2025-05-07T09:07:57.379Z");
+
+        let again = EphemeralKey::derive_ephemeral_key(&seed, 0, expiry).unwrap();
+        assert_eq!(
+            again.get_ephemeral_private_key(),
+            key.get_ephemeral_private_key()
+        );
+        assert_eq!(again.get_ephemeral_salt(), key.get_ephemeral_salt());
+        assert_eq!(
+            again.get_ephemeral_pubkey_hash(),
+            key.get_ephemeral_pubkey_hash()
+        );
+
+        let next = EphemeralKey::derive_ephemeral_key(&seed, 1, expiry).unwrap();
+        assert_ne!(
+            next.get_ephemeral_private_key(),
+            key.get_ephemeral_private_key()
+        );
+        assert_ne!(next.get_ephemeral_salt(), key.get_ephemeral_salt());
+
+        assert!(EphemeralKey::derive_ephemeral_key(&seed[..8], 0, expiry).is_none());
+    }
+
+    #[test]
+    fn test_identity_secret_does_not_depend_on_salt() {
+        let key = EphemeralKey::generate_ephemeral_key().unwrap();
+        let resalted = EphemeralKey::from_private_key(
+            &key.get_ephemeral_private_key(),
+            "1",
+            &key.get_ephemeral_expiry(),
+        )
+        .unwrap();
+        assert_eq!(resalted.identity_secret(), key.identity_secret());
+
+        let other = EphemeralKey::generate_ephemeral_key().unwrap();
+        assert_ne!(other.identity_secret(), key.identity_secret());
+    }
+
+    #[test]
+    fn test_rotate_and_revoke() {
+        let mut key = EphemeralKey::generate_ephemeral_key().unwrap();
+        let (next, rotation) = key.rotate(Duration::days(1)).unwrap();
+
+        assert_eq!(rotation.previous_pubkey, key.get_ephemeral_public_key());
+        assert_eq!(rotation.new_pubkey, next.get_ephemeral_public_key());
+        assert_eq!(rotation.new_pubkey_hash, next.get_ephemeral_pubkey_hash());
+        assert!(rotation.verify().is_ok());
+
+        let revocation = key.revoke();
+        assert_eq!(revocation.pubkey, key.get_ephemeral_public_key());
+        assert!(revocation.verify().is_ok());
+    }
+
     #[test]
     fn test_sign_and_verify_message() {
         let mut key = EphemeralKey::generate_ephemeral_key().unwrap();
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use acir::acir_field::FieldElement;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use noir::barretenberg::srs::setup_srs_from_bytecode;
+use num_bigint::BigUint;
+
+use super::{
+    claim_disclosure::{hash_sub, MAX_HD_LENGTH},
+    ephemeral_commitment::field_to_biguint,
+    jwt_proof::{
+        check_public_inputs, claim_public_inputs, claim_str, flatten_fields_as_array,
+        load_bytecode, modulus_public_inputs, prove_circuit_with_witness_keys,
+        pubkey_modulus_from_jwk, EphemeralExpiryPolicy, JwtPublicInputs, MembershipType,
+        ProofTranscript, JWT_WITNESS_KEYS,
+    },
+    poseidon2::{pack_bytes, Poseidon2},
+};
+
+/// Name of the group nullifier circuit (`circuit/zkjwt_group_nullifier_circuit`).
+pub const GROUP_NULLIFIER_CIRCUIT_NAME: &str = "verified_anonymous_sns_jwt_group_nullifier";
+
+/// Domain of the group id hash.
+pub const GROUP_ID_DOMAIN: &str = "group";
+/// Domain of the group nullifier.
+pub const GROUP_NULLIFIER_DOMAIN: &str = "group_nullifier";
+/// Domain of the nullifier scope.
+pub const NULLIFIER_SCOPE_DOMAIN: &str = "nullifier_scope";
+
+/// Input of the group nullifier circuit after those of the JWT circuit.
+const NULLIFIER_SCOPE_INPUT: &str = "nullifier_scope";
+
+/// Scope of group nullifiers: an app and an epoch of it, e.g. a season or a poll. Proofs of
+/// an account for a group return the same nullifier within a scope, and unlinkable ones
+/// across scopes.
+#[derive(uniffi::Record, Clone, Debug, PartialEq)]
+pub struct NullifierScope {
+    pub app_id: String,
+    pub epoch: u64,
+}
+
+impl NullifierScope {
+    /// The `nullifier_scope` input of the circuit.
+    pub fn field(&self) -> FieldElement {
+        Poseidon2::hash_with_domain(
+            NULLIFIER_SCOPE_DOMAIN,
+            &[
+                Poseidon2::hash_bytes(self.app_id.as_bytes()),
+                FieldElement::from(self.epoch),
+            ],
+        )
+    }
+}
+
+/// Public inputs of the group nullifier circuit, as known to the verifier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupNullifierPublicInputs {
+    pub jwt: JwtPublicInputs,
+    pub scope: NullifierScope,
+}
+
+/// Id of the group of `domain` in the nullifier: the hash of the domain zero-padded to the
+/// 64 bytes of the circuit.
+pub fn group_id_hash(domain: &str) -> Result<FieldElement> {
+    if domain.len() > MAX_HD_LENGTH {
+        bail!("Domain longer than {} bytes", MAX_HD_LENGTH);
+    }
+
+    let mut padded = domain.as_bytes().to_vec();
+    padded.resize(MAX_HD_LENGTH, 0);
+    let mut input = vec![FieldElement::from(domain.len() as u64)];
+    input.extend(pack_bytes(&padded));
+    Ok(Poseidon2::hash_with_domain(GROUP_ID_DOMAIN, &input))
+}
+
+/// Nullifier the group nullifier circuit returns for the account of `sub` in the group of
+/// `domain`. It does not depend on the ephemeral key, so an account registers once per group
+/// and scope.
+pub fn group_nullifier(sub: &str, domain: &str, scope: &NullifierScope) -> Result<FieldElement> {
+    Ok(Poseidon2::hash_with_domain(
+        GROUP_NULLIFIER_DOMAIN,
+        &[hash_sub(sub)?, group_id_hash(domain)?, scope.field()],
+    ))
+}
+
+/// Claims the group nullifier circuit reads; `generate_inputs` stops the partial SHA before
+/// the first of them in the payload.
+pub fn sha_precompute_keys() -> Vec<&'static str> {
+    let mut keys = MembershipType::EmailDomain.sha_precompute_keys();
+    keys.push("sub");
+    keys
+}
+
+/// Adds the nullifier scope to the JWT circuit inputs of the JWT `payload`.
+pub fn insert_group_nullifier_inputs(
+    payload: &serde_json::Value,
+    scope: &NullifierScope,
+    inputs: &mut HashMap<String, Vec<String>>,
+) -> Result<()> {
+    // Checked here rather than failing in the circuit
+    hash_sub(claim_str(payload, "sub")?)?;
+
+    inputs.insert(
+        NULLIFIER_SCOPE_INPUT.to_string(),
+        vec![field_to_biguint(scope.field()).to_string()],
+    );
+    Ok(())
+}
+
+/// Proves a JWT with the group nullifier circuit at `circuit_path`. The proof keeps its
+/// public inputs, which end with the nullifier.
+pub fn generate_group_nullifier_proof(
+    srs_path: String,
+    circuit_path: String,
+    inputs: HashMap<String, Vec<String>>,
+) -> Result<Vec<u8>> {
+    let bytecode = load_bytecode(&circuit_path)?;
+    let mut witness_keys = JWT_WITNESS_KEYS.to_vec();
+    witness_keys.push(NULLIFIER_SCOPE_INPUT);
+
+    prove_circuit_with_witness_keys(
+        &bytecode,
+        Some(&srs_path),
+        &inputs,
+        &witness_keys,
+        ProofTranscript::Poseidon2,
+    )
+}
+
+/// Public inputs of the group nullifier circuit before the returned nullifier.
+fn expected_public_inputs(public_inputs: &GroupNullifierPublicInputs) -> Result<Vec<String>> {
+    let jwt_pubkey = pubkey_modulus_from_jwk(&public_inputs.jwt.google_jwt_pubkey_modulus)
+        .map_err(|e| anyhow!("Invalid JWT pubkey modulus: {}", e))?;
+    let mut fields = modulus_public_inputs(&jwt_pubkey);
+    fields.extend(claim_public_inputs(&public_inputs.jwt));
+    fields.push(format!(
+        "0x{:0>64x}",
+        field_to_biguint(public_inputs.scope.field())
+    ));
+    Ok(fields)
+}
+
+/// Verifies a proof of `generate_group_nullifier_proof` against its public inputs, checked as
+/// in `verify_jwt_proof`, and returns the nullifier of the account in the group and scope.
+pub fn verify_group_nullifier_proof(
+    srs_path: String,
+    circuit_path: String,
+    proof: Vec<u8>,
+    public_inputs: &GroupNullifierPublicInputs,
+    allowed_client_ids: &[String],
+    expiry_policy: &EphemeralExpiryPolicy,
+    now: DateTime<Utc>,
+) -> Result<BigUint> {
+    check_public_inputs(&public_inputs.jwt, allowed_client_ids, expiry_policy, now)?;
+
+    let expected = flatten_fields_as_array(&expected_public_inputs(public_inputs)?);
+    if proof.len() < 4 + expected.len() + 32 || proof[4..4 + expected.len()] != expected[..] {
+        bail!("Proof is not for this JWT key, claims and nullifier scope");
+    }
+    let nullifier = BigUint::from_bytes_be(&proof[4 + expected.len()..4 + expected.len() + 32]);
+
+    let bytecode = load_bytecode(&circuit_path)?;
+    setup_srs_from_bytecode(&bytecode, Some(&srs_path), false).map_err(|e| anyhow!(e))?;
+    let vk = ProofTranscript::Poseidon2.verification_key(&bytecode)?;
+
+    let start = std::time::Instant::now();
+    let verdict = ProofTranscript::Poseidon2.verify(proof, vk)?;
+    println!("Proof verification time: {:?}", start.elapsed());
+
+    if !verdict {
+        bail!("Invalid group nullifier proof");
+    }
+    Ok(nullifier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_server::nullifier::record_nullifier;
+    use crate::proof::jwt_proof::GOOGLE_ISSUERS;
+    use acir::AcirField;
+
+    // `sub` and domain of the Google ID token used in `test_prove_jwt_with_real_data`
+    const SUB: &str = "108522077721826439364";
+    const DOMAIN: &str = "pse.dev";
+
+    const GROUP_ID_VECTOR: &str =
+        "0x11165810a10a27a0f401e313bbdb8140ecdf7a44936d090cd0594fc25f6fc1cc";
+    const NULLIFIER_SCOPE_VECTOR: &str =
+        "0x21735451fd222ea1892e673da58a4301107b24fb2f4b9aecb62e002303824f78";
+    const GROUP_NULLIFIER_VECTOR: &str =
+        "0x1a1f4cd84d26e850a7988ef81e00b60057f8a15a86d4e28f057963b2c579361e";
+
+    fn scope(epoch: u64) -> NullifierScope {
+        NullifierScope {
+            app_id: "verified-anonymous-sns".to_string(),
+            epoch,
+        }
+    }
+
+    #[test]
+    fn test_group_nullifier_vectors() {
+        // Same vectors as `test_hash_group_id` and `test_group_nullifier` of the circuit
+        assert_eq!(
+            group_id_hash(DOMAIN).unwrap(),
+            FieldElement::from_hex(GROUP_ID_VECTOR).unwrap()
+        );
+        assert_eq!(
+            scope(1).field(),
+            FieldElement::from_hex(NULLIFIER_SCOPE_VECTOR).unwrap()
+        );
+        assert_eq!(
+            group_nullifier(SUB, DOMAIN, &scope(1)).unwrap(),
+            FieldElement::from_hex(GROUP_NULLIFIER_VECTOR).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_group_nullifier_is_scoped() {
+        let nullifier = group_nullifier(SUB, DOMAIN, &scope(1)).unwrap();
+        assert_ne!(
+            group_nullifier(SUB, "example.com", &scope(1)).unwrap(),
+            nullifier
+        );
+        assert_ne!(group_nullifier(SUB, DOMAIN, &scope(2)).unwrap(), nullifier);
+        assert_ne!(
+            group_nullifier("108522077721826439365", DOMAIN, &scope(1)).unwrap(),
+            nullifier
+        );
+
+        assert!(group_id_hash(&"a".repeat(MAX_HD_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_registry_enforces_one_membership_per_account() {
+        let path = std::env::temp_dir().join(format!("group-nullifier-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        // Proofs with a new ephemeral key return the same nullifier, rejected the second time
+        let nullifier = field_to_biguint(group_nullifier(SUB, DOMAIN, &scope(1)).unwrap());
+        record_nullifier(&nullifier.to_string(), DOMAIN, &path, None).unwrap();
+        assert!(record_nullifier(&nullifier.to_string(), DOMAIN, &path, None).is_err());
+
+        // A new scope lets the account register again
+        let next = field_to_biguint(group_nullifier(SUB, DOMAIN, &scope(2)).unwrap());
+        record_nullifier(&next.to_string(), DOMAIN, &path, None).unwrap();
+
+        let _ = std::fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_verify_rejects_other_public_inputs() {
+        let public_inputs = GroupNullifierPublicInputs {
+            jwt: JwtPublicInputs {
+                domain: DOMAIN.to_string(),
+                google_jwt_pubkey_modulus: base64::Engine::encode(
+                    &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+                    [0xffu8; 256],
+                ),
+                ephemeral_pubkey: "1".to_string(),
+                ephemeral_pubkey_expiry: "2025-05-01T09:00:00.000Z".to_string(),
+                iss: GOOGLE_ISSUERS[0].to_string(),
+                aud: "client-id".to_string(),
+                iat: 1746004080,
+            },
+            scope: scope(1),
+        };
+        let now: DateTime<Utc> = "2025-04-30T09:10:00Z".parse().unwrap();
+        let policy = EphemeralExpiryPolicy {
+            max_lifetime_secs: 7 * 24 * 3600,
+        };
+        let allowed = vec!["client-id".to_string()];
+
+        // A proof of another scope
+        let mut other = public_inputs.clone();
+        other.scope = scope(2);
+        let mut fields = expected_public_inputs(&other).unwrap();
+        fields.push(format!("0x{:0>64x}", 42));
+        let mut proof = ((fields.len() + 1) as u32).to_be_bytes().to_vec();
+        proof.extend(flatten_fields_as_array(&fields));
+        proof.extend([0u8; 32]);
+
+        let err = verify_group_nullifier_proof(
+            String::new(),
+            String::new(),
+            proof,
+            &public_inputs,
+            &allowed,
+            &policy,
+            now,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("nullifier scope"));
+    }
+}
@@ -1,29 +1,294 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
 use byteorder::{BigEndian, ByteOrder};
 use chrono::{DateTime, Utc};
 use noir::{
     barretenberg::{
-        prove::prove_ultra_honk, srs::setup_srs_from_bytecode, utils::get_honk_verification_key,
-        verify::verify_ultra_honk,
+        prove::{prove_ultra_honk, prove_ultra_honk_keccak},
+        srs::setup_srs_from_bytecode,
+        utils::{get_honk_verification_key, get_honk_verification_key_keccak},
+        verify::{verify_ultra_honk, verify_ultra_honk_keccak},
     },
     witness::from_vec_str_to_witness_map,
 };
 use num_bigint::BigUint;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use sha2::digest::generic_array::GenericArray;
 use std::{collections::HashMap, str::FromStr};
 
+use super::ephemeral_commitment::{field_to_biguint, EphemeralPubkeyCommitment};
+
+/// Claims read by the JWT circuit, which proves the domain of the `email` claim.
+pub const EMAIL_DOMAIN_CLAIM_KEYS: [&str; 3] = ["email", "email_verified", "nonce"];
+/// Claims read by the workspace domain circuit, which proves the `hd` claim.
+pub const WORKSPACE_DOMAIN_CLAIM_KEYS: [&str; 2] = ["hd", "nonce"];
+/// Claims the membership circuits bind the proof to, checked by `verify_jwt_proof`.
+pub const OAUTH_CLIENT_CLAIM_KEYS: [&str; 2] = ["iss", "aud"];
+/// Issue time the membership circuits expose, bounding the ephemeral key expiry.
+pub const IAT_CLAIM_KEY: &str = "iat";
+
+/// Default longest ephemeral key lifetime after the JWT `iat`, that of
+/// `EphemeralKey::generate_ephemeral_key`.
+pub const DEFAULT_MAX_EPHEMERAL_LIFETIME_SECS: u64 = 7 * 24 * 3600;
+
+/// Signed data length after partial SHA in the membership circuits. `iss` is the first claim
+/// of Google ID tokens, so almost the whole payload is hashed in-circuit.
+pub const MAX_JWT_PARTIAL_DATA_LENGTH: usize = 1024;
+pub const MAX_ISS_LENGTH: usize = 128;
+pub const MAX_AUD_LENGTH: usize = 128;
+
+/// Issuers of Google ID tokens.
+pub const GOOGLE_ISSUERS: [&str; 2] = ["https://accounts.google.com", "accounts.google.com"];
+
+/// How a JWT proof shows membership of a domain group.
+#[derive(uniffi::Enum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum MembershipType {
+    /// Domain after the `@` of the verified `email` claim (`circuit/zkjwt_circuit`).
+    EmailDomain,
+    /// Google Workspace `hd` (hosted domain) claim (`circuit/zkjwt_hd_circuit`).
+    WorkspaceDomain,
+}
+
+impl MembershipType {
+    /// Claims the circuit reads; `generate_inputs` stops the partial SHA before the first of
+    /// them in the payload.
+    pub fn sha_precompute_keys(&self) -> Vec<&'static str> {
+        let mut keys = match self {
+            Self::EmailDomain => EMAIL_DOMAIN_CLAIM_KEYS.to_vec(),
+            Self::WorkspaceDomain => WORKSPACE_DOMAIN_CLAIM_KEYS.to_vec(),
+        };
+        keys.extend(OAUTH_CLIENT_CLAIM_KEYS);
+        keys.push(IAT_CLAIM_KEY);
+        keys
+    }
+
+    /// Domain a JWT with this `payload` can prove membership of.
+    pub fn domain_of(&self, payload: &serde_json::Value) -> Result<String> {
+        match self {
+            Self::EmailDomain => payload["email"]
+                .as_str()
+                .and_then(|email| email.rsplit_once('@'))
+                .map(|(_, domain)| domain.to_string())
+                .ok_or_else(|| anyhow!("JWT has no email claim")),
+            Self::WorkspaceDomain => payload["hd"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("JWT has no hd claim, not a Google Workspace account")),
+        }
+    }
+}
+
+/// Hash of the Fiat-Shamir transcript of an UltraHonk proof.
+#[derive(uniffi::Enum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ProofTranscript {
+    /// Poseidon2, the cheapest to prove and verify natively.
+    Poseidon2,
+    /// Keccak with zero-knowledge masking, the proofs the ZK Solidity `HonkVerifier`
+    /// generated by `bb write_solidity_verifier` verifies.
+    Keccak,
+}
+
+impl ProofTranscript {
+    /// Proves the circuit of `bytecode` for the witness values `witness`.
+    pub(crate) fn prove(&self, bytecode: &str, witness: Vec<&str>) -> Result<Vec<u8>> {
+        let initial_witness = from_vec_str_to_witness_map(witness).map_err(|e| anyhow!(e))?;
+        match self {
+            Self::Poseidon2 => prove_ultra_honk(bytecode, initial_witness, false),
+            Self::Keccak => prove_ultra_honk_keccak(bytecode, initial_witness, false),
+        }
+        .map_err(|e| anyhow!(e))
+    }
+
+    /// Verification key of the circuit of `bytecode` for proofs with this transcript.
+    pub(crate) fn verification_key(&self, bytecode: &str) -> Result<Vec<u8>> {
+        match self {
+            Self::Poseidon2 => get_honk_verification_key(bytecode, false),
+            Self::Keccak => get_honk_verification_key_keccak(bytecode, false),
+        }
+        .map_err(|e| anyhow!(e))
+    }
+
+    pub(crate) fn verify(&self, proof: Vec<u8>, vk: Vec<u8>) -> Result<bool> {
+        match self {
+            Self::Poseidon2 => verify_ultra_honk(proof, vk),
+            Self::Keccak => verify_ultra_honk_keccak(proof, vk, false),
+        }
+        .map_err(|e| anyhow!(e))
+    }
+}
+
+/// Public inputs of the membership circuits, as known to the verifier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwtPublicInputs {
+    pub domain: String,
+    /// Base64url modulus of the RSA signing key; unused for ES256 proofs, whose key is
+    /// given separately
+    pub google_jwt_pubkey_modulus: String,
+    pub ephemeral_pubkey: String,
+    pub ephemeral_pubkey_expiry: String,
+    /// `iss` claim of the JWT
+    pub iss: String,
+    /// `aud` claim of the JWT, the OAuth client id it was minted for
+    pub aud: String,
+    /// `iat` claim of the JWT, in seconds since epoch
+    pub iat: u64,
+}
+
+/// Bounds on the ephemeral key expiry of a JWT proof. The ephemeral key is created right
+/// before signing in, so its expiry is at most `max_lifetime_secs` after the JWT `iat`.
+#[derive(uniffi::Record, Clone, Copy, Debug, PartialEq)]
+pub struct EphemeralExpiryPolicy {
+    pub max_lifetime_secs: u64,
+}
+
+impl Default for EphemeralExpiryPolicy {
+    fn default() -> Self {
+        EphemeralExpiryPolicy {
+            max_lifetime_secs: DEFAULT_MAX_EPHEMERAL_LIFETIME_SECS,
+        }
+    }
+}
+
+impl EphemeralExpiryPolicy {
+    /// Checks an ephemeral key `expiry` against the JWT `iat` and the current time `now`.
+    pub fn check(&self, expiry: DateTime<Utc>, iat: u64, now: DateTime<Utc>) -> Result<()> {
+        if expiry <= now {
+            bail!("Ephemeral key expired at {}", expiry);
+        }
+        let expiry_secs = expiry.timestamp().max(0) as u64;
+        if expiry_secs <= iat {
+            bail!("Ephemeral key expired before the JWT was issued");
+        }
+        if expiry_secs - iat > self.max_lifetime_secs {
+            bail!(
+                "Ephemeral key expires {} seconds after the JWT was issued, more than {}",
+                expiry_secs - iat,
+                self.max_lifetime_secs
+            );
+        }
+        Ok(())
+    }
+}
+
 #[derive(uniffi::Record, Debug, Deserialize, Serialize, Clone)]
 pub struct JsonWebKey {
     pub kid: String,
+    /// RSA modulus, empty for EC keys
+    #[serde(default)]
     pub n: String,
     #[serde(rename = "use")]
     pub use_: String,
     pub alg: String,
     pub kty: String,
+    /// RSA public exponent, empty for EC keys
+    #[serde(default)]
     pub e: String,
+    /// EC curve (`P-256`), empty for RSA keys
+    #[serde(default)]
+    pub crv: String,
+    /// EC public key coordinates, empty for RSA keys
+    #[serde(default)]
+    pub x: String,
+    #[serde(default)]
+    pub y: String,
+}
+
+/// Hash of an RSA JWT signature, from the `alg` of its key: RS256, RS384 or RS512.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JwtHashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl JwtHashAlgorithm {
+    pub fn from_alg(alg: &str) -> Result<Self> {
+        match alg {
+            "RS256" => Ok(JwtHashAlgorithm::Sha256),
+            "RS384" => Ok(JwtHashAlgorithm::Sha384),
+            "RS512" => Ok(JwtHashAlgorithm::Sha512),
+            _ => bail!("Unsupported JWT signing algorithm {}", alg),
+        }
+    }
+
+    pub fn alg(&self) -> &'static str {
+        match self {
+            JwtHashAlgorithm::Sha256 => "RS256",
+            JwtHashAlgorithm::Sha384 => "RS384",
+            JwtHashAlgorithm::Sha512 => "RS512",
+        }
+    }
+
+    /// Block size of the hash in bytes, the granularity of the partial SHA.
+    pub fn block_size(&self) -> usize {
+        match self {
+            JwtHashAlgorithm::Sha256 => 64,
+            JwtHashAlgorithm::Sha384 | JwtHashAlgorithm::Sha512 => 128,
+        }
+    }
+
+    /// Intermediate state after the blocks before `hash_until_index`, and the remaining data.
+    /// SHA-256 states are 32-bit words and SHA-384/512 states 64-bit words.
+    pub fn partial_hash(&self, data: &[u8], hash_until_index: usize) -> (Vec<u64>, Vec<u8>) {
+        match self {
+            JwtHashAlgorithm::Sha256 => {
+                let (state, remaining) = generate_partial_sha256(data, hash_until_index);
+                (state.into_iter().map(u64::from).collect(), remaining)
+            }
+            JwtHashAlgorithm::Sha384 => generate_partial_sha512(SHA384_IV, data, hash_until_index),
+            JwtHashAlgorithm::Sha512 => generate_partial_sha512(SHA512_IV, data, hash_until_index),
+        }
+    }
+}
+
+/// RSA modulus sizes with a JWT circuit variant.
+pub const RSA_MODULUS_BITS: [usize; 3] = [2048, 3072, 4096];
+/// Bits per limb of the RSA key and signature circuit inputs.
+pub const RSA_LIMB_BITS: usize = 120;
+
+/// Signing key of a JWT, which selects the circuit variant proving it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RsaJwtKey {
+    pub hash: JwtHashAlgorithm,
+    pub modulus_bits: usize,
+}
+
+impl RsaJwtKey {
+    pub fn new(alg: &str, modulus: &BigUint) -> Result<Self> {
+        let hash = JwtHashAlgorithm::from_alg(alg)?;
+        let modulus_bits = modulus.bits() as usize;
+        if !RSA_MODULUS_BITS.contains(&modulus_bits) {
+            bail!("Unsupported RSA modulus size of {} bits", modulus_bits);
+        }
+        Ok(RsaJwtKey { hash, modulus_bits })
+    }
+
+    pub fn from_jwk(jwk: &JsonWebKey) -> Result<Self> {
+        if jwk.kty != "RSA" {
+            bail!("Unsupported JWT key type {}", jwk.kty);
+        }
+        RsaJwtKey::new(&jwk.alg, &biguint_from_bytes(&base64_url_to_bytes(&jwk.n)?))
+    }
+
+    /// Number of limbs of the modulus, redc params and signature inputs.
+    pub fn limbs(&self) -> usize {
+        self.modulus_bits.div_ceil(RSA_LIMB_BITS)
+    }
+
+    /// Name of the circuit artifact of the key: `circuit/zkjwt_circuit` for RS256 with a
+    /// 2048-bit key, a variant of `circuit/zkjwt_rsa_circuit` otherwise.
+    pub fn circuit_name(&self) -> String {
+        match (self.hash, self.modulus_bits) {
+            (JwtHashAlgorithm::Sha256, 2048) => "verified_anonymous_sns_jwt".to_string(),
+            (hash, bits) => format!(
+                "verified_anonymous_sns_jwt_{}_{}",
+                hash.alg().to_lowercase(),
+                bits
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -34,7 +299,7 @@ pub struct JWTCircuitInputs {
     pub redc_params_limbs: Vec<String>,
     pub signature_limbs: Vec<String>,
     pub partial_data: Option<StorageBlock>,
-    pub partial_hash: Option<Vec<u32>>,
+    pub partial_hash: Option<Vec<u64>>,
     pub full_data_length: Option<usize>,
 }
 
@@ -50,28 +315,57 @@ pub fn generate_inputs(
     sha_precompute_keys: Option<Vec<&str>>,
     max_signed_data_len: usize,
 ) -> Result<JWTCircuitInputs> {
+    let (header_b64, payload_b64, sig_b64) = jwt_parts(jwt)?;
+
+    let signature_bytes = base64_url_to_bytes(sig_b64)?;
+    let signature = biguint_from_bytes(&signature_bytes);
+
+    let n_bytes = base64_url_to_bytes(&pubkey.n)?;
+    let n_big = biguint_from_bytes(&n_bytes);
+    let key = RsaJwtKey::new(&pubkey.alg, &n_big)?;
+    let redc = ((BigUint::from(1u64)) << (2 * key.modulus_bits + 4)) / &n_big;
+
+    let mut inputs = generate_signed_data_inputs(
+        header_b64,
+        payload_b64,
+        key.hash,
+        sha_precompute_keys,
+        max_signed_data_len,
+    )?;
+    inputs.pubkey_modulus_limbs = split_biguint(&n_big, RSA_LIMB_BITS, key.limbs());
+    inputs.redc_params_limbs = split_biguint(&redc, RSA_LIMB_BITS, key.limbs());
+    inputs.signature_limbs = split_biguint(&signature, RSA_LIMB_BITS, key.limbs());
+
+    Ok(inputs)
+}
+
+/// Header, payload and signature of a JWT, base64url encoded.
+pub(crate) fn jwt_parts(jwt: &str) -> Result<(&str, &str, &str)> {
     let parts: Vec<&str> = jwt.split('.').collect();
     if parts.len() != 3 {
         return Err(anyhow!("Invalid JWT format"));
     }
+    Ok((parts[0], parts[1], parts[2]))
+}
 
-    let (header_b64, payload_b64, sig_b64) = (parts[0], parts[1], parts[2]);
+/// Signed data inputs of a JWT, hashed with `hash` up to the first of `sha_precompute_keys`.
+/// The signature inputs are left empty, as they depend on the key type.
+pub(crate) fn generate_signed_data_inputs(
+    header_b64: &str,
+    payload_b64: &str,
+    hash: JwtHashAlgorithm,
+    sha_precompute_keys: Option<Vec<&str>>,
+    max_signed_data_len: usize,
+) -> Result<JWTCircuitInputs> {
     let signed_data = format!("{}.{}", header_b64, payload_b64)
         .as_bytes()
         .to_vec();
     // println!("Signed data : {:?}", signed_data);
 
-    let signature_bytes = base64_url_to_bytes(sig_b64)?;
-    let signature = biguint_from_bytes(&signature_bytes);
-
-    let n_bytes = base64_url_to_bytes(&pubkey.n)?;
-    let n_big = biguint_from_bytes(&n_bytes);
-    let redc = ((BigUint::from(1u64)) << (2 * 2048 + 4)) / &n_big;
-
     let mut inputs = JWTCircuitInputs {
-        pubkey_modulus_limbs: split_biguint(&n_big, 120, 18),
-        redc_params_limbs: split_biguint(&redc, 120, 18),
-        signature_limbs: split_biguint(&signature, 120, 18),
+        pubkey_modulus_limbs: Vec::new(),
+        redc_params_limbs: Vec::new(),
+        signature_limbs: Vec::new(),
         data: None,
         base64_decode_offset: 0,
         partial_data: None,
@@ -100,9 +394,10 @@ pub fn generate_inputs(
             .min()
             .ok_or_else(|| anyhow!("None of the keys found in payload"))?;
 
-        let min_index_b64 = (min_index * 4) / 3;
+        // Start of the base64 group encoding the key, so that it is decoded whole
+        let min_index_b64 = (min_index / 3) * 4;
         let slice_start = header_b64.len() + min_index_b64 + 1;
-        let (partial_hash, remaining) = generate_partial_sha256(&signed_data, slice_start);
+        let (partial_hash, remaining) = hash.partial_hash(&signed_data, slice_start);
 
         if remaining.len() > max_signed_data_len {
             return Err(anyhow!("remaining data too long"));
@@ -112,14 +407,21 @@ pub fn generate_inputs(
         padded[..remaining.len()].copy_from_slice(&remaining);
 
         let sha_cutoff = signed_data.len() - remaining.len();
-        let payload_bytes_in_precompute = sha_cutoff - (header_b64.len() + 1);
-        let offset_to_make_it_4x = 4 - (payload_bytes_in_precompute % 4);
+        let payload_start = header_b64.len() + 1;
+        // A key at the start of the payload (e.g. `iss`) moves the cut-off into the header;
+        // the payload is then decoded from its start in `partial_data`
+        let offset_to_make_it_4x = if sha_cutoff < payload_start {
+            payload_start - sha_cutoff
+        } else {
+            let payload_bytes_in_precompute = sha_cutoff - payload_start;
+            (4 - (payload_bytes_in_precompute % 4)) % 4
+        };
 
         inputs.partial_data = Some(StorageBlock {
             storage: padded,
             len: remaining.len(),
         });
-        inputs.partial_hash = Some(partial_hash.to_vec());
+        inputs.partial_hash = Some(partial_hash);
         inputs.full_data_length = Some(signed_data.len());
         inputs.base64_decode_offset = offset_to_make_it_4x;
     }
@@ -127,57 +429,183 @@ pub fn generate_inputs(
     Ok(inputs)
 }
 
-pub fn generate_jwt_proof(srs_path: String, inputs: HashMap<String, Vec<String>>) -> Vec<u8> {
+pub fn generate_jwt_proof(
+    srs_path: String,
+    inputs: HashMap<String, Vec<String>>,
+) -> Result<Vec<u8>> {
+    generate_jwt_proof_with_transcript(srs_path, inputs, ProofTranscript::Poseidon2)
+}
+
+/// Same as `generate_jwt_proof` with the given transcript; a `Keccak` proof is the one to
+/// submit to the `HonkVerifier` of `contracts/`. Fails when the bundled circuit artifact does
+/// not take the JWT witness inputs, i.e. when it is stale.
+pub fn generate_jwt_proof_with_transcript(
+    srs_path: String,
+    inputs: HashMap<String, Vec<String>>,
+    transcript: ProofTranscript,
+) -> Result<Vec<u8>> {
     // @dev - Store the circuit artifact (circuit JSON)
     const JWT_JSON: &str = include_str!("../../circuit/verified_anonymous_sns_jwt.json");
     //const JWT_JSON: &str = include_str!("../../circuit/stealthnote_jwt.json");
 
-    let bytecode_json: serde_json::Value = serde_json::from_str(&JWT_JSON).unwrap();
-    let bytecode = bytecode_json["bytecode"].as_str().unwrap();
+    let bytecode_json: serde_json::Value = serde_json::from_str(JWT_JSON)?;
+    check_circuit_witness_keys(&bytecode_json, &JWT_WITNESS_KEYS)?;
+    let bytecode = bytecode_json["bytecode"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Circuit artifact has no bytecode"))?;
 
-    // Setup SRS
-    setup_srs_from_bytecode(bytecode, Some(&srs_path), false).unwrap();
+    prove_jwt_circuit(bytecode, &srs_path, &inputs, transcript)
+}
 
-    // Define the expected order of witness values based on the JwtInput struct
-    let witness_key_order = [
-        "partial_data_storage",
-        "partial_data_len",
-        "partial_hash",
-        "full_data_length",
-        "base64_decode_offset",
-        "jwt_pubkey_modulus_limbs",
-        "jwt_pubkey_redc_params_limbs",
-        "jwt_signature_limbs",
-        "domain_storage",
-        "domain_len",
-        "ephemeral_pubkey",
-        "ephemeral_pubkey_salt",
-        "ephemeral_pubkey_expiry",
-    ];
+/// Proves a JWT with the circuit at `circuit_path`, which takes the same inputs as the JWT
+/// circuit: the workspace domain circuit (`circuit/zkjwt_hd_circuit`) or a signing key
+/// variant (`circuit/zkjwt_rsa_circuit`).
+pub fn generate_jwt_proof_with_circuit(
+    srs_path: String,
+    circuit_path: String,
+    inputs: HashMap<String, Vec<String>>,
+) -> Result<Vec<u8>> {
+    let bytecode = load_bytecode(&circuit_path)?;
+    prove_jwt_circuit(&bytecode, &srs_path, &inputs, ProofTranscript::Poseidon2)
+}
+
+/// Order of the witness values of the JWT circuit, based on the JwtInput struct.
+pub(crate) const JWT_WITNESS_KEYS: [&str; 18] = [
+    "partial_data_storage",
+    "partial_data_len",
+    "partial_hash",
+    "full_data_length",
+    "base64_decode_offset",
+    "jwt_pubkey_modulus_limbs",
+    "jwt_pubkey_redc_params_limbs",
+    "jwt_signature_limbs",
+    "domain_storage",
+    "domain_len",
+    "ephemeral_pubkey",
+    "ephemeral_pubkey_salt",
+    "ephemeral_pubkey_expiry",
+    "iss_storage",
+    "iss_len",
+    "aud_storage",
+    "aud_len",
+    "iat",
+];
+
+fn prove_jwt_circuit(
+    bytecode: &str,
+    srs_path: &str,
+    inputs: &HashMap<String, Vec<String>>,
+    transcript: ProofTranscript,
+) -> Result<Vec<u8>> {
+    prove_circuit_with_witness_keys(
+        bytecode,
+        Some(srs_path),
+        inputs,
+        &JWT_WITNESS_KEYS,
+        transcript,
+    )
+}
+
+/// Bytecode of the circuit artifact compiled by `nargo compile` at `circuit_path`.
+pub(crate) fn load_bytecode(circuit_path: &str) -> Result<String> {
+    let circuit_json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(circuit_path)?)?;
+    match circuit_json["bytecode"].as_str() {
+        Some(bytecode) => Ok(bytecode.to_string()),
+        None => bail!("No bytecode in circuit artifact {}", circuit_path),
+    }
+}
 
+/// Witness keys of the parameters in the ABI of a circuit artifact, in order. A struct
+/// parameter such as a `BoundedVec` gives a `{name}_{field}` key per field.
+fn circuit_witness_keys(circuit_json: &serde_json::Value) -> Vec<String> {
+    let mut keys = Vec::new();
+    for parameter in circuit_json["abi"]["parameters"]
+        .as_array()
+        .into_iter()
+        .flatten()
+    {
+        let name = parameter["name"].as_str().unwrap_or_default();
+        match parameter["type"]["fields"].as_array() {
+            Some(fields) if parameter["type"]["kind"] == "struct" => {
+                keys.extend(fields.iter().map(|field| {
+                    format!("{}_{}", name, field["name"].as_str().unwrap_or_default())
+                }))
+            }
+            _ => keys.push(name.to_string()),
+        }
+    }
+    keys
+}
+
+/// Checks that a circuit artifact takes the witness values of `witness_keys`, so a stale
+/// artifact fails with its inputs instead of a witness error of the prover.
+pub(crate) fn check_circuit_witness_keys(
+    circuit_json: &serde_json::Value,
+    witness_keys: &[&str],
+) -> Result<()> {
+    let keys = circuit_witness_keys(circuit_json);
+    if keys != witness_keys {
+        bail!(
+            "Circuit artifact takes the inputs {:?}, not {:?}; recompile it with its build.sh",
+            keys,
+            witness_keys
+        );
+    }
+    Ok(())
+}
+
+/// Values of `inputs` in the order of `witness_keys`.
+pub(crate) fn witness_values(
+    inputs: &HashMap<String, Vec<String>>,
+    witness_keys: &[&str],
+) -> Result<Vec<String>> {
     let mut witness_vec_string: Vec<String> = Vec::new();
-    for key in witness_key_order {
-        match inputs.get(key) {
+    for key in witness_keys {
+        match inputs.get(*key) {
             Some(values) => witness_vec_string.extend(values.iter().cloned()),
-            None => panic!("Missing required input key in HashMap: {}", key),
+            None => bail!("Missing required input key in HashMap: {}", key),
         }
     }
+    Ok(witness_vec_string)
+}
+
+/// Proves a circuit taking the witness values of `witness_keys`, in this order. Without
+/// `srs_path`, the SRS is downloaded.
+pub(crate) fn prove_circuit_with_witness_keys(
+    bytecode: &str,
+    srs_path: Option<&str>,
+    inputs: &HashMap<String, Vec<String>>,
+    witness_keys: &[&str],
+    transcript: ProofTranscript,
+) -> Result<Vec<u8>> {
+    // Setup SRS
+    setup_srs_from_bytecode(bytecode, srs_path, false).map_err(|e| anyhow!(e))?;
 
+    let witness_vec_string = witness_values(inputs, witness_keys)?;
     // Convert Vec<String> to Vec<&str> for the function call
     let witness_vec_str: Vec<&str> = witness_vec_string.iter().map(AsRef::as_ref).collect();
 
-    let initial_witness = from_vec_str_to_witness_map(witness_vec_str).unwrap();
-
     // Start timing the proof generation
     let start = std::time::Instant::now();
-    let proof = prove_ultra_honk(bytecode, initial_witness, false).unwrap();
+    let proof = transcript.prove(bytecode, witness_vec_str)?;
 
     println!("Proof generation time: {:?}", start.elapsed());
 
-    proof
+    Ok(proof)
 }
 
 pub fn verify_jwt(srs_path: String, proof: Vec<u8>) -> bool {
+    verify_jwt_with_transcript(srs_path, proof, ProofTranscript::Poseidon2)
+}
+
+/// Verifies a proof of `generate_jwt_proof_with_transcript` natively, with the verification
+/// key of the same transcript.
+pub fn verify_jwt_with_transcript(
+    srs_path: String,
+    proof: Vec<u8>,
+    transcript: ProofTranscript,
+) -> bool {
     // @dev - Store the circuit artifact (circuit JSON)
     const JWT_JSON: &str = include_str!("../../circuit/verified_anonymous_sns_jwt.json");
     //const JWT_JSON: &str = include_str!("../../circuit/stealthnote_jwt.json");
@@ -189,11 +617,11 @@ pub fn verify_jwt(srs_path: String, proof: Vec<u8>) -> bool {
     setup_srs_from_bytecode(bytecode, Some(&srs_path), false).unwrap();
 
     // Get the verification key
-    let vk = get_honk_verification_key(bytecode, false).unwrap();
+    let vk = transcript.verification_key(bytecode).unwrap();
 
     // Start timing the proof verification
     let start = std::time::Instant::now();
-    let verdict = verify_ultra_honk(proof, vk).unwrap();
+    let verdict = transcript.verify(proof, vk).unwrap();
 
     println!("Proof verification time: {:?}", start.elapsed());
     println!("Proof verification verdict: {}", verdict);
@@ -236,6 +664,8 @@ struct MessageResponse {
 struct ProofArgs {
     keyId: String,
     jwtCircuitVersion: String,
+    #[serde(default)]
+    jwtIat: u64,
 }
 
 fn get_ephemeral_pubkey() -> Option<String> {
@@ -334,7 +764,7 @@ fn flatten_u8_arrays(arrays: Vec<Vec<u8>>) -> Vec<u8> {
     result
 }
 
-fn flatten_fields_as_array(fields: &[String]) -> Vec<u8> {
+pub(crate) fn flatten_fields_as_array(fields: &[String]) -> Vec<u8> {
     let parsed_fields: Vec<Vec<u8>> = fields.iter().map(|s| hex_to_u8_array(s)).collect();
     flatten_u8_arrays(parsed_fields)
 }
@@ -346,7 +776,11 @@ fn num_to_uint32_be(n: u32, buffer_size: usize) -> Vec<u8> {
     buf
 }
 
-fn reconstruct_honk_proof(public_inputs: &[u8], proof: &[u8], field_byte_size: usize) -> Vec<u8> {
+pub(crate) fn reconstruct_honk_proof(
+    public_inputs: &[u8],
+    proof: &[u8],
+    field_byte_size: usize,
+) -> Vec<u8> {
     let total_size = (public_inputs.len() + proof.len()) / field_byte_size;
     let proof_size = num_to_uint32_be(total_size as u32, 4);
 
@@ -364,13 +798,31 @@ pub fn prepare_public_inputs(
     ephemeral_pubkey: BigUint,
     parsed_ephemeral_pubkey_expiry: DateTime<Utc>,
 ) -> Vec<String> {
-    let mut public_inputs = Vec::new();
+    let mut public_inputs = modulus_public_inputs(&jwt_pubkey);
+    public_inputs.extend(prepare_domain_public_inputs(
+        domain,
+        ephemeral_pubkey,
+        parsed_ephemeral_pubkey_expiry,
+    ));
+    public_inputs
+}
 
-    // === 1. Modulus limbs (18 limbs of 120 bits each) ===
-    let modulus_limbs = split_bigint_to_limbs(&jwt_pubkey, 120, 18);
-    for limb in modulus_limbs.clone() {
-        public_inputs.push(format!("0x{:0>64x}", limb));
-    }
+// === 1. Modulus limbs (120 bits each, 18 limbs for a 2048-bit key) ===
+pub(crate) fn modulus_public_inputs(jwt_pubkey: &BigUint) -> Vec<String> {
+    let num_limbs = (jwt_pubkey.bits() as usize).div_ceil(RSA_LIMB_BITS);
+    split_bigint_to_limbs(jwt_pubkey, RSA_LIMB_BITS, num_limbs)
+        .iter()
+        .map(|limb| format!("0x{:0>64x}", limb))
+        .collect()
+}
+
+/// Public inputs after the JWT signing key: domain, ephemeral pubkey and expiry.
+pub(crate) fn prepare_domain_public_inputs(
+    domain: String,
+    ephemeral_pubkey: BigUint,
+    parsed_ephemeral_pubkey_expiry: DateTime<Utc>,
+) -> Vec<String> {
+    let mut public_inputs = Vec::new();
 
     // === 2. Domain as 64-byte padded array ===
     let mut domain_bytes = [0u8; 64];
@@ -385,58 +837,252 @@ pub fn prepare_public_inputs(
     public_inputs.push(format!("0x{:0>64x}", domain.len()));
 
     // === 4. Ephemeral pubkey shifted right by 3 bits ===
-    let shifted_pubkey = &ephemeral_pubkey >> 3;
-    public_inputs.push(format!("0x{:0>64x}", shifted_pubkey));
+    let shifted_pubkey = EphemeralPubkeyCommitment::encode_pubkey(&ephemeral_pubkey)
+        .expect("Invalid ephemeral pubkey");
+    public_inputs.push(format!("0x{:0>64x}", field_to_biguint(shifted_pubkey)));
 
     // === 5. Expiry timestamp in seconds since epoch ===
-    // Parse to DateTime<Utc>
-    let parsed_datetime: DateTime<Utc> = parsed_ephemeral_pubkey_expiry;
+    let epoch_seconds = EphemeralPubkeyCommitment::encode_expiry(parsed_ephemeral_pubkey_expiry)
+        .expect("Invalid ephemeral pubkey expiry");
+    public_inputs.push(format!("0x{:0>64x}", epoch_seconds));
+
+    public_inputs
+}
 
-    // Get epoch seconds
-    let epoch_seconds = parsed_datetime.timestamp();
+/// Public inputs of the `iss` and `aud` bounded vectors, after those of `prepare_public_inputs`.
+pub fn prepare_oauth_client_public_inputs(iss: &str, aud: &str) -> Result<Vec<String>> {
+    let mut public_inputs = Vec::new();
+    for (value, max_len) in [(iss, MAX_ISS_LENGTH), (aud, MAX_AUD_LENGTH)] {
+        let (storage, len) = bounded_vec_inputs(value, max_len)?;
+        for field in storage.iter().chain([&len]) {
+            public_inputs.push(format!("0x{:0>64x}", field.parse::<usize>()?));
+        }
+    }
+    Ok(public_inputs)
+}
 
-    // Format to hex with 64-character padding
-    let formatted = format!("0x{:0>64x}", epoch_seconds);
-    public_inputs.push(formatted);
+/// Adds the `iss` and `aud` witness of the JWT `payload` to the circuit inputs.
+pub fn insert_oauth_client_inputs(
+    payload: &serde_json::Value,
+    inputs: &mut HashMap<String, Vec<String>>,
+) -> Result<()> {
+    for (key, max_len) in [("iss", MAX_ISS_LENGTH), ("aud", MAX_AUD_LENGTH)] {
+        let (storage, len) = bounded_vec_inputs(claim_str(payload, key)?, max_len)?;
+        inputs.insert(format!("{}_storage", key), storage);
+        inputs.insert(format!("{}_len", key), vec![len]);
+    }
+    Ok(())
+}
 
-    public_inputs
+/// Adds the `iat` witness of the JWT `payload` to the circuit inputs.
+pub fn insert_iat_input(
+    payload: &serde_json::Value,
+    inputs: &mut HashMap<String, Vec<String>>,
+) -> Result<()> {
+    let iat = payload[IAT_CLAIM_KEY]
+        .as_u64()
+        .ok_or_else(|| anyhow!("JWT has no numeric claim {}", IAT_CLAIM_KEY))?;
+    inputs.insert(IAT_CLAIM_KEY.to_string(), vec![iat.to_string()]);
+    Ok(())
+}
+
+/// Checks that a JWT was issued by Google for one of the `allowed_client_ids`.
+pub fn check_oauth_client(iss: &str, aud: &str, allowed_client_ids: &[String]) -> Result<()> {
+    check_oauth_client_with_issuers(iss, aud, &google_issuers(), allowed_client_ids)
+}
+
+/// `GOOGLE_ISSUERS` as an issuer allow-list.
+pub fn google_issuers() -> Vec<String> {
+    GOOGLE_ISSUERS.iter().map(|iss| iss.to_string()).collect()
+}
+
+/// Checks that a JWT was issued by one of the `allowed_issuers`, e.g. the identity provider of
+/// an enterprise, for one of the `allowed_client_ids`.
+pub fn check_oauth_client_with_issuers(
+    iss: &str,
+    aud: &str,
+    allowed_issuers: &[String],
+    allowed_client_ids: &[String],
+) -> Result<()> {
+    if !allowed_issuers.iter().any(|issuer| issuer == iss) {
+        bail!("JWT issuer {} is not an allowed issuer", iss);
+    }
+    if !allowed_client_ids.iter().any(|client_id| client_id == aud) {
+        bail!("JWT audience {} is not an allowed OAuth client id", aud);
+    }
+    Ok(())
 }
 
-fn extract_proof(result: &[u8], public_inputs_len: usize) -> &[u8] {
+pub(crate) fn extract_proof(result: &[u8], public_inputs_len: usize) -> &[u8] {
     let offset = 4 + public_inputs_len;
     &result[offset..]
 }
 
+/// Checks the public inputs of a membership proof before verifying it: a JWT of Google
+/// minted for one of the `allowed_client_ids`, and an ephemeral key valid at `now`.
+pub(crate) fn check_public_inputs(
+    public_inputs: &JwtPublicInputs,
+    allowed_client_ids: &[String],
+    expiry_policy: &EphemeralExpiryPolicy,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    check_public_inputs_with_issuers(
+        public_inputs,
+        &google_issuers(),
+        allowed_client_ids,
+        expiry_policy,
+        now,
+    )
+}
+
+/// Same as `check_public_inputs` for a JWT of one of the `allowed_issuers`.
+pub(crate) fn check_public_inputs_with_issuers(
+    public_inputs: &JwtPublicInputs,
+    allowed_issuers: &[String],
+    allowed_client_ids: &[String],
+    expiry_policy: &EphemeralExpiryPolicy,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    check_oauth_client_with_issuers(
+        &public_inputs.iss,
+        &public_inputs.aud,
+        allowed_issuers,
+        allowed_client_ids,
+    )?;
+    let expiry = public_inputs
+        .ephemeral_pubkey_expiry
+        .parse::<DateTime<Utc>>()
+        .map_err(|e| anyhow!("Invalid ephemeral pubkey expiry: {}", e))?;
+    expiry_policy.check(expiry, public_inputs.iat, now)
+}
+
+/// Verifies a proof of the JWT circuit without public inputs. The proof is rejected with an
+/// error unless `check_public_inputs` accepts its public inputs at `now`.
 pub fn verify_jwt_proof(
     srs_path: String,
     proof: Vec<u8>,
-    domain: String,
-    google_jwt_pubkey_modulus: String,
-    ephemeral_pubkey: String,
-    ephemeral_pubkey_expiry: String,
-) -> bool {
-    let jwt_pubkey = pubkey_modulus_from_jwk(&google_jwt_pubkey_modulus).unwrap();
-    let ephemeral_pubkey_biguint = BigUint::from_str(&ephemeral_pubkey).unwrap();
-    let parsed_ephemeral_pubkey_expiry: DateTime<Utc> = ephemeral_pubkey_expiry
+    public_inputs: &JwtPublicInputs,
+    allowed_client_ids: &[String],
+    expiry_policy: &EphemeralExpiryPolicy,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    verify_jwt_proof_with_transcript(
+        srs_path,
+        proof,
+        public_inputs,
+        allowed_client_ids,
+        expiry_policy,
+        now,
+        ProofTranscript::Poseidon2,
+    )
+}
+
+/// Same as `verify_jwt_proof` for a proof of `generate_jwt_proof_with_transcript`.
+pub fn verify_jwt_proof_with_transcript(
+    srs_path: String,
+    proof: Vec<u8>,
+    public_inputs: &JwtPublicInputs,
+    allowed_client_ids: &[String],
+    expiry_policy: &EphemeralExpiryPolicy,
+    now: DateTime<Utc>,
+    transcript: ProofTranscript,
+) -> Result<bool> {
+    check_public_inputs(public_inputs, allowed_client_ids, expiry_policy, now)?;
+    let proof = with_public_inputs(proof, public_inputs);
+
+    Ok(verify_jwt_with_transcript(srs_path, proof, transcript))
+}
+
+/// Verifies a proof of `generate_jwt_proof_with_circuit`. It has the same public inputs as a
+/// proof of the JWT circuit, but only verifies against the verification key of the
+/// circuit at `circuit_path`.
+pub fn verify_jwt_proof_with_circuit(
+    srs_path: String,
+    circuit_path: String,
+    proof: Vec<u8>,
+    public_inputs: &JwtPublicInputs,
+    allowed_client_ids: &[String],
+    expiry_policy: &EphemeralExpiryPolicy,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    check_public_inputs(public_inputs, allowed_client_ids, expiry_policy, now)?;
+    let proof = with_public_inputs(proof, public_inputs);
+
+    let bytecode = load_bytecode(&circuit_path)?;
+    setup_srs_from_bytecode(&bytecode, Some(&srs_path), false).map_err(|e| anyhow!(e))?;
+    let transcript = ProofTranscript::Poseidon2;
+    let vk = transcript.verification_key(&bytecode)?;
+
+    let start = std::time::Instant::now();
+    let verdict = transcript.verify(proof, vk)?;
+    println!("Proof verification time: {:?}", start.elapsed());
+
+    Ok(verdict)
+}
+
+/// Prepends the public inputs of the membership circuits to a proof without public inputs.
+fn with_public_inputs(proof: Vec<u8>, public_inputs: &JwtPublicInputs) -> Vec<u8> {
+    let jwt_pubkey = pubkey_modulus_from_jwk(&public_inputs.google_jwt_pubkey_modulus).unwrap();
+    let mut fields = modulus_public_inputs(&jwt_pubkey);
+    fields.extend(claim_public_inputs(public_inputs));
+
+    reconstruct_honk_proof(&flatten_fields_as_array(&fields), &proof, 32)
+}
+
+/// Public inputs of the membership circuits after the JWT signing key.
+pub(crate) fn claim_public_inputs(public_inputs: &JwtPublicInputs) -> Vec<String> {
+    let ephemeral_pubkey_biguint = BigUint::from_str(&public_inputs.ephemeral_pubkey).unwrap();
+    let parsed_ephemeral_pubkey_expiry: DateTime<Utc> = public_inputs
+        .ephemeral_pubkey_expiry
         .parse::<DateTime<Utc>>()
         .expect("Invalid datetime format");
 
-    let public_inputs = prepare_public_inputs(
-        jwt_pubkey,
-        domain,
+    let mut fields = prepare_domain_public_inputs(
+        public_inputs.domain.clone(),
         ephemeral_pubkey_biguint,
         parsed_ephemeral_pubkey_expiry,
     );
+    fields.extend(
+        prepare_oauth_client_public_inputs(&public_inputs.iss, &public_inputs.aud)
+            .expect("Invalid iss or aud"),
+    );
+    fields.push(format!("0x{:0>64x}", public_inputs.iat));
 
-    let proof = reconstruct_honk_proof(&flatten_fields_as_array(&public_inputs), &proof, 32);
+    fields
+}
 
-    let verified = verify_jwt(srs_path, proof);
-    verified
+/// Decodes the (unverified) JSON payload of a JWT.
+pub fn decode_jwt_payload(jwt: &str) -> Result<serde_json::Value> {
+    let payload_b64 = jwt
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| anyhow!("Invalid JWT format"))?;
+    Ok(serde_json::from_slice(&base64_url_to_bytes(payload_b64)?)?)
 }
 
 //
 // utils
 //
+pub(crate) fn claim_str<'a>(payload: &'a serde_json::Value, key: &str) -> Result<&'a str> {
+    payload[key]
+        .as_str()
+        .ok_or_else(|| anyhow!("JWT has no string claim {}", key))
+}
+
+/// Storage and length of a bounded vector of `max_len` bytes.
+pub(crate) fn bounded_vec_inputs(value: &str, max_len: usize) -> Result<(Vec<String>, String)> {
+    if value.len() > max_len {
+        bail!("Claim {} longer than {} bytes", value, max_len);
+    }
+
+    let mut storage = value.as_bytes().to_vec();
+    storage.resize(max_len, 0);
+    Ok((
+        storage.iter().map(|b| b.to_string()).collect(),
+        value.len().to_string(),
+    ))
+}
+
 fn base64_url_to_bytes(s: &str) -> Result<Vec<u8>> {
     URL_SAFE_NO_PAD
         .decode(s)
@@ -447,129 +1093,592 @@ fn biguint_from_bytes(bytes: &[u8]) -> BigUint {
     BigUint::from_bytes_be(bytes)
 }
 
-fn split_biguint(num: &BigUint, chunk_bits: usize, n_chunks: usize) -> Vec<String> {
+pub(crate) fn split_biguint(num: &BigUint, chunk_bits: usize, n_chunks: usize) -> Vec<String> {
     let mask = (BigUint::from(1u64) << chunk_bits) - 1u64;
     (0..n_chunks)
         .map(|i| ((num >> (i * chunk_bits)) & &mask).to_string())
         .collect()
 }
 
-pub fn generate_partial_sha256(data: &[u8], hash_until_index: usize) -> (Vec<u32>, Vec<u8>) {
-    let block_size = 64; // 512 bits
-    let block_index = hash_until_index / block_size;
+/// SHA-256 of signed data hashed outside of the circuit up to a block boundary, which the
+/// circuit finishes over `remaining` (`partial_sha256_var_end`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartialSha256 {
+    /// Intermediate hash state after the first `hashed_len` bytes
+    pub state: [u32; 8],
+    /// Data after the hashed blocks
+    pub remaining: Vec<u8>,
+    /// Bytes hashed into `state`, a multiple of `BLOCK_SIZE`
+    pub hashed_len: usize,
+}
+
+impl PartialSha256 {
+    pub const BLOCK_SIZE: usize = 64; // 512 bits
 
     // Initial hash values (first 32 bits of the fractional parts of the square roots of the first 8 primes)
-    let mut h = [
-        0x6a09e667u32,
-        0xbb67ae85,
-        0x3c6ef372,
-        0xa54ff53a,
-        0x510e527f,
-        0x9b05688c,
-        0x1f83d9ab,
+    const IV: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
         0x5be0cd19,
     ];
 
-    for i in 0..block_index {
-        if i * block_size >= data.len() {
-            panic!("Block index out of range.");
+    /// Hashes the blocks of `data` before `hash_until_index`.
+    pub fn new(data: &[u8], hash_until_index: usize) -> Result<Self> {
+        if hash_until_index > data.len() {
+            bail!(
+                "Partial SHA-256 cut-off {} after the end of the data",
+                hash_until_index
+            );
         }
 
-        let mut block = [0u8; 64];
-        let end_idx = std::cmp::min((i + 1) * block_size, data.len());
-        block[..(end_idx - i * block_size)].copy_from_slice(&data[i * block_size..end_idx]);
+        let hashed_len = hash_until_index / Self::BLOCK_SIZE * Self::BLOCK_SIZE;
+        let mut state = Self::IV;
+        let blocks: Vec<_> = data[..hashed_len]
+            .chunks_exact(Self::BLOCK_SIZE)
+            .map(GenericArray::clone_from_slice)
+            .collect();
+        sha2::compress256(&mut state, &blocks);
+
+        Ok(PartialSha256 {
+            state,
+            remaining: data[hashed_len..].to_vec(),
+            hashed_len,
+        })
+    }
 
-        sha256_block(&mut h, &block);
+    /// Digest of the whole data, padding `remaining` as the circuit does.
+    pub fn finalize(&self) -> [u8; 32] {
+        let data_len = self.hashed_len + self.remaining.len();
+        let mut padded = self.remaining.clone();
+        padded.push(0x80);
+        while padded.len() % Self::BLOCK_SIZE != Self::BLOCK_SIZE - 8 {
+            padded.push(0);
+        }
+        padded.extend_from_slice(&(data_len as u64 * 8).to_be_bytes());
+
+        let mut state = self.state;
+        let blocks: Vec<_> = padded
+            .chunks_exact(Self::BLOCK_SIZE)
+            .map(GenericArray::clone_from_slice)
+            .collect();
+        sha2::compress256(&mut state, &blocks);
+
+        let mut digest = [0u8; 32];
+        for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
     }
+}
 
-    // Return the intermediate digest and remaining data
-    (h.to_vec(), data[block_index * block_size..].to_vec())
+pub fn generate_partial_sha256(data: &[u8], hash_until_index: usize) -> (Vec<u32>, Vec<u8>) {
+    let partial = PartialSha256::new(data, hash_until_index).expect("Block index out of range.");
+    (partial.state.to_vec(), partial.remaining)
 }
 
-// SHA-256 constants (first 32 bits of fractional parts of cube roots of primes)
-const K: [u32; 64] = [
-    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
-    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
-    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
-    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
-    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
-    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
-    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
-    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+// SHA-512 and SHA-384 initial hash values
+const SHA512_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
 ];
+const SHA384_IV: [u64; 8] = [
+    0xcbbb9d5dc1059ed8,
+    0x629a292a367cd507,
+    0x9159015a3070dd17,
+    0x152fecd8f70e5939,
+    0x67332667ffc00b31,
+    0x8eb44a8768581511,
+    0xdb0c2e0d64f98fa7,
+    0x47b5481dbefa4fa4,
+];
+
+/// Same as `generate_partial_sha256` for SHA-512 and SHA-384 (SHA-512 from `iv`), on
+/// 128-byte blocks.
+pub fn generate_partial_sha512(
+    iv: [u64; 8],
+    data: &[u8],
+    hash_until_index: usize,
+) -> (Vec<u64>, Vec<u8>) {
+    let block_size = 128; // 1024 bits
+    let block_index = hash_until_index / block_size;
+    if hash_until_index > data.len() {
+        panic!("Block index out of range.");
+    }
 
-// Rotate right function (SHA-256 bitwise operations)
-#[inline]
-fn rotr(n: u32, x: u32) -> u32 {
-    (x >> n) | (x << (32 - n))
-}
-
-// SHA-256 Compression Function (Processes 64-byte blocks)
-fn sha256_block(h: &mut [u32; 8], block: &[u8; 64]) {
-    let mut w = [0u32; 64];
-    let mut a = h[0];
-    let mut b = h[1];
-    let mut c = h[2];
-    let mut d = h[3];
-    let mut e = h[4];
-    let mut f = h[5];
-    let mut g = h[6];
-    let mut h_val = h[7];
-
-    // Convert block into 32-bit words
-    for i in 0..16 {
-        w[i] = ((block[i * 4] as u32) << 24)
-            | ((block[i * 4 + 1] as u32) << 16)
-            | ((block[i * 4 + 2] as u32) << 8)
-            | (block[i * 4 + 3] as u32);
-    }
-
-    for i in 16..64 {
-        let s0 = rotr(7, w[i - 15]) ^ rotr(18, w[i - 15]) ^ (w[i - 15] >> 3);
-        let s1 = rotr(17, w[i - 2]) ^ rotr(19, w[i - 2]) ^ (w[i - 2] >> 10);
-        w[i] = w[i - 16]
-            .wrapping_add(s0)
-            .wrapping_add(w[i - 7])
-            .wrapping_add(s1);
-    }
-
-    // Main compression loop
-    for i in 0..64 {
-        let s1 = rotr(6, e) ^ rotr(11, e) ^ rotr(25, e);
-        let ch = (e & f) ^ (!e & g);
-        let temp1 = h_val
-            .wrapping_add(s1)
-            .wrapping_add(ch)
-            .wrapping_add(K[i])
-            .wrapping_add(w[i]);
-        let s0 = rotr(2, a) ^ rotr(13, a) ^ rotr(22, a);
-        let maj = (a & b) ^ (a & c) ^ (b & c);
-        let temp2 = s0.wrapping_add(maj);
-
-        h_val = g;
-        g = f;
-        f = e;
-        e = d.wrapping_add(temp1);
-        d = c;
-        c = b;
-        b = a;
-        a = temp1.wrapping_add(temp2);
-    }
-
-    // Update intermediate hash values
-    h[0] = h[0].wrapping_add(a);
-    h[1] = h[1].wrapping_add(b);
-    h[2] = h[2].wrapping_add(c);
-    h[3] = h[3].wrapping_add(d);
-    h[4] = h[4].wrapping_add(e);
-    h[5] = h[5].wrapping_add(f);
-    h[6] = h[6].wrapping_add(g);
-    h[7] = h[7].wrapping_add(h_val);
+    let mut h = iv;
+    let blocks: Vec<_> = data[..block_index * block_size]
+        .chunks_exact(block_size)
+        .map(|block| GenericArray::clone_from_slice(block))
+        .collect();
+    sha2::compress512(&mut h, &blocks);
+
+    (h.to_vec(), data[block_index * block_size..].to_vec())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_check_circuit_witness_keys() {
+        let circuit_json = serde_json::json!({"abi": {"parameters": [
+            {"name": "domain", "type": {"kind": "struct", "path": "std::collections::bounded_vec::BoundedVec", "fields": [
+                {"name": "storage", "type": {"kind": "array", "length": 64}},
+                {"name": "len", "type": {"kind": "integer"}},
+            ]}},
+            {"name": "iat", "type": {"kind": "integer"}},
+        ]}});
+        check_circuit_witness_keys(&circuit_json, &["domain_storage", "domain_len", "iat"])
+            .unwrap();
+        let err = check_circuit_witness_keys(&circuit_json, &["domain_storage", "domain_len"])
+            .unwrap_err();
+        assert!(err.to_string().contains("recompile"));
+    }
+
+    #[test]
+    fn test_jwt_circuit_artifact_matches_inputs() {
+        let circuit_json: serde_json::Value = serde_json::from_str(include_str!(
+            "../../circuit/verified_anonymous_sns_jwt.json"
+        ))
+        .unwrap();
+        check_circuit_witness_keys(&circuit_json, &JWT_WITNESS_KEYS).unwrap();
+        assert_eq!(
+            circuit_json["abi"]["parameters"][0]["type"]["fields"][0]["type"]["length"],
+            MAX_JWT_PARTIAL_DATA_LENGTH
+        );
+    }
+
+    const SAMPLE_KID: &str = "07b80a365428525f8bf7cd0846d74a8ee4ef3625";
+
+    fn sample_jwt(payload: &str) -> String {
+        let header = format!(r#"{{"alg":"RS256","kid":"{}","typ":"JWT"}}"#, SAMPLE_KID);
+        format!(
+            "{}.{}.{}",
+            URL_SAFE_NO_PAD.encode(header),
+            URL_SAFE_NO_PAD.encode(payload),
+            URL_SAFE_NO_PAD.encode([7u8; 256]),
+        )
+    }
+
+    fn sample_jwk() -> JsonWebKey {
+        JsonWebKey {
+            kid: SAMPLE_KID.to_string(),
+            n: URL_SAFE_NO_PAD.encode([0xffu8; 256]),
+            use_: "sig".to_string(),
+            alg: "RS256".to_string(),
+            kty: "RSA".to_string(),
+            e: "AQAB".to_string(),
+            crv: String::new(),
+            x: String::new(),
+            y: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_membership_type_domain() {
+        let workspace: serde_json::Value = serde_json::from_str(
+            r#"{"sub":"1","hd":"pse.dev","email":"alice@mail.pse.dev","email_verified":true}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            MembershipType::WorkspaceDomain
+                .domain_of(&workspace)
+                .unwrap(),
+            "pse.dev"
+        );
+        // Subdomain addresses only prove their own email domain
+        assert_eq!(
+            MembershipType::EmailDomain.domain_of(&workspace).unwrap(),
+            "mail.pse.dev"
+        );
+
+        let consumer: serde_json::Value =
+            serde_json::from_str(r#"{"sub":"1","email":"alice@gmail.com","email_verified":true}"#)
+                .unwrap();
+        assert!(MembershipType::WorkspaceDomain
+            .domain_of(&consumer)
+            .is_err());
+    }
+
+    #[test]
+    fn test_membership_cutoff_is_before_oauth_client() {
+        let padding = "x".repeat(400);
+        let payload = format!(
+            r#"{{"iss":"https://accounts.google.com","azp":"{}","aud":"1006701293748-1irmgu910p2n7vjmoa441ramm6dcrveb.apps.googleusercontent.com","sub":"108522077721826439364","hd":"pse.dev","email":"vivianjeng@pse.dev","email_verified":true,"nonce":"1","exp":1746007680}}"#,
+            padding
+        );
+        let jwt = sample_jwt(&payload);
+        let payload_start = jwt.find('.').unwrap() + 1;
+        let inputs = |membership_type: MembershipType, max_len: usize| {
+            generate_inputs(
+                &jwt,
+                &sample_jwk(),
+                Some(membership_type.sha_precompute_keys()),
+                max_len,
+            )
+        };
+
+        for membership_type in [MembershipType::EmailDomain, MembershipType::WorkspaceDomain] {
+            let partial_data = inputs(membership_type, MAX_JWT_PARTIAL_DATA_LENGTH)
+                .unwrap()
+                .partial_data
+                .unwrap();
+            // `iss` opens the payload, so the whole payload is hashed in-circuit
+            assert!(jwt.rfind('.').unwrap() - partial_data.len < payload_start);
+            assert!(inputs(membership_type, 640).is_err());
+        }
+    }
+
+    #[test]
+    fn test_check_oauth_client() {
+        let client_id = "1006701293748-1irmgu910p2n7vjmoa441ramm6dcrveb.apps.googleusercontent.com";
+        let allowed = vec![client_id.to_string()];
+
+        assert!(check_oauth_client(GOOGLE_ISSUERS[0], client_id, &allowed).is_ok());
+        assert!(check_oauth_client(GOOGLE_ISSUERS[1], client_id, &allowed).is_ok());
+        // Token minted for another app
+        assert!(
+            check_oauth_client(GOOGLE_ISSUERS[0], "42.apps.googleusercontent.com", &allowed)
+                .is_err()
+        );
+        assert!(
+            check_oauth_client("https://login.microsoftonline.com", client_id, &allowed).is_err()
+        );
+        assert!(check_oauth_client(GOOGLE_ISSUERS[0], client_id, &[]).is_err());
+
+        let issuers = vec!["https://login.microsoftonline.com".to_string()];
+        assert!(
+            check_oauth_client_with_issuers(&issuers[0], client_id, &issuers, &allowed).is_ok()
+        );
+        assert!(
+            check_oauth_client_with_issuers(GOOGLE_ISSUERS[0], client_id, &issuers, &allowed)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_ephemeral_expiry_policy() {
+        let policy = EphemeralExpiryPolicy::default();
+        let iat = 1746004080;
+        let issued_at = DateTime::from_timestamp(iat as i64, 0).unwrap();
+        let expiry = issued_at + chrono::Duration::days(6);
+
+        assert!(policy.check(expiry, iat, issued_at).is_ok());
+        // Expired by the wall clock
+        assert!(policy
+            .check(expiry, iat, expiry + chrono::Duration::seconds(1))
+            .is_err());
+        // Expiry too far beyond the token issue time
+        let far_expiry = issued_at + chrono::Duration::days(8);
+        assert!(policy.check(far_expiry, iat, issued_at).is_err());
+        let lenient = EphemeralExpiryPolicy {
+            max_lifetime_secs: 30 * 24 * 3600,
+        };
+        assert!(lenient.check(far_expiry, iat, issued_at).is_ok());
+        // Expiry before the token was issued
+        assert!(policy
+            .check(
+                issued_at - chrono::Duration::seconds(1),
+                iat,
+                issued_at - chrono::Duration::days(1)
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_verify_jwt_proof_rejects_expired_key() {
+        let client_id = "client".to_string();
+        let public_inputs = JwtPublicInputs {
+            domain: "pse.dev".to_string(),
+            google_jwt_pubkey_modulus: sample_jwk().n,
+            ephemeral_pubkey: "1".to_string(),
+            ephemeral_pubkey_expiry: "2025-05-07T09:07:57.379Z".to_string(),
+            iss: GOOGLE_ISSUERS[0].to_string(),
+            aud: client_id.clone(),
+            iat: 1746004080,
+        };
+        let policy = EphemeralExpiryPolicy::default();
+        let before_expiry: DateTime<Utc> = "2025-05-01T09:07:57.379Z".parse().unwrap();
+        let after_expiry: DateTime<Utc> = "2025-05-08T09:07:57.379Z".parse().unwrap();
+
+        let allowed = vec![client_id];
+        let check = |now| check_public_inputs(&public_inputs, &allowed, &policy, now);
+        assert!(check(before_expiry).is_ok());
+        assert!(check(after_expiry).is_err());
+        // Rejected before the proof is looked at
+        let err = verify_jwt_proof(
+            "public/jwt-srs.local".to_string(),
+            vec![],
+            &public_inputs,
+            &allowed,
+            &policy,
+            after_expiry,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("expired"));
+    }
+
+    #[test]
+    fn test_oauth_client_public_inputs_match_witness() {
+        let payload: serde_json::Value =
+            serde_json::from_str(r#"{"iss":"accounts.google.com","aud":"client"}"#).unwrap();
+        let mut inputs = HashMap::new();
+        insert_oauth_client_inputs(&payload, &mut inputs).unwrap();
+
+        let witness: Vec<String> = ["iss_storage", "iss_len", "aud_storage", "aud_len"]
+            .iter()
+            .flat_map(|key| inputs[*key].clone())
+            .map(|value| format!("0x{:0>64x}", value.parse::<usize>().unwrap()))
+            .collect();
+        let public_inputs =
+            prepare_oauth_client_public_inputs("accounts.google.com", "client").unwrap();
+        assert_eq!(public_inputs.len(), MAX_ISS_LENGTH + 1 + MAX_AUD_LENGTH + 1);
+        assert_eq!(public_inputs, witness);
+
+        assert!(insert_oauth_client_inputs(
+            &serde_json::from_str(r#"{"iss":"accounts.google.com"}"#).unwrap(),
+            &mut inputs
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_partial_sha512_matches_sha2() {
+        use sha2::{Digest, Sha384, Sha512};
+
+        // Finishes the hash of `data` from its partial state, as the circuit does
+        let finish = |state: Vec<u64>, remaining: &[u8], data_len: usize| {
+            let mut padded = remaining.to_vec();
+            padded.push(0x80);
+            while padded.len() % 128 != 112 {
+                padded.push(0);
+            }
+            padded.extend_from_slice(&(data_len as u128 * 8).to_be_bytes());
+
+            let mut h: [u64; 8] = state.try_into().unwrap();
+            let blocks: Vec<_> = padded
+                .chunks_exact(128)
+                .map(GenericArray::clone_from_slice)
+                .collect();
+            sha2::compress512(&mut h, &blocks);
+            h.iter()
+                .flat_map(|word| word.to_be_bytes())
+                .collect::<Vec<u8>>()
+        };
+
+        let data: Vec<u8> = (0..700u32).map(|i| (i * 7 % 251) as u8).collect();
+        for hash_until_index in [0, 127, 128, 300, 640, 700] {
+            let (state, remaining) = JwtHashAlgorithm::Sha512.partial_hash(&data, hash_until_index);
+            assert_eq!(remaining.len(), data.len() - hash_until_index / 128 * 128);
+            assert_eq!(
+                finish(state, &remaining, data.len()),
+                Sha512::digest(&data).to_vec()
+            );
+
+            let (state, remaining) = JwtHashAlgorithm::Sha384.partial_hash(&data, hash_until_index);
+            assert_eq!(
+                finish(state, &remaining, data.len())[..48],
+                Sha384::digest(&data)[..]
+            );
+        }
+
+        let (state, remaining) = JwtHashAlgorithm::Sha256.partial_hash(&data, 300);
+        let (expected_state, expected_remaining) = generate_partial_sha256(&data, 300);
+        assert_eq!(
+            state,
+            expected_state
+                .into_iter()
+                .map(u64::from)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(remaining, expected_remaining);
+    }
+
+    #[test]
+    fn test_partial_sha256_matches_sha2_at_every_cut_off() {
+        let data: Vec<u8> = (0..300u32).map(|i| (i * 13 % 256) as u8).collect();
+        let digest = Sha256::digest(&data).to_vec();
+
+        for hash_until_index in 0..=data.len() {
+            let partial = PartialSha256::new(&data, hash_until_index).unwrap();
+            assert_eq!(partial.hashed_len % PartialSha256::BLOCK_SIZE, 0);
+            assert!(partial.hashed_len <= hash_until_index);
+            assert!(hash_until_index < partial.hashed_len + PartialSha256::BLOCK_SIZE);
+            assert_eq!(partial.remaining, data[partial.hashed_len..]);
+            assert_eq!(partial.finalize().to_vec(), digest);
+
+            let (state, remaining) = generate_partial_sha256(&data, hash_until_index);
+            assert_eq!(state, partial.state);
+            assert_eq!(remaining, partial.remaining);
+        }
+
+        assert!(PartialSha256::new(&data, data.len() + 1).is_err());
+    }
+
+    /// Port of the payload decoding of `noir-jwt`: the base64 payload starting at
+    /// `base64_decode_offset` in the partial data.
+    fn circuit_decoded_payload(inputs: &JWTCircuitInputs) -> Vec<u8> {
+        let partial_data = inputs.partial_data.as_ref().unwrap();
+        URL_SAFE_NO_PAD
+            .decode(&partial_data.storage[inputs.base64_decode_offset..partial_data.len])
+            .unwrap()
+    }
+
+    fn random_jwt_payload() -> impl Strategy<Value = String> {
+        (
+            "[A-Za-z0-9_-]{0,120}",
+            "[a-z0-9.]{1,24}",
+            "[a-z]{1,12}\\.(com|dev|org)",
+            "[0-9]{1,77}",
+            1_000_000_000u64..2_000_000_000,
+        )
+            .prop_map(|(azp, local, domain, nonce, iat)| {
+                format!(
+                    r#"{{"iss":"https://accounts.google.com","azp":"{}","aud":"client","sub":"1","email":"{}@{}","email_verified":true,"nonce":"{}","iat":{},"exp":{}}}"#,
+                    azp,
+                    local,
+                    domain,
+                    nonce,
+                    iat,
+                    iat + 3600
+                )
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_partial_sha256_matches_sha2(
+            data in proptest::collection::vec(any::<u8>(), 0..400),
+            cut_off in any::<prop::sample::Index>(),
+        ) {
+            let hash_until_index = cut_off.index(data.len() + 1);
+            let partial = PartialSha256::new(&data, hash_until_index).unwrap();
+            prop_assert_eq!(partial.finalize().to_vec(), Sha256::digest(&data).to_vec());
+        }
+
+        #[test]
+        fn prop_partial_inputs_satisfy_circuit(
+            payload in random_jwt_payload(),
+            kid in "[0-9a-f]{0,40}",
+        ) {
+            let header = format!(r#"{{"alg":"RS256","kid":"{}","typ":"JWT"}}"#, kid);
+            let jwt = format!(
+                "{}.{}.{}",
+                URL_SAFE_NO_PAD.encode(&header),
+                URL_SAFE_NO_PAD.encode(&payload),
+                URL_SAFE_NO_PAD.encode([7u8; 256]),
+            );
+            let signed_data = &jwt.as_bytes()[..jwt.rfind('.').unwrap()];
+            let payload_start = jwt.find('.').unwrap() + 1;
+
+            for keys in [
+                EMAIL_DOMAIN_CLAIM_KEYS.to_vec(),
+                MembershipType::EmailDomain.sha_precompute_keys(),
+            ] {
+                let inputs = generate_inputs(&jwt, &sample_jwk(), Some(keys.clone()), 2048).unwrap();
+                let partial_data = inputs.partial_data.as_ref().unwrap();
+                let sha_cutoff = inputs.full_data_length.unwrap() - partial_data.len;
+
+                // The precompute stops at a block boundary, and the circuit finishes the hash
+                let partial = PartialSha256 {
+                    state: inputs
+                        .partial_hash
+                        .as_ref()
+                        .unwrap()
+                        .iter()
+                        .map(|word| *word as u32)
+                        .collect::<Vec<_>>()
+                        .try_into()
+                        .unwrap(),
+                    remaining: partial_data.storage[..partial_data.len].to_vec(),
+                    hashed_len: sha_cutoff,
+                };
+                prop_assert_eq!(sha_cutoff % PartialSha256::BLOCK_SIZE, 0);
+                prop_assert_eq!(partial.finalize().to_vec(), Sha256::digest(signed_data).to_vec());
+
+                // The offset lands on the first base64 group of the payload after the cut-off
+                let decode_start = sha_cutoff + inputs.base64_decode_offset;
+                prop_assert!(decode_start >= payload_start);
+                prop_assert_eq!((decode_start - payload_start) % 4, 0);
+                if sha_cutoff >= payload_start {
+                    prop_assert!(inputs.base64_decode_offset < 4);
+                }
+
+                // Every claim the circuit reads is decoded whole
+                let decoded = String::from_utf8_lossy(&circuit_decoded_payload(&inputs)).to_string();
+                prop_assert!(payload.ends_with(&decoded));
+                for key in keys {
+                    let claim = format!("\"{}\":", key);
+                    prop_assert!(decoded.contains(&claim), "{} not decoded from {}", claim, decoded);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rsa_jwt_key_selects_circuit() {
+        let modulus = |bits: u64| (BigUint::from(1u8) << (bits - 1)) + 1u8;
+
+        let key = RsaJwtKey::from_jwk(&sample_jwk()).unwrap();
+        assert_eq!(key.limbs(), 18);
+        assert_eq!(key.circuit_name(), "verified_anonymous_sns_jwt");
+
+        let key = RsaJwtKey::new("RS384", &modulus(3072)).unwrap();
+        assert_eq!(key.limbs(), 26);
+        assert_eq!(key.circuit_name(), "verified_anonymous_sns_jwt_rs384_3072");
+
+        let key = RsaJwtKey::new("RS512", &modulus(4096)).unwrap();
+        assert_eq!(key.limbs(), 35);
+        assert_eq!(key.circuit_name(), "verified_anonymous_sns_jwt_rs512_4096");
+
+        assert!(RsaJwtKey::new("ES256", &modulus(2048)).is_err());
+        assert!(RsaJwtKey::new("RS256", &modulus(1024)).is_err());
+    }
+
+    #[test]
+    fn test_generate_inputs_with_rs512_4096_key() {
+        let payload = r#"{"iss":"https://accounts.google.com","aud":"client","sub":"1","email":"alice@pse.dev","email_verified":true,"nonce":"1","iat":1746004080}"#;
+        let header = format!(r#"{{"alg":"RS512","kid":"{}","typ":"JWT"}}"#, SAMPLE_KID);
+        let jwt = format!(
+            "{}.{}.{}",
+            URL_SAFE_NO_PAD.encode(header),
+            URL_SAFE_NO_PAD.encode(payload),
+            URL_SAFE_NO_PAD.encode([7u8; 512]),
+        );
+        let jwk = JsonWebKey {
+            n: URL_SAFE_NO_PAD.encode([0xffu8; 512]),
+            alg: "RS512".to_string(),
+            ..sample_jwk()
+        };
+
+        let inputs = generate_inputs(
+            &jwt,
+            &jwk,
+            Some(MembershipType::EmailDomain.sha_precompute_keys()),
+            MAX_JWT_PARTIAL_DATA_LENGTH,
+        )
+        .unwrap();
+        assert_eq!(inputs.pubkey_modulus_limbs.len(), 35);
+        assert_eq!(inputs.redc_params_limbs.len(), 35);
+        assert_eq!(inputs.signature_limbs.len(), 35);
+        assert_eq!(inputs.partial_hash.unwrap().len(), 8);
+        let precomputed = inputs.full_data_length.unwrap() - inputs.partial_data.unwrap().len;
+        assert_eq!(precomputed % 128, 0);
+
+        // A key whose `alg` does not match its circuit variants is rejected
+        let jwk = JsonWebKey {
+            alg: "PS512".to_string(),
+            ..jwk
+        };
+        assert!(generate_inputs(&jwt, &jwk, None, 2048).is_err());
+    }
 
     #[tokio::test]
     async fn test_verify_jwt_from_database() -> Result<(), anyhow::Error> {
@@ -629,14 +1738,26 @@ mod tests {
         //   });
 
         let srs_path = "public/jwt-srs.local".to_string();
-        let verified = verify_jwt_proof(
-            srs_path,
-            proof,
+        // OAuth client id of the app the messages were posted from
+        let client_id =
+            "1006701293748-1irmgu910p2n7vjmoa441ramm6dcrveb.apps.googleusercontent.com".to_string();
+        let public_inputs = JwtPublicInputs {
             domain,
             google_jwt_pubkey_modulus,
             ephemeral_pubkey,
             ephemeral_pubkey_expiry,
-        );
+            iss: GOOGLE_ISSUERS[0].to_string(),
+            aud: client_id.clone(),
+            iat: message.proofArgs.jwtIat,
+        };
+        let verified = verify_jwt_proof(
+            srs_path,
+            proof,
+            &public_inputs,
+            &[client_id],
+            &EphemeralExpiryPolicy::default(),
+            Utc::now(),
+        )?;
         println!("verified: {}", verified);
         Ok(())
         // assert!(result);
@@ -0,0 +1,519 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use super::ephemeral_key::EphemeralKey;
+
+/// Version of the encrypted key envelope. Bump when the layout or primitives change.
+pub const KEY_STORE_VERSION: u32 = 1;
+
+const KDF_ALGORITHM: &str = "argon2id";
+const CIPHER: &str = "chacha20poly1305";
+const KDF_SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// Largest Argon2 cost an envelope may ask for, so a crafted envelope cannot make
+/// `decrypt` exhaust the memory or time of the device.
+const MAX_KDF_MEMORY_KIB: u32 = 256 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 16;
+const MAX_KDF_PARALLELISM: u32 = 8;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct KdfParams {
+    pub algorithm: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    pub salt: String,
+}
+
+impl KdfParams {
+    fn generate() -> Self {
+        let mut salt = [0u8; KDF_SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+
+        KdfParams {
+            algorithm: KDF_ALGORITHM.to_string(),
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+            salt: BASE64_STANDARD.encode(salt),
+        }
+    }
+
+    fn derive_key(&self, passphrase: &str) -> Result<Zeroizing<[u8; 32]>> {
+        if self.algorithm != KDF_ALGORITHM {
+            bail!("Unsupported key derivation function: {}", self.algorithm);
+        }
+        if self.memory_kib > MAX_KDF_MEMORY_KIB
+            || self.iterations > MAX_KDF_ITERATIONS
+            || self.parallelism > MAX_KDF_PARALLELISM
+        {
+            bail!(
+                "Argon2 params m={} t={} p={} exceed the limits m={} t={} p={}",
+                self.memory_kib,
+                self.iterations,
+                self.parallelism,
+                MAX_KDF_MEMORY_KIB,
+                MAX_KDF_ITERATIONS,
+                MAX_KDF_PARALLELISM
+            );
+        }
+
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| anyhow!("Invalid argon2 params: {}", e))?;
+        let salt = BASE64_STANDARD.decode(&self.salt)?;
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+        Ok(key)
+    }
+}
+
+/// Plaintext contents of an envelope. Only ever held in memory.
+#[derive(Serialize, Deserialize)]
+struct StoredEphemeralKey {
+    private_key: String,
+    salt: String,
+    expiry: String,
+}
+
+/// Versioned, passphrase-encrypted ephemeral key, used both at rest and for import/export.
+/// The public key is kept in clear so a store can be listed without the passphrase.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EncryptedEphemeralKey {
+    pub version: u32,
+    pub kdf: KdfParams,
+    pub cipher: String,
+    pub nonce: String,
+    pub ciphertext: String,
+    pub public_key: String,
+}
+
+impl EncryptedEphemeralKey {
+    pub fn encrypt(key: &EphemeralKey, passphrase: &str) -> Result<Self> {
+        let kdf = KdfParams::generate();
+        let derived_key = kdf.derive_key(passphrase)?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        let plaintext = Zeroizing::new(serde_json::to_vec(&StoredEphemeralKey {
+            private_key: key.get_ephemeral_private_key(),
+            salt: key.get_ephemeral_salt(),
+            expiry: key.get_ephemeral_expiry(),
+        })?);
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(derived_key.as_ref()));
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| anyhow!("Failed to encrypt ephemeral key"))?;
+
+        Ok(EncryptedEphemeralKey {
+            version: KEY_STORE_VERSION,
+            kdf,
+            cipher: CIPHER.to_string(),
+            nonce: BASE64_STANDARD.encode(nonce),
+            ciphertext: BASE64_STANDARD.encode(ciphertext),
+            public_key: key.get_ephemeral_public_key(),
+        })
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<EphemeralKey> {
+        if self.version != KEY_STORE_VERSION {
+            bail!("Unsupported key store version: {}", self.version);
+        }
+        if self.cipher != CIPHER {
+            bail!("Unsupported cipher: {}", self.cipher);
+        }
+
+        let derived_key = self.kdf.derive_key(passphrase)?;
+        let nonce = BASE64_STANDARD.decode(&self.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            bail!("Invalid nonce length: {}", nonce.len());
+        }
+        let ciphertext = BASE64_STANDARD.decode(&self.ciphertext)?;
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(derived_key.as_ref()));
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+                .map_err(|_| anyhow!("Failed to decrypt ephemeral key: wrong passphrase?"))?,
+        );
+        let stored: StoredEphemeralKey = serde_json::from_slice(&plaintext)?;
+        let stored_private_key = Zeroizing::new(stored.private_key);
+
+        let key = EphemeralKey::from_private_key(&stored_private_key, &stored.salt, &stored.expiry)
+            .ok_or_else(|| anyhow!("Stored ephemeral key is invalid"))?;
+        if key.get_ephemeral_public_key() != self.public_key {
+            bail!("Stored ephemeral key does not match its public key");
+        }
+        Ok(key)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(data: &str) -> Result<Self> {
+        Ok(serde_json::from_str(data)?)
+    }
+}
+
+pub trait KeyStore {
+    fn save(&self, key_id: &str, key: &EphemeralKey) -> Result<()>;
+    fn load(&self, key_id: &str) -> Result<EphemeralKey>;
+    fn delete(&self, key_id: &str) -> Result<()>;
+    fn contains(&self, key_id: &str) -> Result<bool>;
+
+    /// Exports a key re-encrypted under `export_passphrase`, e.g. for a backup or a new device.
+    fn export_key(&self, key_id: &str, export_passphrase: &str) -> Result<String> {
+        let key = self.load(key_id)?;
+        EncryptedEphemeralKey::encrypt(&key, export_passphrase)?.to_json()
+    }
+
+    fn import_key(
+        &self,
+        key_id: &str,
+        exported: &str,
+        export_passphrase: &str,
+    ) -> Result<EphemeralKey> {
+        let key = EncryptedEphemeralKey::from_json(exported)?.decrypt(export_passphrase)?;
+        self.save(key_id, &key)?;
+        Ok(key)
+    }
+}
+
+fn validate_key_id(key_id: &str) -> Result<()> {
+    let valid = !key_id.is_empty()
+        && key_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!("Invalid key id: {}", key_id);
+    }
+    Ok(())
+}
+
+/// Writes `data` to a new file readable by the owner only and renames it over `path`, so a
+/// failed save leaves the previous key in place and no other user can read the envelope.
+fn write_private_file(path: &Path, data: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid key path: {}", path.display()))?
+        .to_string_lossy();
+    let tmp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4()));
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let result = options
+        .open(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    Ok(result?)
+}
+
+/// Stores each key as a passphrase-encrypted envelope in `{dir}/{key_id}.key.json`.
+pub struct EncryptedFileKeyStore {
+    dir: PathBuf,
+    passphrase: Zeroizing<String>,
+}
+
+impl EncryptedFileKeyStore {
+    pub fn new(dir: impl Into<PathBuf>, passphrase: &str) -> Self {
+        EncryptedFileKeyStore {
+            dir: dir.into(),
+            passphrase: Zeroizing::new(passphrase.to_string()),
+        }
+    }
+
+    fn key_path(&self, key_id: &str) -> Result<PathBuf> {
+        validate_key_id(key_id)?;
+        Ok(self.dir.join(format!("{}.key.json", key_id)))
+    }
+}
+
+impl KeyStore for EncryptedFileKeyStore {
+    fn save(&self, key_id: &str, key: &EphemeralKey) -> Result<()> {
+        let path = self.key_path(key_id)?;
+        fs::create_dir_all(&self.dir)?;
+
+        let envelope = EncryptedEphemeralKey::encrypt(key, &self.passphrase)?;
+        write_private_file(&path, envelope.to_json()?.as_bytes())
+    }
+
+    fn load(&self, key_id: &str) -> Result<EphemeralKey> {
+        let path = self.key_path(key_id)?;
+        if !path.exists() {
+            bail!("Ephemeral key {} not found", key_id);
+        }
+        let data = fs::read_to_string(path)?;
+        EncryptedEphemeralKey::from_json(&data)?.decrypt(&self.passphrase)
+    }
+
+    fn delete(&self, key_id: &str) -> Result<()> {
+        let path = self.key_path(key_id)?;
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn contains(&self, key_id: &str) -> Result<bool> {
+        Ok(self.key_path(key_id)?.exists())
+    }
+}
+
+/// Hook for platform secure storage (iOS Keychain, Android Keystore), implemented in Swift/Kotlin.
+/// Only encrypted envelopes cross the FFI boundary; the private key stays in Rust.
+#[uniffi::export(with_foreign)]
+pub trait PlatformKeyStore: Send + Sync {
+    fn store(&self, key_id: String, data: Vec<u8>) -> bool;
+    fn load(&self, key_id: String) -> Option<Vec<u8>>;
+    fn delete(&self, key_id: String) -> bool;
+}
+
+/// `KeyStore` backed by a `PlatformKeyStore` implementation.
+pub struct PlatformBackedKeyStore {
+    platform: Arc<dyn PlatformKeyStore>,
+    passphrase: Zeroizing<String>,
+}
+
+impl PlatformBackedKeyStore {
+    pub fn new(platform: Arc<dyn PlatformKeyStore>, passphrase: &str) -> Self {
+        PlatformBackedKeyStore {
+            platform,
+            passphrase: Zeroizing::new(passphrase.to_string()),
+        }
+    }
+}
+
+impl KeyStore for PlatformBackedKeyStore {
+    fn save(&self, key_id: &str, key: &EphemeralKey) -> Result<()> {
+        validate_key_id(key_id)?;
+        let envelope = EncryptedEphemeralKey::encrypt(key, &self.passphrase)?;
+        if !self
+            .platform
+            .store(key_id.to_string(), envelope.to_json()?.into_bytes())
+        {
+            bail!("Platform key store rejected key {}", key_id);
+        }
+        Ok(())
+    }
+
+    fn load(&self, key_id: &str) -> Result<EphemeralKey> {
+        validate_key_id(key_id)?;
+        let data = self
+            .platform
+            .load(key_id.to_string())
+            .ok_or_else(|| anyhow!("Ephemeral key {} not found", key_id))?;
+        EncryptedEphemeralKey::from_json(&String::from_utf8(data)?)?.decrypt(&self.passphrase)
+    }
+
+    fn delete(&self, key_id: &str) -> Result<()> {
+        validate_key_id(key_id)?;
+        if !self.platform.delete(key_id.to_string()) {
+            bail!("Platform key store failed to delete key {}", key_id);
+        }
+        Ok(())
+    }
+
+    fn contains(&self, key_id: &str) -> Result<bool> {
+        validate_key_id(key_id)?;
+        Ok(self.platform.load(key_id.to_string()).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashMap, sync::Mutex};
+
+    fn test_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("key-store-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[derive(Default)]
+    struct InMemoryPlatformKeyStore {
+        entries: Mutex<HashMap<String, Vec<u8>>>,
+    }
+
+    impl PlatformKeyStore for InMemoryPlatformKeyStore {
+        fn store(&self, key_id: String, data: Vec<u8>) -> bool {
+            self.entries.lock().unwrap().insert(key_id, data);
+            true
+        }
+
+        fn load(&self, key_id: String) -> Option<Vec<u8>> {
+            self.entries.lock().unwrap().get(&key_id).cloned()
+        }
+
+        fn delete(&self, key_id: String) -> bool {
+            self.entries.lock().unwrap().remove(&key_id).is_some()
+        }
+    }
+
+    #[test]
+    fn test_encrypted_file_key_store_roundtrip() {
+        let dir = test_dir();
+        let store = EncryptedFileKeyStore::new(&dir, "correct horse battery staple");
+        let key = EphemeralKey::generate_ephemeral_key().unwrap();
+
+        store.save("default", &key).unwrap();
+        assert!(store.contains("default").unwrap());
+
+        // The private key is not stored in clear
+        let data = fs::read_to_string(dir.join("default.key.json")).unwrap();
+        assert!(!data.contains(&key.get_ephemeral_private_key()));
+
+        let loaded = store.load("default").unwrap();
+        assert_eq!(
+            loaded.get_ephemeral_private_key(),
+            key.get_ephemeral_private_key()
+        );
+        assert_eq!(
+            loaded.get_ephemeral_pubkey_hash(),
+            key.get_ephemeral_pubkey_hash()
+        );
+
+        let wrong = EncryptedFileKeyStore::new(&dir, "wrong passphrase");
+        assert!(wrong.load("default").is_err());
+
+        store.delete("default").unwrap();
+        assert!(!store.contains("default").unwrap());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_encrypted_file_key_store_replaces_the_key_privately() {
+        let dir = test_dir();
+        let store = EncryptedFileKeyStore::new(&dir, "passphrase");
+        store
+            .save("default", &EphemeralKey::generate_ephemeral_key().unwrap())
+            .unwrap();
+        let key = EphemeralKey::generate_ephemeral_key().unwrap();
+        store.save("default", &key).unwrap();
+
+        assert_eq!(
+            store.load("default").unwrap().get_ephemeral_public_key(),
+            key.get_ephemeral_public_key()
+        );
+        // Only the key file is left in the directory
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = fs::metadata(dir.join("default.key.json")).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        }
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_key_store_rejects_path_traversal() {
+        let store = EncryptedFileKeyStore::new(test_dir(), "passphrase");
+        let key = EphemeralKey::generate_ephemeral_key().unwrap();
+        assert!(store.save("../outside", &key).is_err());
+    }
+
+    #[test]
+    fn test_export_import() {
+        let dir = test_dir();
+        let source = EncryptedFileKeyStore::new(dir.join("source"), "device passphrase");
+        let target = PlatformBackedKeyStore::new(
+            Arc::new(InMemoryPlatformKeyStore::default()),
+            "other device passphrase",
+        );
+        let key = EphemeralKey::generate_ephemeral_key().unwrap();
+        source.save("default", &key).unwrap();
+
+        let exported = source.export_key("default", "backup passphrase").unwrap();
+        let envelope = EncryptedEphemeralKey::from_json(&exported).unwrap();
+        assert_eq!(envelope.version, KEY_STORE_VERSION);
+        assert_eq!(envelope.public_key, key.get_ephemeral_public_key());
+
+        assert!(target
+            .import_key("default", &exported, "wrong passphrase")
+            .is_err());
+        let imported = target
+            .import_key("default", &exported, "backup passphrase")
+            .unwrap();
+        assert_eq!(
+            imported.get_ephemeral_private_key(),
+            key.get_ephemeral_private_key()
+        );
+        assert_eq!(
+            target.load("default").unwrap().get_ephemeral_public_key(),
+            key.get_ephemeral_public_key()
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_rejects_excessive_kdf_params() {
+        let key = EphemeralKey::generate_ephemeral_key().unwrap();
+        let envelope = EncryptedEphemeralKey::encrypt(&key, "passphrase").unwrap();
+
+        for (memory_kib, iterations, parallelism) in [
+            (
+                MAX_KDF_MEMORY_KIB + 1,
+                Params::DEFAULT_T_COST,
+                Params::DEFAULT_P_COST,
+            ),
+            (
+                Params::DEFAULT_M_COST,
+                MAX_KDF_ITERATIONS + 1,
+                Params::DEFAULT_P_COST,
+            ),
+            (
+                Params::DEFAULT_M_COST,
+                Params::DEFAULT_T_COST,
+                MAX_KDF_PARALLELISM + 1,
+            ),
+            (u32::MAX, u32::MAX, u32::MAX),
+        ] {
+            let mut crafted = envelope.clone();
+            crafted.kdf.memory_kib = memory_kib;
+            crafted.kdf.iterations = iterations;
+            crafted.kdf.parallelism = parallelism;
+            let err = crafted.decrypt("passphrase").unwrap_err();
+            assert!(err.to_string().contains("exceed the limits"));
+        }
+        assert!(envelope.decrypt("passphrase").is_ok());
+    }
+
+    #[test]
+    fn test_rejects_unknown_version() {
+        let key = EphemeralKey::generate_ephemeral_key().unwrap();
+        let mut envelope = EncryptedEphemeralKey::encrypt(&key, "passphrase").unwrap();
+        envelope.version = KEY_STORE_VERSION + 1;
+        assert!(envelope.decrypt("passphrase").is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::VecDeque;
+
+use acir::{acir_field::FieldElement, AcirField};
+use anyhow::{bail, Result};
+
+use super::poseidon2::Poseidon2;
+
+/// Depth of the group member trees, fixed by the membership circuit (2^16 members per group).
+pub const MERKLE_TREE_DEPTH: usize = 16;
+
+/// Number of past roots that are still accepted, so a proof built against a slightly
+/// outdated root stays valid while new members join.
+pub const ROOT_HISTORY_SIZE: usize = 30;
+
+/// Hash of two sibling nodes, `Poseidon2::hash([left, right], 2)` in Noir.
+pub fn hash_nodes(left: FieldElement, right: FieldElement) -> FieldElement {
+    Poseidon2::hash(&[left, right], false)
+}
+
+/// Inclusion path of a leaf, ordered from the leaf level up to the root.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    pub siblings: Vec<FieldElement>,
+    pub root: FieldElement,
+}
+
+impl MerkleProof {
+    /// Bit `i` is set when the node at level `i` is a right child.
+    pub fn path_indices(&self) -> Vec<bool> {
+        (0..self.siblings.len())
+            .map(|level| (self.leaf_index >> level) & 1 == 1)
+            .collect()
+    }
+
+    pub fn compute_root(&self, leaf: FieldElement) -> FieldElement {
+        self.siblings
+            .iter()
+            .zip(self.path_indices())
+            .fold(leaf, |node, (sibling, is_right)| {
+                if is_right {
+                    hash_nodes(*sibling, node)
+                } else {
+                    hash_nodes(node, *sibling)
+                }
+            })
+    }
+
+    pub fn verify(&self, leaf: FieldElement) -> bool {
+        self.compute_root(leaf) == self.root
+    }
+}
+
+/// Append-only Poseidon2 Merkle tree. Empty leaves are zero and empty subtrees hash
+/// to the precomputed `zeros` of their level; removed members are zeroed in place.
+#[derive(Clone, Debug)]
+pub struct IncrementalMerkleTree {
+    depth: usize,
+    zeros: Vec<FieldElement>,
+    /// `levels[0]` holds the leaves, `levels[i]` the non-empty nodes at height `i`.
+    levels: Vec<Vec<FieldElement>>,
+    root_history: VecDeque<FieldElement>,
+}
+
+impl IncrementalMerkleTree {
+    pub fn new(depth: usize) -> Self {
+        let mut zeros = vec![FieldElement::zero()];
+        for level in 0..depth {
+            zeros.push(hash_nodes(zeros[level], zeros[level]));
+        }
+
+        let mut root_history = VecDeque::with_capacity(ROOT_HISTORY_SIZE);
+        root_history.push_back(zeros[depth]);
+
+        IncrementalMerkleTree {
+            depth,
+            zeros,
+            levels: vec![vec![]; depth],
+            root_history,
+        }
+    }
+
+    /// Rebuilds a tree from its leaves in insertion order; zero leaves are removed members.
+    pub fn from_leaves(depth: usize, leaves: &[FieldElement]) -> Result<Self> {
+        let mut tree = Self::new(depth);
+        for leaf in leaves {
+            if leaf == &FieldElement::zero() {
+                tree.append(*leaf)?;
+            } else {
+                tree.insert(*leaf)?;
+            }
+        }
+        Ok(tree)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.first().map_or(0, |leaves| leaves.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn leaves(&self) -> &[FieldElement] {
+        self.levels.first().map_or(&[], |leaves| leaves.as_slice())
+    }
+
+    pub fn root(&self) -> FieldElement {
+        *self.root_history.back().unwrap()
+    }
+
+    /// Recent roots, oldest first.
+    pub fn root_history(&self) -> impl Iterator<Item = &FieldElement> {
+        self.root_history.iter()
+    }
+
+    pub fn is_known_root(&self, root: FieldElement) -> bool {
+        self.root_history.contains(&root)
+    }
+
+    pub fn index_of(&self, leaf: FieldElement) -> Option<usize> {
+        self.leaves().iter().position(|l| *l == leaf)
+    }
+
+    /// Appends a leaf and returns its index.
+    pub fn insert(&mut self, leaf: FieldElement) -> Result<usize> {
+        if leaf == FieldElement::zero() {
+            bail!("Merkle tree leaf must not be zero");
+        }
+        self.append(leaf)
+    }
+
+    /// Zeroes the leaf at `index`. Older roots still contain it, so the root history is
+    /// reset to the new root.
+    pub fn remove(&mut self, index: usize) -> Result<()> {
+        if index >= self.len() {
+            bail!("Merkle tree has no leaf at index {}", index);
+        }
+
+        let root = self.update(index, FieldElement::zero());
+        self.root_history.clear();
+        self.root_history.push_back(root);
+        Ok(())
+    }
+
+    fn append(&mut self, leaf: FieldElement) -> Result<usize> {
+        let index = self.len();
+        if index >= 1usize << self.depth {
+            bail!("Merkle tree is full");
+        }
+
+        let root = self.update(index, leaf);
+        if self.root_history.len() == ROOT_HISTORY_SIZE {
+            self.root_history.pop_front();
+        }
+        self.root_history.push_back(root);
+
+        Ok(index)
+    }
+
+    /// Sets the leaf at `index`, which is at most `len()`, and returns the new root.
+    fn update(&mut self, index: usize, leaf: FieldElement) -> FieldElement {
+        let mut node = leaf;
+        let mut position = index;
+        for level in 0..self.depth {
+            let nodes = &mut self.levels[level];
+            if position == nodes.len() {
+                nodes.push(node);
+            } else {
+                nodes[position] = node;
+            }
+
+            node = if position % 2 == 0 {
+                hash_nodes(
+                    node,
+                    nodes
+                        .get(position + 1)
+                        .copied()
+                        .unwrap_or(self.zeros[level]),
+                )
+            } else {
+                hash_nodes(nodes[position - 1], node)
+            };
+            position /= 2;
+        }
+        node
+    }
+
+    /// Inclusion path of the leaf at `leaf_index` against the current root.
+    pub fn proof(&self, leaf_index: usize) -> Option<MerkleProof> {
+        if leaf_index >= self.len() {
+            return None;
+        }
+
+        let siblings = (0..self.depth)
+            .map(|level| {
+                let sibling = (leaf_index >> level) ^ 1;
+                self.levels[level]
+                    .get(sibling)
+                    .copied()
+                    .unwrap_or(self.zeros[level])
+            })
+            .collect();
+
+        Some(MerkleProof {
+            leaf_index,
+            siblings,
+            root: self.root(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(value: u64) -> FieldElement {
+        FieldElement::from(value)
+    }
+
+    /// Root computed level by level over the full, zero-padded leaf array.
+    fn naive_root(depth: usize, leaves: &[FieldElement]) -> FieldElement {
+        let mut nodes = leaves.to_vec();
+        nodes.resize(1 << depth, FieldElement::zero());
+        while nodes.len() > 1 {
+            nodes = nodes
+                .chunks(2)
+                .map(|pair| hash_nodes(pair[0], pair[1]))
+                .collect();
+        }
+        nodes[0]
+    }
+
+    #[test]
+    fn test_insert_matches_full_tree() {
+        let mut tree = IncrementalMerkleTree::new(4);
+        assert_eq!(tree.root(), naive_root(4, &[]));
+
+        let mut leaves = vec![];
+        for i in 1..=16 {
+            assert_eq!(tree.insert(leaf(i)).unwrap(), leaves.len());
+            leaves.push(leaf(i));
+            assert_eq!(tree.root(), naive_root(4, &leaves));
+        }
+
+        assert!(tree.insert(leaf(17)).is_err());
+        assert!(IncrementalMerkleTree::new(4).insert(leaf(0)).is_err());
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        let leaves: Vec<_> = (1..=11).map(leaf).collect();
+        let tree = IncrementalMerkleTree::from_leaves(MERKLE_TREE_DEPTH, &leaves).unwrap();
+
+        for (index, value) in leaves.iter().enumerate() {
+            let proof = tree.proof(index).unwrap();
+            assert_eq!(proof.siblings.len(), MERKLE_TREE_DEPTH);
+            assert!(proof.verify(*value));
+            assert!(!proof.verify(leaf(100)));
+        }
+        assert!(tree.proof(leaves.len()).is_none());
+        assert_eq!(tree.index_of(leaf(5)), Some(4));
+    }
+
+    #[test]
+    fn test_root_history() {
+        let mut tree = IncrementalMerkleTree::new(8);
+        let empty_root = tree.root();
+
+        tree.insert(leaf(1)).unwrap();
+        let proof = tree.proof(0).unwrap();
+        for i in 2..=ROOT_HISTORY_SIZE as u64 {
+            tree.insert(leaf(i)).unwrap();
+        }
+
+        // A proof against an older root is still accepted until it leaves the history
+        assert!(tree.is_known_root(proof.root));
+        assert!(!tree.is_known_root(empty_root));
+        assert_eq!(tree.root_history().count(), ROOT_HISTORY_SIZE);
+
+        tree.insert(leaf(100)).unwrap();
+        assert!(!tree.is_known_root(proof.root));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut leaves: Vec<_> = (1..=6).map(leaf).collect();
+        let mut tree = IncrementalMerkleTree::from_leaves(4, &leaves).unwrap();
+        let before = tree.proof(3).unwrap();
+
+        tree.remove(2).unwrap();
+        leaves[2] = FieldElement::zero();
+        assert_eq!(tree.root(), naive_root(4, &leaves));
+        assert!(!tree.is_known_root(before.root));
+        assert_eq!(tree.root_history().count(), 1);
+        assert!(tree.proof(3).unwrap().verify(leaf(4)));
+        assert!(tree.remove(6).is_err());
+
+        // Removed leaves keep their slot when the tree is rebuilt
+        let rebuilt = IncrementalMerkleTree::from_leaves(4, tree.leaves()).unwrap();
+        assert_eq!(rebuilt.root(), tree.root());
+        tree.insert(leaf(7)).unwrap();
+        leaves.push(leaf(7));
+        assert_eq!(tree.root(), naive_root(4, &leaves));
+    }
+}
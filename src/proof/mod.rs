@@ -1,3 +1,15 @@
+pub mod anon_post;
+pub mod claim_disclosure;
+pub mod dkim;
+pub mod ephemeral_commitment;
 pub mod ephemeral_key;
+pub mod group_nullifier;
+pub mod jwt_es256;
 pub mod jwt_proof;
-pub mod poseidon2;
\ No newline at end of file
+pub mod key_store;
+pub mod merkle_tree;
+pub mod poseidon2;
+pub mod rate_limit;
+pub mod solidity;
+pub mod solidity_verifier;
+pub mod zkemail;
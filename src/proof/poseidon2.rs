@@ -8,11 +8,17 @@ use bn254_blackbox_solver::poseidon2_permutation;
 const RATE: usize = 3;
 const STATE_SIZE: usize = 4;
 
+/// Number of bytes packed into one field element by `pack_bytes`; 31 bytes always fit in BN254.
+pub const BYTES_PER_FIELD: usize = 31;
+
+/// Poseidon2 sponge over BN254 with the same parameters and padding as Noir's
+/// `std::hash::poseidon2::Poseidon2`.
 pub struct Poseidon2 {
     cache: [Fr; RATE],
     state: Vec<Fr>,
     cache_size: usize,
     squeeze_mode: bool,
+    squeeze_index: usize,
 }
 
 impl Poseidon2 {
@@ -25,52 +31,225 @@ impl Poseidon2 {
             state,
             cache_size: 0,
             squeeze_mode: false,
+            squeeze_index: 0,
         }
     }
 
+    /// Creates a sponge initialised for a message of `message_len` field elements, as Noir does.
+    pub fn with_message_len(message_len: usize) -> Self {
+        Self::new(Fr::from(message_len as u64) * Fr::from(1u128 << 64))
+    }
+
     fn perform_duplex(&mut self) {
         for i in 0..RATE {
             if i < self.cache_size {
                 self.state[i] += self.cache[i];
             }
         }
+        self.cache_size = 0;
 
-        // Replace with your actual Poseidon2 permutation function
-        self.state = poseidon2_permutation(&self.state, 4).unwrap();
+        self.state = poseidon2_permutation(&self.state, STATE_SIZE as u32).unwrap();
     }
 
-    fn absorb(&mut self, input: Fr) {
-        assert!(!self.squeeze_mode);
+    /// Absorbs one field element. Panics if the sponge is already squeezing.
+    pub fn absorb(&mut self, input: Fr) {
+        assert!(!self.squeeze_mode, "Poseidon2: absorb after squeeze");
 
         if self.cache_size == RATE {
             self.perform_duplex();
-            self.cache[0] = input;
-            self.cache_size = 1;
-        } else {
-            self.cache[self.cache_size] = input;
-            self.cache_size += 1;
         }
+        self.cache[self.cache_size] = input;
+        self.cache_size += 1;
     }
 
-    fn squeeze(&mut self) -> Fr {
-        assert!(!self.squeeze_mode);
-        self.perform_duplex();
-        self.squeeze_mode = true;
-        self.state[0]
+    pub fn absorb_all(&mut self, inputs: &[Fr]) {
+        for input in inputs {
+            self.absorb(*input);
+        }
     }
 
-    pub fn hash(input: &[Fr], is_variable_length: bool) -> Fr {
-        let iv = Fr::from(input.len() as u64) * Fr::from(1u128 << 64);
-        let mut sponge = Poseidon2::new(iv);
-
-        for i in 0..input.len() {
-            sponge.absorb(input[i]);
+    /// Squeezes one field element. The first output equals Noir's `Poseidon2::hash`;
+    /// further outputs read the rest of the rate and then permute again.
+    pub fn squeeze(&mut self) -> Fr {
+        if !self.squeeze_mode {
+            self.perform_duplex();
+            self.squeeze_mode = true;
+        } else if self.squeeze_index == RATE {
+            self.perform_duplex();
+            self.squeeze_index = 0;
         }
 
+        let output = self.state[self.squeeze_index];
+        self.squeeze_index += 1;
+        output
+    }
+
+    pub fn squeeze_many(&mut self, count: usize) -> Vec<Fr> {
+        (0..count).map(|_| self.squeeze()).collect()
+    }
+
+    /// Hashes `input`. With `is_variable_length` a `1` is absorbed after the message,
+    /// which is what Noir does when `message_size` is smaller than the array length.
+    pub fn hash(input: &[Fr], is_variable_length: bool) -> Fr {
+        let mut sponge = Poseidon2::with_message_len(input.len());
+        sponge.absorb_all(input);
+
         if is_variable_length {
             sponge.absorb(Fr::from(1u64));
         }
 
         sponge.squeeze()
     }
+
+    /// Mirrors Noir's `Poseidon2::hash(input, message_size)` for a fixed-size `input` array.
+    pub fn hash_with_message_size(input: &[Fr], message_size: usize) -> Fr {
+        assert!(message_size <= input.len());
+        Self::hash(&input[..message_size], message_size != input.len())
+    }
+
+    /// Hashes `input` prefixed with the tag of `domain`, so hashes computed for different
+    /// purposes (nullifiers, commitments, Merkle nodes...) never collide.
+    pub fn hash_with_domain(domain: &str, input: &[Fr]) -> Fr {
+        let mut sponge = Poseidon2::with_message_len(input.len() + 1);
+        sponge.absorb(domain_tag(domain));
+        sponge.absorb_all(input);
+        sponge.squeeze()
+    }
+
+    /// Hashes a byte string as its length followed by `pack_bytes(bytes)`.
+    pub fn hash_bytes(bytes: &[u8]) -> Fr {
+        let packed = pack_bytes(bytes);
+        let mut sponge = Poseidon2::with_message_len(packed.len() + 1);
+        sponge.absorb(Fr::from(bytes.len() as u64));
+        sponge.absorb_all(&packed);
+        sponge.squeeze()
+    }
+}
+
+/// Packs bytes into field elements of `BYTES_PER_FIELD` big-endian bytes each; the last
+/// element holds the remaining bytes.
+pub fn pack_bytes(bytes: &[u8]) -> Vec<Fr> {
+    bytes
+        .chunks(BYTES_PER_FIELD)
+        .map(Fr::from_be_bytes_reduce)
+        .collect()
+}
+
+/// Domain separation tag: the ASCII bytes of `domain` read as a big-endian integer.
+/// Panics if `domain` is longer than `BYTES_PER_FIELD` bytes.
+pub fn domain_tag(domain: &str) -> Fr {
+    assert!(
+        domain.len() <= BYTES_PER_FIELD,
+        "Poseidon2: domain tag longer than {} bytes",
+        BYTES_PER_FIELD
+    );
+    Fr::from_be_bytes_reduce(domain.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(values: &[u64]) -> Vec<Fr> {
+        values.iter().map(|v| Fr::from(*v)).collect()
+    }
+
+    fn hex(value: &str) -> Fr {
+        Fr::from_hex(value).unwrap()
+    }
+
+    // The same vectors are asserted against Noir's `std::hash::poseidon2` in
+    // `circuit/zkjwt_circuit/src/zkjwt/poseidon2_test.nr`.
+    #[test]
+    fn test_hash_vectors() {
+        assert_eq!(
+            Poseidon2::hash(&fields(&[1]), false),
+            hex("0x168758332d5b3e2d13be8048c8011b454590e06c44bce7f702f09103eef5a373")
+        );
+        assert_eq!(
+            Poseidon2::hash(&fields(&[1, 2]), false),
+            hex("0x038682aa1cb5ae4e0a3f13da432a95c77c5c111f6f030faf9cad641ce1ed7383")
+        );
+        assert_eq!(
+            Poseidon2::hash(&fields(&[1, 2, 3, 4]), false),
+            hex("0x130bf204a32cac1f0ace56c78b731aa3809f06df2731ebcf6b3464a15788b1b9")
+        );
+    }
+
+    #[test]
+    fn test_variable_length_hash() {
+        let expected = hex("0x05183cc69f95f56ec1bbd9eedd6f337448abba8ed4bc19799ae2c684fea26dfe");
+        assert_eq!(
+            Poseidon2::hash_with_message_size(&fields(&[1, 2, 0, 0]), 2),
+            expected
+        );
+        assert_eq!(Poseidon2::hash(&fields(&[1, 2]), true), expected);
+        assert_eq!(
+            Poseidon2::hash_with_message_size(&fields(&[1, 2, 3, 4]), 4),
+            Poseidon2::hash(&fields(&[1, 2, 3, 4]), false)
+        );
+    }
+
+    #[test]
+    fn test_sponge_absorb_squeeze() {
+        let mut sponge = Poseidon2::with_message_len(4);
+        sponge.absorb(Fr::from(1u64));
+        sponge.absorb_all(&fields(&[2, 3, 4]));
+
+        assert_eq!(
+            sponge.squeeze_many(5),
+            vec![
+                hex("0x130bf204a32cac1f0ace56c78b731aa3809f06df2731ebcf6b3464a15788b1b9"),
+                hex("0x1fa82028fe3bc75bc16ce5b172c61cca5c696301921d1772349a6263b309df78"),
+                hex("0x2b5a182a13cbc583129884417669af8bf498a2eee48d9c3d1184e1206a8211c8"),
+                hex("0x1f567c10045a3faf251ed183145074d136d30b7eec8100bb0ac13837c7d28645"),
+                hex("0x13fbd2b876bc6aa0e35ab463bd892c733221321bb6bf10b7acbbf442c08f056b"),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "absorb after squeeze")]
+    fn test_absorb_after_squeeze() {
+        let mut sponge = Poseidon2::with_message_len(1);
+        sponge.absorb(Fr::from(1u64));
+        sponge.squeeze();
+        sponge.absorb(Fr::from(2u64));
+    }
+
+    #[test]
+    fn test_hash_with_domain() {
+        assert_eq!(domain_tag("nullifier"), hex("0x6e756c6c6966696572"));
+
+        let input = fields(&[1, 2]);
+        let hash = Poseidon2::hash_with_domain("nullifier", &input);
+        assert_eq!(
+            hash,
+            hex("0x0ff0c1d9747608e9eb27cd213c69c71e7c05b0382c19f38296a5084510cca47f")
+        );
+        assert_ne!(hash, Poseidon2::hash_with_domain("commitment", &input));
+        assert_ne!(hash, Poseidon2::hash(&input, false));
+    }
+
+    #[test]
+    fn test_hash_bytes() {
+        let message = b"verified-anonymous-sns: hello, poseidon2 world!";
+        assert_eq!(
+            pack_bytes(message),
+            vec![
+                hex("0x76657269666965642d616e6f6e796d6f75732d736e733a2068656c6c6f2c20"),
+                hex("0x706f736569646f6e3220776f726c6421"),
+            ]
+        );
+        assert_eq!(
+            Poseidon2::hash_bytes(message),
+            hex("0x01218cfad5260015f6565e5676f7641a7a0fa5da90e4ca4e42c5eeaf47afae79")
+        );
+        assert_eq!(
+            Poseidon2::hash_bytes(b""),
+            hex("0x2710144414c3a5f2354f4c08d52ed655b9fe253b4bf12cb9ad3de693d9b1db11")
+        );
+        // Leading zero bytes are kept apart by the length prefix
+        assert_ne!(Poseidon2::hash_bytes(b"\0a"), Poseidon2::hash_bytes(b"a"));
+    }
 }
@@ -0,0 +1,146 @@
+use acir::{acir_field::FieldElement, AcirField};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+
+use super::poseidon2::Poseidon2;
+
+/// Domain of the per-epoch line slope `a1`.
+pub const RATE_LIMIT_DOMAIN: &str = "rate-limit";
+/// Domain of the rate limiting nullifier.
+pub const RATE_LIMIT_NULLIFIER_DOMAIN: &str = "rate-limit-nullifier";
+
+/// Epoch containing `timestamp`, counted in `epoch_length_secs` since the UNIX epoch.
+pub fn epoch_at(timestamp: DateTime<Utc>, epoch_length_secs: u64) -> u64 {
+    timestamp.timestamp().max(0) as u64 / epoch_length_secs.max(1)
+}
+
+/// RLN share of a post: `share_y = identity_secret + a1 * share_x` with
+/// `a1 = hash(identity_secret, epoch, message_id)`. Posts of a member in an epoch use
+/// distinct message ids below the rate limit; reusing one gives two points on the same
+/// line, which reveals the identity secret.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimitShare {
+    pub epoch: u64,
+    pub nullifier: FieldElement,
+    pub share_x: FieldElement,
+    pub share_y: FieldElement,
+}
+
+impl RateLimitShare {
+    pub fn new(
+        identity_secret: FieldElement,
+        epoch: u64,
+        message_id: u32,
+        rate_limit: u32,
+        share_x: FieldElement,
+    ) -> Result<Self> {
+        if message_id >= rate_limit {
+            bail!(
+                "Message id {} exceeds the rate limit of {} posts per epoch",
+                message_id,
+                rate_limit
+            );
+        }
+
+        let a1 = Poseidon2::hash_with_domain(
+            RATE_LIMIT_DOMAIN,
+            &[
+                identity_secret,
+                FieldElement::from(epoch),
+                FieldElement::from(message_id as u64),
+            ],
+        );
+        Ok(RateLimitShare {
+            epoch,
+            nullifier: Poseidon2::hash_with_domain(RATE_LIMIT_NULLIFIER_DOMAIN, &[a1]),
+            share_x,
+            share_y: identity_secret + a1 * share_x,
+        })
+    }
+}
+
+/// Recovers the identity secret from two shares with the same nullifier and different
+/// messages.
+pub fn recover_identity_secret(
+    first: &RateLimitShare,
+    second: &RateLimitShare,
+) -> Option<FieldElement> {
+    if first.epoch != second.epoch
+        || first.nullifier != second.nullifier
+        || first.share_x == second.share_x
+    {
+        return None;
+    }
+
+    let a1 = (second.share_y - first.share_y) / (second.share_x - first.share_x);
+    let identity_secret = first.share_y - a1 * first.share_x;
+    if identity_secret == FieldElement::zero() {
+        return None;
+    }
+    Some(identity_secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(value: &str) -> FieldElement {
+        FieldElement::from_hex(value).unwrap()
+    }
+
+    // Same vector as `test_anon_post` in `circuit/anon_post_circuit/src/main.nr`
+    #[test]
+    fn test_rate_limit_share_vector() {
+        let share = RateLimitShare::new(
+            FieldElement::from(12345u64),
+            1000,
+            0,
+            10,
+            FieldElement::from(7u64),
+        )
+        .unwrap();
+
+        assert_eq!(
+            share.nullifier,
+            hex("0x0cd9046d72bc559a44b748eda1d294b3d9798a55f3e5407f69e7de22af342761")
+        );
+        assert_eq!(
+            share.share_y,
+            hex("0x01a5554f883ce569b3245c1f2008e2da1aa017061078541ee1cba15bcc19651f")
+        );
+        assert!(RateLimitShare::new(
+            FieldElement::from(12345u64),
+            1000,
+            10,
+            10,
+            FieldElement::one()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_recover_identity_secret() {
+        let secret = FieldElement::from(987654321u64);
+        let first = RateLimitShare::new(secret, 5, 3, 10, FieldElement::from(111u64)).unwrap();
+        let second = RateLimitShare::new(secret, 5, 3, 10, FieldElement::from(222u64)).unwrap();
+        assert_eq!(recover_identity_secret(&first, &second), Some(secret));
+
+        // Distinct message ids or epochs are unlinkable and reveal nothing
+        let other_id = RateLimitShare::new(secret, 5, 4, 10, FieldElement::from(222u64)).unwrap();
+        let other_epoch =
+            RateLimitShare::new(secret, 6, 3, 10, FieldElement::from(222u64)).unwrap();
+        assert_ne!(first.nullifier, other_id.nullifier);
+        assert_ne!(first.nullifier, other_epoch.nullifier);
+        assert_eq!(recover_identity_secret(&first, &other_id), None);
+
+        // Resubmitting the same message is not a second share
+        assert_eq!(recover_identity_secret(&first, &first), None);
+    }
+
+    #[test]
+    fn test_epoch_at() {
+        let timestamp: DateTime<Utc> = "2025-05-01T09:07:57.379Z".parse().unwrap();
+        assert_eq!(epoch_at(timestamp, 3600), 1746090477 / 3600);
+        assert_eq!(epoch_at(timestamp, 0), 1746090477);
+    }
+}
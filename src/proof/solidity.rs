@@ -0,0 +1,280 @@
+use anyhow::{anyhow, bail, Result};
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::BigUint;
+use sha3::{Digest, Keccak256};
+
+use super::jwt_proof::{
+    claim_public_inputs, extract_proof, flatten_fields_as_array, modulus_public_inputs,
+    pubkey_modulus_from_jwk, reconstruct_honk_proof, JwtPublicInputs, RsaJwtKey, MAX_AUD_LENGTH,
+    MAX_ISS_LENGTH,
+};
+
+/// Signature of `verify` of the Honk verifiers generated by `bb write_solidity_verifier`.
+pub const HONK_VERIFY_SIGNATURE: &str = "verify(bytes,bytes32[])";
+/// Signature of `ZkJwtProofVerifier.verifyZkJwtProof`.
+pub const VERIFY_ZK_JWT_PROOF_SIGNATURE: &str = "verifyZkJwtProof(bytes,bytes32[])";
+
+/// Public inputs of the membership circuits after the JWT signing key: domain, ephemeral
+/// pubkey and expiry, `iss`, `aud`, `iat` and the returned nullifier.
+pub const JWT_CLAIM_PUBLIC_INPUTS: usize =
+    (64 + 1) + 1 + 1 + (MAX_ISS_LENGTH + 1) + (MAX_AUD_LENGTH + 1) + 1 + 1;
+/// Public inputs of the ES256 circuit: the `x` and `y` bytes of the signing key, then the claims.
+pub const ES256_PUBLIC_INPUTS: usize = 32 + 32 + JWT_CLAIM_PUBLIC_INPUTS;
+
+/// Number of public inputs of the RSA membership circuit of `key`.
+pub fn rsa_jwt_public_inputs(key: &RsaJwtKey) -> usize {
+    key.limbs() + JWT_CLAIM_PUBLIC_INPUTS
+}
+
+/// A Honk proof laid out as the arguments of a Solidity verifier: the proof without its
+/// public inputs, and the public inputs (return values last) as `bytes32` words.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolidityProof {
+    pub proof: Vec<u8>,
+    pub public_inputs: Vec<[u8; 32]>,
+}
+
+impl SolidityProof {
+    /// Splits a proof with public inputs, `[u32 BE total fields][public inputs][proof]`, as
+    /// returned by the provers of this crate.
+    pub fn from_honk_proof(proof: &[u8], public_inputs_count: usize) -> Result<Self> {
+        let public_inputs_len = public_inputs_count * 32;
+        if proof.len() < 4 + public_inputs_len {
+            bail!(
+                "Proof of {} bytes does not have {} public inputs",
+                proof.len(),
+                public_inputs_count
+            );
+        }
+        let total_fields = BigEndian::read_u32(&proof[..4]) as usize;
+        if 4 + total_fields * 32 != proof.len() {
+            bail!(
+                "Proof of {} bytes does not have the {} fields of its header",
+                proof.len(),
+                total_fields
+            );
+        }
+
+        Ok(SolidityProof {
+            proof: extract_proof(proof, public_inputs_len).to_vec(),
+            public_inputs: proof[4..4 + public_inputs_len]
+                .chunks(32)
+                .map(|field| field.try_into().unwrap())
+                .collect(),
+        })
+    }
+
+    /// Splits a proof with public inputs of the RSA membership circuit of `key`.
+    pub fn from_rsa_jwt_proof(proof: &[u8], key: &RsaJwtKey) -> Result<Self> {
+        SolidityProof::from_honk_proof(proof, rsa_jwt_public_inputs(key))
+    }
+
+    /// Splits a proof with public inputs of the ES256 membership circuit.
+    pub fn from_es256_jwt_proof(proof: &[u8]) -> Result<Self> {
+        SolidityProof::from_honk_proof(proof, ES256_PUBLIC_INPUTS)
+    }
+
+    /// Rebuilds the public inputs of a JWT proof without public inputs, as in
+    /// `verify_jwt_proof`, followed by the `nullifier` returned by the circuit.
+    pub fn from_jwt_public_inputs(
+        proof: Vec<u8>,
+        public_inputs: &JwtPublicInputs,
+        nullifier: &BigUint,
+    ) -> Result<Self> {
+        let jwt_pubkey = pubkey_modulus_from_jwk(&public_inputs.google_jwt_pubkey_modulus)
+            .map_err(|e| anyhow!("Invalid JWT pubkey modulus: {}", e))?;
+        let mut fields = modulus_public_inputs(&jwt_pubkey);
+        fields.extend(claim_public_inputs(public_inputs));
+        fields.push(format!("0x{:0>64x}", nullifier));
+
+        Ok(SolidityProof {
+            proof,
+            public_inputs: flatten_fields_as_array(&fields)
+                .chunks(32)
+                .map(|field| field.try_into().unwrap())
+                .collect(),
+        })
+    }
+
+    /// The proof with public inputs that `verify_ultra_honk` expects.
+    pub fn to_honk_proof(&self) -> Vec<u8> {
+        reconstruct_honk_proof(&self.public_inputs.concat(), &self.proof, 32)
+    }
+
+    /// The nullifier returned by the membership circuits, their last public input.
+    pub fn nullifier(&self) -> Option<[u8; 32]> {
+        self.public_inputs.last().copied()
+    }
+
+    /// Public inputs as `0x`-prefixed hex words.
+    pub fn public_inputs_hex(&self) -> Vec<String> {
+        self.public_inputs
+            .iter()
+            .map(|field| format!("0x{}", hex::encode(field)))
+            .collect()
+    }
+
+    /// ABI encoding of the `(bytes proof, bytes32[] publicInputs)` arguments.
+    pub fn abi_encode(&self) -> Vec<u8> {
+        let padded_proof_len = self.proof.len().div_ceil(32) * 32;
+        let mut encoded =
+            Vec::with_capacity(5 * 32 + padded_proof_len + self.public_inputs.len() * 32);
+
+        // Heads: offsets of the proof and the public inputs
+        encoded.extend(abi_word(64));
+        encoded.extend(abi_word(64 + 32 + padded_proof_len));
+        // Tails: length-prefixed proof padded to a word, then the public inputs
+        encoded.extend(abi_word(self.proof.len()));
+        encoded.extend_from_slice(&self.proof);
+        encoded.resize(encoded.len() + padded_proof_len - self.proof.len(), 0);
+        encoded.extend(abi_word(self.public_inputs.len()));
+        for field in &self.public_inputs {
+            encoded.extend_from_slice(field);
+        }
+
+        encoded
+    }
+
+    /// Calldata of a call of the function with `signature`, such as `HONK_VERIFY_SIGNATURE`
+    /// or `VERIFY_ZK_JWT_PROOF_SIGNATURE`, with the proof and public inputs as arguments.
+    pub fn calldata(&self, signature: &str) -> Vec<u8> {
+        let mut calldata = function_selector(signature).to_vec();
+        calldata.extend(self.abi_encode());
+        calldata
+    }
+}
+
+/// First 4 bytes of the Keccak-256 hash of a Solidity function signature.
+pub fn function_selector(signature: &str) -> [u8; 4] {
+    let hash = Keccak256::digest(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+fn abi_word(value: usize) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    BigEndian::write_u64(&mut word[24..], value as u64);
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proof::jwt_proof::{JwtHashAlgorithm, GOOGLE_ISSUERS};
+
+    fn field(value: u8) -> String {
+        format!("0x{:0>64x}", value)
+    }
+
+    fn honk_proof(public_inputs: usize, proof_fields: usize) -> Vec<u8> {
+        let fields: Vec<String> = (0..public_inputs).map(|i| field(i as u8)).collect();
+        let proof = vec![0xab; proof_fields * 32];
+        reconstruct_honk_proof(&flatten_fields_as_array(&fields), &proof, 32)
+    }
+
+    #[test]
+    fn test_from_honk_proof_splits_public_inputs() {
+        let proof = honk_proof(3, 5);
+        let solidity_proof = SolidityProof::from_honk_proof(&proof, 3).unwrap();
+
+        assert_eq!(solidity_proof.proof, vec![0xab; 5 * 32]);
+        assert_eq!(solidity_proof.public_inputs.len(), 3);
+        assert_eq!(solidity_proof.public_inputs[2][31], 2);
+        assert_eq!(solidity_proof.nullifier().unwrap()[31], 2);
+        assert_eq!(solidity_proof.public_inputs_hex()[1], field(1));
+        assert_eq!(solidity_proof.to_honk_proof(), proof);
+    }
+
+    #[test]
+    fn test_from_honk_proof_rejects_malformed_proofs() {
+        let proof = honk_proof(3, 5);
+
+        assert!(SolidityProof::from_honk_proof(&proof, 9).is_err());
+        assert!(SolidityProof::from_honk_proof(&proof[..proof.len() - 1], 3).is_err());
+
+        let mut wrong_header = proof.clone();
+        wrong_header[3] += 1;
+        assert!(SolidityProof::from_honk_proof(&wrong_header, 3).is_err());
+    }
+
+    #[test]
+    fn test_rsa_jwt_public_inputs() {
+        let key = RsaJwtKey {
+            hash: JwtHashAlgorithm::Sha256,
+            modulus_bits: 2048,
+        };
+        assert_eq!(rsa_jwt_public_inputs(&key), 18 + 65 + 2 + 129 + 129 + 2);
+
+        let proof = honk_proof(rsa_jwt_public_inputs(&key), 4);
+        let solidity_proof = SolidityProof::from_rsa_jwt_proof(&proof, &key).unwrap();
+        assert_eq!(solidity_proof.proof.len(), 4 * 32);
+        assert!(SolidityProof::from_es256_jwt_proof(&proof).is_err());
+    }
+
+    #[test]
+    fn test_from_jwt_public_inputs_appends_the_nullifier() {
+        let public_inputs = JwtPublicInputs {
+            domain: "pse.dev".to_string(),
+            google_jwt_pubkey_modulus: "03Cww27F2O7JxB5Ji9iT9szfKZ4MK-iPzVpQkdLjCuGKfpjaCVAz9zIQ0-7gbZ-8cJRaSLfByWTGMIHRYiX2efdjz1Z9jck0DK9W3mapFrBPvM7AlRni4lPlwUigDd8zxAMDCheqyK3vCOLFW-1xYHt_YGwv8b0dP7rjujarEYlWjeppO_QMNtXdKdT9eZtBEcj_9ms9W0aLdCFNR5AAR3y0kLkKR1H4DW7vncB46rqCJLenhlCbcW0MZ3asqcjqBQ2t9QMRnY83Zf_pNEsCcXlKp4uOQqEvzjAc9ZSr2sOmd_ESZ_3jMlNkCZ4J41TuG-My5illFcW5LajSKvxD3w".to_string(),
+            ephemeral_pubkey: "1".to_string(),
+            ephemeral_pubkey_expiry: "2025-05-07T09:07:57.379Z".to_string(),
+            iss: GOOGLE_ISSUERS[0].to_string(),
+            aud: "client-id".to_string(),
+            iat: 1746004080,
+        };
+        let solidity_proof = SolidityProof::from_jwt_public_inputs(
+            vec![0xab; 64],
+            &public_inputs,
+            &BigUint::from(42u8),
+        )
+        .unwrap();
+
+        let key = RsaJwtKey {
+            hash: JwtHashAlgorithm::Sha256,
+            modulus_bits: 2048,
+        };
+        assert_eq!(
+            solidity_proof.public_inputs.len(),
+            rsa_jwt_public_inputs(&key)
+        );
+        assert_eq!(solidity_proof.nullifier().unwrap()[31], 42);
+        // Domain bytes follow the 18 modulus limbs
+        assert_eq!(solidity_proof.public_inputs[18][31], b'p');
+        assert_eq!(
+            SolidityProof::from_rsa_jwt_proof(&solidity_proof.to_honk_proof(), &key).unwrap(),
+            solidity_proof
+        );
+    }
+
+    #[test]
+    fn test_function_selector() {
+        assert_eq!(
+            hex::encode(function_selector("transfer(address,uint256)")),
+            "a9059cbb"
+        );
+    }
+
+    #[test]
+    fn test_abi_encode() {
+        let solidity_proof = SolidityProof {
+            proof: vec![0xab; 33],
+            public_inputs: vec![[1u8; 32], [2u8; 32]],
+        };
+        let encoded = solidity_proof.abi_encode();
+
+        let words: Vec<&[u8]> = encoded.chunks(32).collect();
+        assert_eq!(words.len(), 2 + 1 + 2 + 1 + 2);
+        assert_eq!(words[0], abi_word(64));
+        assert_eq!(words[1], abi_word(64 + 32 + 64));
+        assert_eq!(words[2], abi_word(33));
+        assert_eq!(words[3], [0xab; 32]);
+        assert_eq!(&words[4][..1], [0xab]);
+        assert_eq!(&words[4][1..], [0u8; 31]);
+        assert_eq!(words[5], abi_word(2));
+        assert_eq!(words[6], [1u8; 32]);
+        assert_eq!(words[7], [2u8; 32]);
+
+        let calldata = solidity_proof.calldata(HONK_VERIFY_SIGNATURE);
+        assert_eq!(calldata[..4], function_selector(HONK_VERIFY_SIGNATURE));
+        assert_eq!(calldata[4..], encoded);
+    }
+}
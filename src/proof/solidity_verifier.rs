@@ -0,0 +1,305 @@
+use std::{collections::BTreeMap, process::Command};
+
+use anyhow::{anyhow, bail, Result};
+use noir::barretenberg::srs::setup_srs_from_bytecode;
+
+use super::jwt_proof::{load_bytecode, ProofTranscript};
+
+/// Precomputed commitments of an UltraHonk verification key, in the order bb serializes
+/// them, by their name in the `HonkVerificationKey` library of the generated verifier.
+pub const VK_COMMITMENTS: [&str; 27] = [
+    "qm",
+    "qc",
+    "ql",
+    "qr",
+    "qo",
+    "q4",
+    "qLookup",
+    "qArith",
+    "qDeltaRange",
+    "qElliptic",
+    "qAux",
+    "qPoseidon2External",
+    "qPoseidon2Internal",
+    "s1",
+    "s2",
+    "s3",
+    "s4",
+    "id1",
+    "id2",
+    "id3",
+    "id4",
+    "t1",
+    "t2",
+    "t3",
+    "t4",
+    "lagrangeFirst",
+    "lagrangeLast",
+];
+
+/// Affine point of a verification key commitment, with big-endian coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct G1Commitment {
+    pub x: [u8; 32],
+    pub y: [u8; 32],
+}
+
+/// Verification key embedded in a Solidity verifier generated by `bb write_solidity_verifier`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolidityVerificationKey {
+    pub circuit_size: u64,
+    pub log_circuit_size: u64,
+    /// Public inputs of the circuit, including the pairing point object appended by bb
+    pub public_inputs_size: u64,
+    pub commitments: BTreeMap<String, G1Commitment>,
+}
+
+impl SolidityVerificationKey {
+    /// Reads the verification key of the `HonkVerificationKey` library of a verifier.
+    pub fn parse(source: &str) -> Result<Self> {
+        let source = &source[source
+            .find("function loadVerificationKey()")
+            .ok_or_else(|| anyhow!("Verifier has no loadVerificationKey function"))?..];
+
+        let mut commitments = BTreeMap::new();
+        for name in VK_COMMITMENTS {
+            let point = &source[source
+                .find(&format!("{}: Honk.G1Point(", name))
+                .ok_or_else(|| anyhow!("Verifier has no {} commitment", name))?..];
+            commitments.insert(
+                name.to_string(),
+                G1Commitment {
+                    x: uint256_bytes(uint256_field(point, "x")?)?,
+                    y: uint256_bytes(uint256_field(point, "y")?)?,
+                },
+            );
+        }
+
+        Ok(SolidityVerificationKey {
+            circuit_size: uint256_field(source, "circuitSize")?.parse()?,
+            log_circuit_size: uint256_field(source, "logCircuitSize")?.parse()?,
+            public_inputs_size: uint256_field(source, "publicInputsSize")?.parse()?,
+            commitments,
+        })
+    }
+
+    /// Names of the commitments of a serialized verification key that differ from the ones
+    /// of this key.
+    pub fn drift(&self, vk: &[u8]) -> Result<Vec<String>> {
+        let commitments = honk_vk_commitments(vk)?;
+        Ok(VK_COMMITMENTS
+            .iter()
+            .filter(|name| self.commitments.get(**name) != commitments.get(**name))
+            .map(|name| name.to_string())
+            .collect())
+    }
+}
+
+/// Value of `label: uint256(value)`, the first after the start of `source`.
+fn uint256_field<'a>(source: &'a str, label: &str) -> Result<&'a str> {
+    let prefix = format!("{}: uint256(", label);
+    let start = source
+        .find(&prefix)
+        .ok_or_else(|| anyhow!("Verifier has no {} value", label))?
+        + prefix.len();
+    let len = source[start..]
+        .find(')')
+        .ok_or_else(|| anyhow!("Unterminated {} value", label))?;
+    Ok(source[start..start + len].trim())
+}
+
+fn uint256_bytes(value: &str) -> Result<[u8; 32]> {
+    let digits = value
+        .strip_prefix("0x")
+        .ok_or_else(|| anyhow!("Expected a hex uint256, got {}", value))?;
+    let bytes = hex::decode(format!("{:0>64}", digits))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("uint256 {} is longer than 32 bytes", value))
+}
+
+/// Commitments of a serialized UltraHonk verification key. bb serializes them last, as affine
+/// points with 32-byte big-endian coordinates, after a header that varies across versions.
+pub fn honk_vk_commitments(vk: &[u8]) -> Result<BTreeMap<String, G1Commitment>> {
+    let commitments_len = VK_COMMITMENTS.len() * 64;
+    if vk.len() < commitments_len {
+        bail!(
+            "Verification key of {} bytes is too short for {} commitments",
+            vk.len(),
+            VK_COMMITMENTS.len()
+        );
+    }
+
+    Ok(VK_COMMITMENTS
+        .iter()
+        .zip(vk[vk.len() - commitments_len..].chunks(64))
+        .map(|(name, point)| {
+            (
+                name.to_string(),
+                G1Commitment {
+                    x: point[..32].try_into().unwrap(),
+                    y: point[32..].try_into().unwrap(),
+                },
+            )
+        })
+        .collect())
+}
+
+/// Checks that the verification key of the Solidity verifier at `verifier_path` is the one
+/// of the circuit at `circuit_path`, and fails with the differing commitments otherwise.
+pub fn check_solidity_verifier(
+    srs_path: Option<&str>,
+    circuit_path: &str,
+    verifier_path: &str,
+) -> Result<()> {
+    let verifier_vk = SolidityVerificationKey::parse(&std::fs::read_to_string(verifier_path)?)?;
+
+    let bytecode = load_bytecode(circuit_path)?;
+    setup_srs_from_bytecode(&bytecode, srs_path, false).map_err(|e| anyhow!(e))?;
+    let vk = ProofTranscript::Keccak.verification_key(&bytecode)?;
+
+    let drift = verifier_vk.drift(&vk)?;
+    if !drift.is_empty() {
+        bail!(
+            "Verifier {} does not match circuit {}: commitments {} differ. Regenerate it with `cargo run --bin solidity_verifier -- generate {} {}`",
+            verifier_path,
+            circuit_path,
+            drift.join(", "),
+            circuit_path,
+            verifier_path
+        );
+    }
+    Ok(())
+}
+
+/// Generates the Solidity verifier of the circuit at `circuit_path` with the `bb` binary at
+/// `bb_path`, as `circuit/zkjwt_circuit/build.sh` does, and checks its verification key.
+pub fn generate_solidity_verifier(
+    bb_path: &str,
+    srs_path: Option<&str>,
+    circuit_path: &str,
+    verifier_path: &str,
+) -> Result<()> {
+    let vk_dir = std::env::temp_dir().join(format!("honk-vk-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&vk_dir)?;
+
+    let vk_path = vk_dir.join("vk");
+    let result = run_bb(
+        bb_path,
+        &[
+            "write_vk",
+            "-b",
+            circuit_path,
+            "-o",
+            &vk_dir.to_string_lossy(),
+            "--oracle_hash",
+            "keccak",
+        ],
+    )
+    .and_then(|_| {
+        run_bb(
+            bb_path,
+            &[
+                "write_solidity_verifier",
+                "-k",
+                &vk_path.to_string_lossy(),
+                "-o",
+                verifier_path,
+            ],
+        )
+    });
+    let _ = std::fs::remove_dir_all(&vk_dir);
+    result?;
+
+    check_solidity_verifier(srs_path, circuit_path, verifier_path)
+}
+
+fn run_bb(bb_path: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(bb_path)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("Failed to run {}: {}", bb_path, e))?;
+    if !output.status.success() {
+        bail!(
+            "{} {} failed: {}",
+            bb_path,
+            args[0],
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HONK_VERIFIER_PATH: &str = "contracts/src/circuits/zk-jwt/honk-verifier/honk_vk.sol";
+
+    fn serialized_vk(verifier_vk: &SolidityVerificationKey) -> Vec<u8> {
+        // Header of circuit size, log circuit size, public inputs size and offset
+        let mut vk = Vec::new();
+        for value in [
+            verifier_vk.circuit_size,
+            verifier_vk.log_circuit_size,
+            verifier_vk.public_inputs_size,
+            1,
+        ] {
+            vk.extend(value.to_be_bytes());
+        }
+        for name in VK_COMMITMENTS {
+            let point = &verifier_vk.commitments[name];
+            vk.extend(point.x);
+            vk.extend(point.y);
+        }
+        vk
+    }
+
+    #[test]
+    fn test_parse_honk_verifier() {
+        let verifier_vk =
+            SolidityVerificationKey::parse(&std::fs::read_to_string(HONK_VERIFIER_PATH).unwrap())
+                .unwrap();
+
+        assert_eq!(verifier_vk.circuit_size, 262144);
+        assert_eq!(verifier_vk.log_circuit_size, 18);
+        assert_eq!(verifier_vk.public_inputs_size, 102);
+        assert_eq!(verifier_vk.commitments.len(), VK_COMMITMENTS.len());
+        assert_eq!(
+            hex::encode(verifier_vk.commitments["ql"].x),
+            "078d68458e302622b3cc35787697ca5a5d57386aea392189a6eecd314f78adac"
+        );
+    }
+
+    #[test]
+    fn test_drift_names_the_differing_commitments() {
+        let verifier_vk =
+            SolidityVerificationKey::parse(&std::fs::read_to_string(HONK_VERIFIER_PATH).unwrap())
+                .unwrap();
+        let mut vk = serialized_vk(&verifier_vk);
+        assert!(verifier_vk.drift(&vk).unwrap().is_empty());
+
+        // Commitments are read from the end, whatever the header
+        let mut longer_header = vec![0u8; 4];
+        longer_header.extend(&vk);
+        assert!(verifier_vk.drift(&longer_header).unwrap().is_empty());
+
+        // Flip a byte of the y coordinate of the last commitment and of the x of the first
+        let len = vk.len();
+        vk[len - 1] ^= 1;
+        vk[32] ^= 1;
+        assert_eq!(
+            verifier_vk.drift(&vk).unwrap(),
+            vec!["qm".to_string(), "lagrangeLast".to_string()]
+        );
+
+        assert!(verifier_vk.drift(&vk[..100]).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_other_contracts() {
+        assert!(SolidityVerificationKey::parse("contract Empty {}").is_err());
+        assert!(uint256_bytes(&format!("0x{}", "1".repeat(65))).is_err());
+        assert_eq!(uint256_bytes("0x01").unwrap()[31], 1);
+    }
+}
@@ -0,0 +1,691 @@
+use std::{collections::HashMap, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use noir::barretenberg::srs::setup_srs_from_bytecode;
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+use super::{
+    dkim::{
+        from_address_sequence, header_field_sequence, parse_dkim_signed_headers, DkimSignature,
+        HeaderSequence,
+    },
+    ephemeral_commitment::{field_to_biguint, EphemeralPubkeyCommitment},
+    group_nullifier::group_id_hash,
+    jwt_proof::{
+        prove_circuit_with_witness_keys, split_biguint, witness_values, EphemeralExpiryPolicy,
+        ProofTranscript, StorageBlock, RSA_LIMB_BITS,
+    },
+    poseidon2::Poseidon2,
+};
+
+/// Header-only zkemail circuit of `prove_zkemail`, which verifies the DKIM signature of the
+/// header and returns the `From` address, `Date` and `Subject` fields.
+const ZKEMAIL_JSON: &str = include_str!("../../circuit/zkemail_test.json");
+
+pub const MAX_EMAIL_HEADER_LENGTH: usize = 576;
+pub const MAX_EMAIL_ADDRESS_LENGTH: usize = 320;
+pub const MAX_DATE_LENGTH: usize = 36;
+pub const MAX_SUBJECT_LENGTH: usize = 100;
+/// Index in the `date:` field of the byte the circuit reads the date length from: a space
+/// for a single-digit day.
+const DATE_LENGTH_DETERMINATION_INDEX: usize = 11;
+/// The circuit verifies 2048-bit DKIM keys (`KEY_LIMBS_2048`).
+pub const DKIM_MODULUS_BITS: usize = 2048;
+
+/// Order of the witness values of the zkemail circuit, based on the ZkEmailInput struct.
+pub const ZKEMAIL_WITNESS_KEYS: [&str; 12] = [
+    "header_storage",
+    "header_len",
+    "pubkey_modulus",
+    "pubkey_redc",
+    "signature",
+    "date_index",
+    "subject_index",
+    "subject_length",
+    "from_header_index",
+    "from_header_length",
+    "from_address_index",
+    "from_address_length",
+];
+
+/// Domain of the sender nullifier.
+pub const SENDER_NULLIFIER_DOMAIN: &str = "email_sender_nullifier";
+
+/// DER prefix of a SHA-256 `DigestInfo` in a PKCS#1 v1.5 signature.
+const SHA256_DIGEST_INFO_PREFIX: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+
+/// Inputs of the zkemail circuit for a DKIM-signed email.
+#[derive(Debug, Clone)]
+pub struct ZkEmailInputs {
+    /// DKIM-signed header, zero-padded to `MAX_EMAIL_HEADER_LENGTH`
+    pub header: StorageBlock,
+    pub pubkey_modulus_limbs: Vec<String>,
+    pub pubkey_redc_limbs: Vec<String>,
+    pub signature_limbs: Vec<String>,
+    pub date_index: usize,
+    pub subject_sequence: HeaderSequence,
+    pub from_header_sequence: HeaderSequence,
+    pub from_address_sequence: HeaderSequence,
+}
+
+impl ZkEmailInputs {
+    /// Reads the inputs of a raw email signed by the DKIM key of `dkim_pubkey_modulus`, the
+    /// modulus of the `p=` key of the `<selector>._domainkey.<domain>` DNS record. Of the DKIM
+    /// signatures of the email, the first one of this key is proven.
+    pub fn from_eml(eml: &str, dkim_pubkey_modulus: &BigUint) -> Result<Self> {
+        if dkim_pubkey_modulus.bits() as usize != DKIM_MODULUS_BITS {
+            bail!(
+                "Unsupported DKIM key of {} bits, the circuit verifies {}-bit keys",
+                dkim_pubkey_modulus.bits(),
+                DKIM_MODULUS_BITS
+            );
+        }
+
+        let mut errors = Vec::new();
+        for (dkim_signature, header) in parse_dkim_signed_headers(eml)? {
+            match verify_dkim_signature(&dkim_signature, &header, dkim_pubkey_modulus) {
+                Ok(signature) => {
+                    return Self::from_signed_header(header, &signature, dkim_pubkey_modulus)
+                }
+                Err(e) => errors.push(format!(
+                    "DKIM signature of {} (selector {}) {}",
+                    dkim_signature.domain, dkim_signature.selector, e
+                )),
+            }
+        }
+        bail!("{}", errors.join("; "))
+    }
+
+    fn from_signed_header(
+        header: Vec<u8>,
+        signature: &BigUint,
+        dkim_pubkey_modulus: &BigUint,
+    ) -> Result<Self> {
+        let date = header_field_sequence(&header, "date")?;
+        let date_length = match header.get(date.index + DATE_LENGTH_DETERMINATION_INDEX) {
+            Some(b' ') => MAX_DATE_LENGTH - 1,
+            _ => MAX_DATE_LENGTH,
+        };
+        if date.length != date_length {
+            bail!("Date field is not a date the circuit reads");
+        }
+        let subject_sequence = header_field_sequence(&header, "subject")?;
+        if subject_sequence.length > MAX_SUBJECT_LENGTH {
+            bail!("Subject field is longer than {} bytes", MAX_SUBJECT_LENGTH);
+        }
+        let from_header_sequence = header_field_sequence(&header, "from")?;
+        let from_address_sequence = from_address_sequence(&header, from_header_sequence)?;
+        if from_address_sequence.length > MAX_EMAIL_ADDRESS_LENGTH {
+            bail!(
+                "From address is longer than {} bytes",
+                MAX_EMAIL_ADDRESS_LENGTH
+            );
+        }
+
+        let limbs = DKIM_MODULUS_BITS.div_ceil(RSA_LIMB_BITS);
+        let redc = (BigUint::from(1u64) << (2 * DKIM_MODULUS_BITS + 4)) / dkim_pubkey_modulus;
+        let len = header.len();
+        let mut storage = header;
+        storage.resize(MAX_EMAIL_HEADER_LENGTH, 0);
+
+        Ok(ZkEmailInputs {
+            header: StorageBlock { storage, len },
+            pubkey_modulus_limbs: split_biguint(dkim_pubkey_modulus, RSA_LIMB_BITS, limbs),
+            pubkey_redc_limbs: split_biguint(&redc, RSA_LIMB_BITS, limbs),
+            signature_limbs: split_biguint(signature, RSA_LIMB_BITS, limbs),
+            date_index: date.index,
+            subject_sequence,
+            from_header_sequence,
+            from_address_sequence,
+        })
+    }
+
+    /// Value of the signed `subject:` field.
+    pub fn subject(&self) -> String {
+        let start = self.subject_sequence.index + "subject:".len();
+        let end = self.subject_sequence.index + self.subject_sequence.length;
+        String::from_utf8_lossy(&self.header.storage[start..end]).to_string()
+    }
+
+    /// The inputs map of `prove_zkemail`.
+    pub fn to_circuit_inputs(&self) -> HashMap<String, Vec<String>> {
+        let mut inputs = HashMap::new();
+        inputs.insert(
+            "header_storage".to_string(),
+            self.header.storage.iter().map(|b| b.to_string()).collect(),
+        );
+        inputs.insert("header_len".to_string(), vec![self.header.len.to_string()]);
+        inputs.insert(
+            "pubkey_modulus".to_string(),
+            self.pubkey_modulus_limbs.clone(),
+        );
+        inputs.insert("pubkey_redc".to_string(), self.pubkey_redc_limbs.clone());
+        inputs.insert("signature".to_string(), self.signature_limbs.clone());
+        for (key, value) in [
+            ("date_index", self.date_index),
+            ("subject_index", self.subject_sequence.index),
+            ("subject_length", self.subject_sequence.length),
+            ("from_header_index", self.from_header_sequence.index),
+            ("from_header_length", self.from_header_sequence.length),
+            ("from_address_index", self.from_address_sequence.index),
+            ("from_address_length", self.from_address_sequence.length),
+        ] {
+            inputs.insert(key.to_string(), vec![value.to_string()]);
+        }
+        inputs
+    }
+}
+
+/// Checks that `dkim_signature` of `header` is one the circuit verifies with the key, and
+/// returns its RSA signature.
+fn verify_dkim_signature(
+    dkim_signature: &DkimSignature,
+    header: &[u8],
+    dkim_pubkey_modulus: &BigUint,
+) -> Result<BigUint> {
+    if dkim_signature.algorithm != "rsa-sha256" {
+        bail!(
+            "uses the unsupported algorithm {}",
+            dkim_signature.algorithm
+        );
+    }
+    if header.len() > MAX_EMAIL_HEADER_LENGTH {
+        bail!(
+            "signs a header of {} bytes, longer than {} bytes",
+            header.len(),
+            MAX_EMAIL_HEADER_LENGTH
+        );
+    }
+    let signature = BigUint::from_bytes_be(&dkim_signature.signature);
+    verify_rsa_sha256(header, &signature, dkim_pubkey_modulus)
+        .map_err(|e| anyhow!("does not verify: {}", e))?;
+    Ok(signature)
+}
+
+/// Checks a PKCS#1 v1.5 RSA signature with SHA-256 and the exponent 65537, as the circuit does.
+fn verify_rsa_sha256(data: &[u8], signature: &BigUint, modulus: &BigUint) -> Result<()> {
+    if signature >= modulus {
+        bail!("signature is not smaller than the modulus");
+    }
+    let key_len = (modulus.bits() as usize).div_ceil(8);
+    let encoded = signature
+        .modpow(&BigUint::from(65537u32), modulus)
+        .to_bytes_be();
+
+    let mut expected = vec![0x01];
+    expected.resize(key_len - 1 - 1 - SHA256_DIGEST_INFO_PREFIX.len() - 32, 0xff);
+    expected.push(0x00);
+    expected.extend(SHA256_DIGEST_INFO_PREFIX);
+    expected.extend(Sha256::digest(data));
+    // `to_bytes_be` drops the leading zero byte
+    if encoded != expected {
+        bail!("invalid signature for this key and header");
+    }
+    Ok(())
+}
+
+/// Public outputs of the zkemail circuit, read from the public inputs of a proof.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZkEmailPublicOutputs {
+    /// Pedersen hash of the DKIM key limbs, `RSAPubkey::hash` in zkemail.nr
+    pub dkim_pubkey_hash: BigUint,
+    /// Pedersen hash of the signature, the same for every proof of the email but another for
+    /// each email of the sender; members register with `sender_nullifier` instead
+    pub email_nullifier: BigUint,
+    pub from_address: String,
+    pub date: String,
+    pub subject: String,
+}
+
+impl ZkEmailPublicOutputs {
+    /// Number of public inputs: the two hashes, then the address, date and subject as
+    /// `BoundedVec`s of one byte per field followed by their length.
+    pub const FIELDS: usize = 2
+        + (MAX_EMAIL_ADDRESS_LENGTH + 1)
+        + (MAX_DATE_LENGTH + 1 + 1)
+        + (MAX_SUBJECT_LENGTH + 1 + 1);
+
+    pub fn from_proof(proof: &[u8]) -> Result<Self> {
+        if proof.len() < 4 + Self::FIELDS * 32 {
+            bail!("Proof is too short for the zkemail public outputs");
+        }
+        let fields: Vec<&[u8]> = proof[4..4 + Self::FIELDS * 32].chunks(32).collect();
+
+        let mut offset = 2;
+        let mut bounded_vec = |max_len: usize| -> Result<String> {
+            let storage = &fields[offset..offset + max_len];
+            let len = BigUint::from_bytes_be(fields[offset + max_len]);
+            offset += max_len + 1;
+            let len = usize::try_from(len)
+                .ok()
+                .filter(|len| *len <= max_len)
+                .ok_or_else(|| anyhow!("Invalid length in the zkemail public outputs"))?;
+            let bytes: Vec<u8> = storage[..len].iter().map(|field| field[31]).collect();
+            String::from_utf8(bytes).map_err(|e| anyhow!("Invalid zkemail public output: {}", e))
+        };
+        let from_address = bounded_vec(MAX_EMAIL_ADDRESS_LENGTH)?;
+        let date = bounded_vec(MAX_DATE_LENGTH + 1)?;
+        let subject = bounded_vec(MAX_SUBJECT_LENGTH + 1)?;
+
+        Ok(ZkEmailPublicOutputs {
+            dkim_pubkey_hash: BigUint::from_bytes_be(fields[0]),
+            email_nullifier: BigUint::from_bytes_be(fields[1]),
+            from_address,
+            date,
+            subject,
+        })
+    }
+
+    /// Domain of the `From` address, in lowercase.
+    pub fn from_domain(&self) -> Result<String> {
+        self.from_address
+            .rsplit_once('@')
+            .map(|(_, domain)| domain.to_lowercase())
+            .ok_or_else(|| anyhow!("From address has no domain"))
+    }
+
+    /// Nullifier of the sender in the group of its domain: the hash of the lowercased `From`
+    /// address and the group id, like the `sub` nullifier of a JWT. Every email of the address
+    /// gives the same one, so an address registers once per group.
+    pub fn sender_nullifier(&self) -> Result<BigUint> {
+        let address = self.from_address.to_lowercase();
+        Ok(field_to_biguint(Poseidon2::hash_with_domain(
+            SENDER_NULLIFIER_DOMAIN,
+            &[
+                Poseidon2::hash_bytes(address.as_bytes()),
+                group_id_hash(&self.from_domain()?)?,
+            ],
+        )))
+    }
+}
+
+fn zkemail_bytecode() -> Result<String> {
+    let bytecode_json: serde_json::Value = serde_json::from_str(ZKEMAIL_JSON)?;
+    match bytecode_json["bytecode"].as_str() {
+        Some(bytecode) => Ok(bytecode.to_string()),
+        None => bail!("No bytecode in the zkemail circuit artifact"),
+    }
+}
+
+/// Values of the witness map of the zkemail circuit, in the order of `ZKEMAIL_WITNESS_KEYS`.
+pub fn zkemail_witness_values(inputs: &HashMap<String, Vec<String>>) -> Result<Vec<String>> {
+    witness_values(inputs, &ZKEMAIL_WITNESS_KEYS)
+}
+
+/// Proves the zkemail circuit with the inputs of `ZkEmailInputs::to_circuit_inputs`. Without
+/// `srs_path`, the SRS is downloaded.
+pub fn generate_zkemail_proof(
+    srs_path: Option<&str>,
+    inputs: &HashMap<String, Vec<String>>,
+) -> Result<Vec<u8>> {
+    prove_circuit_with_witness_keys(
+        &zkemail_bytecode()?,
+        srs_path,
+        inputs,
+        &ZKEMAIL_WITNESS_KEYS,
+        ProofTranscript::Poseidon2,
+    )
+}
+
+/// Verifies a proof of the zkemail circuit. Without `srs_path`, the SRS is downloaded.
+pub fn verify_zkemail_proof(srs_path: Option<&str>, proof: Vec<u8>) -> Result<bool> {
+    let bytecode = zkemail_bytecode()?;
+    setup_srs_from_bytecode(&bytecode, srs_path, false).map_err(|e| anyhow!(e))?;
+    let transcript = ProofTranscript::Poseidon2;
+    let vk = transcript.verification_key(&bytecode)?;
+    transcript.verify(proof, vk)
+}
+
+/// Parses a DKIM key hash given as `0x` hex or decimal.
+pub fn parse_dkim_pubkey_hash(value: &str) -> Result<BigUint> {
+    match value.strip_prefix("0x") {
+        Some(digits) => BigUint::parse_bytes(digits.as_bytes(), 16),
+        None => BigUint::from_str(value).ok(),
+    }
+    .ok_or_else(|| anyhow!("Invalid DKIM pubkey hash: {}", value))
+}
+
+/// Verifies a zkemail proof of membership of `domain` for the ephemeral key of `commitment`:
+/// the email is from an address of `domain`, signed with one of the `trusted_dkim_pubkey_hashes`
+/// keys, and its subject is the nonce of the ephemeral key. The signed `Date` of the email
+/// stands for the JWT `iat` in `expiry_policy`: the key expires within its max lifetime of
+/// the email. Returns the public outputs, whose `sender_nullifier` identifies the sender in the
+/// group.
+///
+/// The circuit returns the sender address, so the verifier learns it.
+pub fn verify_email_membership_proof(
+    srs_path: Option<&str>,
+    proof: Vec<u8>,
+    domain: &str,
+    commitment: &EphemeralPubkeyCommitment,
+    trusted_dkim_pubkey_hashes: &[String],
+    expiry_policy: &EphemeralExpiryPolicy,
+    now: DateTime<Utc>,
+) -> Result<ZkEmailPublicOutputs> {
+    let outputs = ZkEmailPublicOutputs::from_proof(&proof)?;
+    if outputs.from_domain()? != domain.to_lowercase() {
+        bail!("Email is not from the domain {}", domain);
+    }
+    if outputs.subject.trim() != commitment.nonce_decimal() {
+        bail!("Email subject is not the nonce of the ephemeral key");
+    }
+
+    let mut trusted = false;
+    for hash in trusted_dkim_pubkey_hashes {
+        trusted |= parse_dkim_pubkey_hash(hash)? == outputs.dkim_pubkey_hash;
+    }
+    if !trusted {
+        bail!("Email is not signed with a trusted DKIM key of {}", domain);
+    }
+
+    let sent_at = DateTime::parse_from_rfc2822(outputs.date.trim())
+        .map_err(|e| anyhow!("Invalid email date {}: {}", outputs.date, e))?;
+    let expiry = DateTime::from_timestamp(i64::from(commitment.expiry), 0)
+        .ok_or_else(|| anyhow!("Invalid ephemeral key expiry"))?;
+    expiry_policy.check(expiry, sent_at.timestamp().max(0) as u64, now)?;
+
+    if !verify_zkemail_proof(srs_path, proof)? {
+        bail!("Invalid email proof");
+    }
+    Ok(outputs)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::proof::dkim::{parse_dkim_dns_record, tests::sample_dkim_record};
+    use acir::acir_field::FieldElement;
+
+    const SAMPLE_EML_PATH: &str = "public/zkemail_sample.eml";
+    const MULTISIG_EML_PATH: &str = "public/zkemail_sample_multisig.eml";
+
+    /// Modulus of the gmail.com `20230601` DKIM key, which signed the sample email.
+    pub(crate) fn sample_dkim_modulus() -> BigUint {
+        let input: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string("public/zkemail_input.json").unwrap())
+                .unwrap();
+        hex_limbs(&input["pubkey"]["modulus"])
+    }
+
+    fn hex_limbs(limbs: &serde_json::Value) -> BigUint {
+        limbs
+            .as_array()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .map(|(i, limb)| {
+                BigUint::parse_bytes(&limb.as_str().unwrap().as_bytes()[2..], 16).unwrap()
+                    << (i * RSA_LIMB_BITS)
+            })
+            .sum()
+    }
+
+    /// Public inputs of a zkemail proof with these outputs, and a dummy proof.
+    pub(crate) fn proof_with_outputs(
+        dkim_pubkey_hash: u64,
+        email_nullifier: u64,
+        from_address: &str,
+        subject: &str,
+    ) -> Vec<u8> {
+        let mut fields = vec![
+            BigUint::from(dkim_pubkey_hash),
+            BigUint::from(email_nullifier),
+        ];
+        for (value, max_len) in [
+            (from_address, MAX_EMAIL_ADDRESS_LENGTH),
+            ("Tue, 14 Jan 2025 23:00:42 -0700", MAX_DATE_LENGTH + 1),
+            (subject, MAX_SUBJECT_LENGTH + 1),
+        ] {
+            let mut storage: Vec<BigUint> = value.bytes().map(BigUint::from).collect();
+            storage.resize(max_len, BigUint::from(0u8));
+            fields.extend(storage);
+            fields.push(BigUint::from(value.len()));
+        }
+        let mut proof = ((fields.len() + 1) as u32).to_be_bytes().to_vec();
+        for field in fields {
+            let bytes = field.to_bytes_be();
+            proof.extend(vec![0u8; 32 - bytes.len()]);
+            proof.extend(bytes);
+        }
+        proof.extend([0u8; 32]);
+        proof
+    }
+
+    #[test]
+    fn test_inputs_from_eml() {
+        let eml = std::fs::read_to_string(SAMPLE_EML_PATH).unwrap();
+        let inputs = ZkEmailInputs::from_eml(&eml, &sample_dkim_modulus()).unwrap();
+
+        // Same inputs as the `public/zkemail_input.json` fixture
+        let fixture: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string("public/zkemail_input.json").unwrap())
+                .unwrap();
+        let fixture_len = fixture["header"]["len"].as_u64().unwrap() as usize;
+        let fixture_header: Vec<u8> = fixture["header"]["storage"].as_array().unwrap()
+            [..fixture_len]
+            .iter()
+            .map(|b| b.as_u64().unwrap() as u8)
+            .collect();
+        assert_eq!(inputs.header.len, fixture_len);
+        assert_eq!(inputs.header.storage[..fixture_len], fixture_header[..]);
+        assert_eq!(inputs.header.storage.len(), MAX_EMAIL_HEADER_LENGTH);
+        for (limbs, fixture_limbs) in [
+            (&inputs.pubkey_redc_limbs, &fixture["pubkey"]["redc"]),
+            (&inputs.signature_limbs, &fixture["signature"]),
+        ] {
+            let value: BigUint = limbs
+                .iter()
+                .enumerate()
+                .map(|(i, limb)| BigUint::from_str(limb).unwrap() << (i * RSA_LIMB_BITS))
+                .sum();
+            assert_eq!(value, hex_limbs(fixture_limbs));
+        }
+        assert_eq!(inputs.date_index, 133);
+        assert_eq!(
+            inputs.subject_sequence,
+            HeaderSequence {
+                index: 22,
+                length: 28
+            }
+        );
+        assert_eq!(
+            inputs.from_header_sequence,
+            HeaderSequence {
+                index: 171,
+                length: 44
+            }
+        );
+        assert_eq!(
+            inputs.from_address_sequence,
+            HeaderSequence {
+                index: 191,
+                length: 23
+            }
+        );
+        assert_eq!(inputs.subject(), "This is a test email");
+
+        let circuit_inputs = inputs.to_circuit_inputs();
+        assert_eq!(circuit_inputs.len(), ZKEMAIL_WITNESS_KEYS.len());
+        assert_eq!(circuit_inputs["pubkey_modulus"].len(), 18);
+        assert_eq!(circuit_inputs["from_address_length"], ["23"]);
+    }
+
+    #[test]
+    fn test_inputs_from_multisig_eml() {
+        let eml = std::fs::read_to_string(MULTISIG_EML_PATH).unwrap();
+        let example_org =
+            parse_dkim_dns_record(&sample_dkim_record("mail2025._domainkey.example.org")).unwrap();
+        let esp =
+            parse_dkim_dns_record(&sample_dkim_record("esp1._domainkey.esp.example.net")).unwrap();
+
+        // The key selects the signature; the `simple` header keeps `To:` as sent
+        let inputs = ZkEmailInputs::from_eml(&eml, &example_org).unwrap();
+        let header = &inputs.header.storage[..inputs.header.len];
+        assert!(header.starts_with(b"from:Alice Doe <alice@example.org>\r\nTo:   Bob"));
+        assert_eq!(inputs.subject(), "12345");
+        let address = inputs.from_address_sequence;
+        assert_eq!(
+            &header[address.index..address.index + address.length],
+            b"alice@example.org"
+        );
+        assert_eq!(&header[inputs.date_index..inputs.date_index + 5], b"date:");
+
+        let esp_inputs = ZkEmailInputs::from_eml(&eml, &esp).unwrap();
+        assert!(esp_inputs
+            .header
+            .storage
+            .starts_with(b"from:Alice Doe <alice@example.org>\r\nto:Bob"));
+        assert_ne!(esp_inputs.signature_limbs, inputs.signature_limbs);
+
+        let witness = zkemail_witness_values(&inputs.to_circuit_inputs()).unwrap();
+        assert_eq!(witness.len(), MAX_EMAIL_HEADER_LENGTH + 1 + 3 * 18 + 7);
+        assert_eq!(
+            witness[MAX_EMAIL_HEADER_LENGTH],
+            inputs.header.len.to_string()
+        );
+        assert_eq!(witness[witness.len() - 1], "17");
+        let mut missing = inputs.to_circuit_inputs();
+        missing.remove("date_index");
+        assert!(zkemail_witness_values(&missing).is_err());
+
+        // Neither signature is of the gmail.com key
+        let err = ZkEmailInputs::from_eml(&eml, &sample_dkim_modulus()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("esp.example.net (selector esp1) does not verify"));
+        assert!(err
+            .to_string()
+            .contains("example.org (selector mail2025) does not verify"));
+    }
+
+    #[test]
+    fn test_sample_dkim_record() {
+        let record = sample_dkim_record("20230601._domainkey.gmail.com");
+        assert_eq!(
+            parse_dkim_dns_record(&record).unwrap(),
+            sample_dkim_modulus()
+        );
+    }
+
+    #[test]
+    fn test_inputs_reject_other_keys_and_emails() {
+        let eml = std::fs::read_to_string(SAMPLE_EML_PATH).unwrap();
+        let modulus = sample_dkim_modulus();
+
+        let err = ZkEmailInputs::from_eml(&eml, &(&modulus - 2u8)).unwrap_err();
+        assert!(err.to_string().contains("does not verify"));
+        assert!(ZkEmailInputs::from_eml(&eml, &(&modulus >> 8u32)).is_err());
+
+        // A header changed after signing
+        let tampered = eml.replace(
+            "Subject: This is a test email",
+            "Subject: This is a test emaik",
+        );
+        assert!(ZkEmailInputs::from_eml(&tampered, &modulus).is_err());
+    }
+
+    #[test]
+    fn test_public_outputs() {
+        let proof = proof_with_outputs(7, 42, "me@Example.com", "123");
+        let outputs = ZkEmailPublicOutputs::from_proof(&proof).unwrap();
+        assert_eq!(outputs.dkim_pubkey_hash, BigUint::from(7u8));
+        assert_eq!(outputs.email_nullifier, BigUint::from(42u8));
+        assert_eq!(outputs.from_address, "me@Example.com");
+        assert_eq!(outputs.from_domain().unwrap(), "example.com");
+        assert_eq!(outputs.date, "Tue, 14 Jan 2025 23:00:42 -0700");
+        assert_eq!(outputs.subject, "123");
+
+        assert!(ZkEmailPublicOutputs::from_proof(&proof[..100]).is_err());
+    }
+
+    #[test]
+    fn test_sender_nullifier_is_per_address() {
+        let nullifier = |email_nullifier: u64, from_address: &str| {
+            ZkEmailPublicOutputs::from_proof(&proof_with_outputs(
+                7,
+                email_nullifier,
+                from_address,
+                "1",
+            ))
+            .unwrap()
+            .sender_nullifier()
+            .unwrap()
+        };
+
+        // Another email of the same address, whatever the case of the address
+        let sender = nullifier(42, "me@example.com");
+        assert_eq!(nullifier(43, "me@example.com"), sender);
+        assert_eq!(nullifier(44, "Me@Example.com"), sender);
+        assert_ne!(nullifier(42, "you@example.com"), sender);
+        assert_ne!(nullifier(42, "me@example.org"), sender);
+    }
+
+    #[test]
+    fn test_verify_email_membership_checks_outputs() {
+        let commitment = EphemeralPubkeyCommitment {
+            pubkey: FieldElement::from(1u64),
+            salt: FieldElement::from(2u64),
+            expiry: 1746090000,
+        };
+        let now: DateTime<Utc> = "2025-04-30T09:10:00Z".parse().unwrap();
+        let nonce = commitment.nonce_decimal();
+        let trusted = vec!["0x07".to_string()];
+        // The sample email is dated 2025-01-14, 107 days before the key expires
+        let policy = EphemeralExpiryPolicy {
+            max_lifetime_secs: 120 * 24 * 3600,
+        };
+        let verify_with = |proof: Vec<u8>, policy: &EphemeralExpiryPolicy| {
+            verify_email_membership_proof(
+                Some(""),
+                proof,
+                "example.com",
+                &commitment,
+                &trusted,
+                policy,
+                now,
+            )
+        };
+        let verify = |proof: Vec<u8>, domain: &str, now: DateTime<Utc>| {
+            verify_email_membership_proof(
+                Some(""),
+                proof,
+                domain,
+                &commitment,
+                &trusted,
+                &policy,
+                now,
+            )
+            .unwrap_err()
+            .to_string()
+        };
+
+        let proof = proof_with_outputs(7, 42, "me@example.com", &nonce);
+        assert!(verify(proof.clone(), "other.com", now).contains("not from the domain"));
+        assert!(verify(
+            proof.clone(),
+            "example.com",
+            "2025-06-01T00:00:00Z".parse().unwrap()
+        )
+        .contains("expired"));
+        let other_subject = proof_with_outputs(7, 42, "me@example.com", "1");
+        assert!(verify(other_subject, "example.com", now).contains("nonce"));
+        let untrusted = proof_with_outputs(8, 42, "me@example.com", &nonce);
+        assert!(verify(untrusted, "example.com", now).contains("trusted DKIM key"));
+        // A key living longer after the email than the policy allows
+        assert!(
+            verify_with(proof.clone(), &EphemeralExpiryPolicy::default())
+                .unwrap_err()
+                .to_string()
+                .contains("after the JWT was issued")
+        );
+        // Within the policy, the checks get to the proof
+        if let Err(e) = verify_with(proof, &policy) {
+            assert!(!e.to_string().contains("Ephemeral key"));
+        }
+    }
+}